@@ -0,0 +1,146 @@
+use crate::data::{IocEntryId, IocId, SearchType, AccountPredicate, Evidence};
+use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult};
+use crate::search_pattern::{SearchPattern, optional_match};
+use crate::unix_accounts::{AccountDatabase, PasswdEntry};
+
+pub struct AccountParameters {
+    pub ioc_id: IocId,
+    pub ioc_entry_id: IocEntryId,
+    pub search: SearchType,
+    pub name: Option<String>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub shell: Option<String>,
+    pub group: Option<String>,
+    pub sudo_rule: Option<String>,
+    pub predicate: Option<AccountPredicate>,
+}
+
+struct AccountParametersRegexed {
+    account_param: AccountParameters,
+    name: Option<SearchPattern>,
+    shell: Option<SearchPattern>,
+    group: Option<SearchPattern>,
+    sudo_rule: Option<SearchPattern>,
+}
+
+pub fn check_accounts(search_parameters: Vec<AccountParameters>) -> Vec<IocEntrySearchResult> {
+    if search_parameters.is_empty() {
+        return vec![];
+    }
+    info!("Account search: Searching IOCs using local account search.");
+    let search_parameters: Vec<AccountParametersRegexed> = search_parameters.into_iter()
+        .filter_map(compile_parameters)
+        .collect();
+
+    let database = AccountDatabase::load();
+    if database.shadow.is_empty() {
+        debug!("Account search: Shadow database is not readable, password predicates will not match");
+    }
+    let mut result: Vec<IocEntrySearchResult> = Vec::new();
+    for user in &database.users {
+        debug!("Account search: Checking account {}", user.name);
+        search_parameters.iter()
+            .filter(|sp| account_matches(sp, user, &database))
+            .for_each(|sp| {
+                let message = format!("Account search: Found account {} (uid {}, gid {}, shell {}) for IOC {}",
+                                      user.name,
+                                      user.uid,
+                                      user.gid,
+                                      user.shell,
+                                      sp.account_param.ioc_id
+                );
+                info!("{}", message);
+                result.push(IocEntrySearchResult {
+                    ioc_id: sp.account_param.ioc_id,
+                    ioc_entry_id: sp.account_param.ioc_entry_id,
+                    check: CheckKind::Account,
+                    description: message,
+                    timestamp: None,
+                    evidence: Evidence::Account {
+                        name: user.name.clone(),
+                        uid: user.uid,
+                        gid: user.gid,
+                        shell: user.shell.clone(),
+                    },
+                })
+            });
+    }
+    result
+}
+
+fn compile_parameters(sp: AccountParameters) -> Option<AccountParametersRegexed> {
+    if sp.name.is_none() && sp.uid.is_none() && sp.gid.is_none() && sp.shell.is_none()
+        && sp.group.is_none() && sp.sudo_rule.is_none() && sp.predicate.is_none() {
+        warn!("Account search: IOC {} specifies nothing to search for, skipping", sp.ioc_id);
+        return None;
+    }
+    let compile = |pattern: &Option<String>| pattern.as_ref()
+        .map(|it| SearchPattern::new(sp.search, it))
+        .transpose();
+    let compiled = (compile(&sp.name), compile(&sp.shell), compile(&sp.group), compile(&sp.sudo_rule));
+    match compiled {
+        (Ok(name), Ok(shell), Ok(group), Ok(sudo_rule)) =>
+            Some(AccountParametersRegexed { account_param: sp, name, shell, group, sudo_rule }),
+        (Err(err), _, _, _) | (_, Err(err), _, _) | (_, _, Err(err), _) | (_, _, _, Err(err)) => {
+            error!("Account search: {}", err);
+            None
+        }
+    }
+}
+
+fn account_matches(sp: &AccountParametersRegexed, user: &PasswdEntry, database: &AccountDatabase) -> bool {
+    let account_param = &sp.account_param;
+    if account_param.uid.map(|uid| uid != user.uid).unwrap_or(false)
+        || account_param.gid.map(|gid| gid != user.gid).unwrap_or(false) {
+        return false;
+    }
+    if !optional_match(&sp.name, Some(&user.name)) || !optional_match(&sp.shell, Some(&user.shell)) {
+        return false;
+    }
+    if let Some(group) = &sp.group {
+        if !database.groups_of(user).iter().any(|it| group.is_match(&it.name)) {
+            return false;
+        }
+    }
+    if let Some(sudo_rule) = &sp.sudo_rule {
+        if !database.sudo_rules_of(user).iter().any(|it| sudo_rule.is_match(&it.rule)) {
+            return false;
+        }
+    }
+    match account_param.predicate {
+        None => true,
+        Some(AccountPredicate::AdditionalUidZero) => user.uid == 0 && user.name != "root",
+        Some(AccountPredicate::EmptyPassword) => database.shadow_of(user)
+            .map(|it| it.password_hash.is_empty())
+            .unwrap_or(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::account_checker::{compile_parameters, AccountParameters};
+    use crate::data::{AccountPredicate, SearchType};
+
+    fn parameters() -> AccountParameters {
+        AccountParameters {
+            ioc_id: 1,
+            ioc_entry_id: 1,
+            search: SearchType::Exact,
+            name: None,
+            uid: None,
+            gid: None,
+            shell: None,
+            group: None,
+            sudo_rule: None,
+            predicate: None,
+        }
+    }
+
+    #[test]
+    fn test_compile_parameters() {
+        assert!(compile_parameters(parameters()).is_none());
+        assert!(compile_parameters(AccountParameters { uid: Some(0), ..parameters() }).is_some());
+        assert!(compile_parameters(AccountParameters { predicate: Some(AccountPredicate::EmptyPassword), ..parameters() }).is_some());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::data::IocId;
+
 #[derive(Clone)]
 pub struct ParsedArgs {
     pub ioc_definitions: Vec<String>,
@@ -9,7 +11,19 @@ pub struct ParsedArgs {
     pub file_check: bool,
     pub mutex_check: bool,
     pub process_check: bool,
-    pub registry_check: bool
+    pub registry_check: bool,
+    pub task_check: bool,
+    pub shortcut_check: bool,
+    pub service_check: bool,
+    pub ssh_key_check: bool,
+    pub account_check: bool,
+    pub kernel_module_check: bool,
+    pub preload_check: bool,
+    pub environment_check: bool,
+    pub artifacts_dir: Option<String>,
+    pub pcap_file: Option<String>,
+    /// IOC whose evaluation is printed instead of reporting
+    pub explain: Option<IocId>,
 }
 
 const LOCAL_MODE_FLAG: &str = "--local";
@@ -22,6 +36,17 @@ const DIS_FILE_FLAG: &str = "--dis-file";
 const DIS_MUTEX_FLAG: &str = "--dis-mutex";
 const DIS_PROCESS_FLAG: &str = "--dis-proc";
 const DIS_REGISTRY_FLAG: &str = "--dis-reg";
+const DIS_TASK_FLAG: &str = "--dis-task";
+const DIS_SHORTCUT_FLAG: &str = "--dis-lnk";
+const DIS_SERVICE_FLAG: &str = "--dis-service";
+const DIS_SSH_KEY_FLAG: &str = "--dis-ssh";
+const DIS_ACCOUNT_FLAG: &str = "--dis-account";
+const DIS_KERNEL_MODULE_FLAG: &str = "--dis-kmod";
+const DIS_PRELOAD_FLAG: &str = "--dis-preload";
+const DIS_ENVIRONMENT_FLAG: &str = "--dis-env";
+const ARTIFACTS_FLAG: &str = "--artifacts";
+const PCAP_FLAG: &str = "--pcap";
+const EXPLAIN_COMMAND: &str = "explain";
 
 pub fn parsed_args() -> ParsedArgs {
     let args: Vec<String> = std::env::args().collect();
@@ -37,9 +62,21 @@ pub fn parsed_args() -> ParsedArgs {
     let mut mutex_check = true;
     let mut process_check = true;
     let mut registry_check = true;
+    let mut task_check = true;
+    let mut shortcut_check = true;
+    let mut service_check = true;
+    let mut ssh_key_check = true;
+    let mut account_check = true;
+    let mut kernel_module_check = true;
+    let mut preload_check = true;
+    let mut environment_check = true;
     let mut raw_console_mode = false;
+    let mut artifacts_dir = None;
+    let mut pcap_file = None;
+    let mut explain = None;
 
-    for arg in args {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
         match arg.as_str() {
             LOCAL_MODE_FLAG => { local_mode = true }
             LOCAL_MODE_FLAG_S => { local_mode = true }
@@ -50,6 +87,22 @@ pub fn parsed_args() -> ParsedArgs {
             DIS_MUTEX_FLAG => { mutex_check = false }
             DIS_PROCESS_FLAG => { process_check = false }
             DIS_REGISTRY_FLAG => { registry_check = false }
+            DIS_TASK_FLAG => { task_check = false }
+            DIS_SHORTCUT_FLAG => { shortcut_check = false }
+            DIS_SERVICE_FLAG => { service_check = false }
+            DIS_SSH_KEY_FLAG => { ssh_key_check = false }
+            DIS_ACCOUNT_FLAG => { account_check = false }
+            DIS_KERNEL_MODULE_FLAG => { kernel_module_check = false }
+            DIS_PRELOAD_FLAG => { preload_check = false }
+            DIS_ENVIRONMENT_FLAG => { environment_check = false }
+            ARTIFACTS_FLAG => { artifacts_dir = args.next().cloned() }
+            PCAP_FLAG => { pcap_file = args.next().cloned() }
+            EXPLAIN_COMMAND => {
+                explain = args.next().and_then(|it| it.parse::<IocId>().ok());
+                if explain.is_none() {
+                    eprintln!("{} expects a numeric IOC id", EXPLAIN_COMMAND);
+                }
+            }
             RAW_CONSOLE_MODE_FLAG => { raw_console_mode = true }
             _ => {
                 ioc_definitions.push(arg.clone())
@@ -67,6 +120,17 @@ pub fn parsed_args() -> ParsedArgs {
         file_check,
         mutex_check,
         process_check,
-        registry_check
+        registry_check,
+        task_check,
+        shortcut_check,
+        service_check,
+        ssh_key_check,
+        account_check,
+        kernel_module_check,
+        preload_check,
+        environment_check,
+        artifacts_dir,
+        pcap_file,
+        explain,
     }
 }
\ No newline at end of file
@@ -0,0 +1,449 @@
+use std::fmt::{Display, Formatter};
+use std::fmt;
+
+// Shell link (.lnk) layout, see [MS-SHLLINK]
+const LNK_HEADER_SIZE: u32 = 0x4C;
+const LNK_CLSID: [u8; 16] = [0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46];
+const LNK_HAS_LINK_TARGET_ID_LIST: u32 = 0x01;
+const LNK_HAS_LINK_INFO: u32 = 0x02;
+const LNK_HAS_NAME: u32 = 0x04;
+const LNK_HAS_RELATIVE_PATH: u32 = 0x08;
+const LNK_HAS_WORKING_DIR: u32 = 0x10;
+const LNK_HAS_ARGUMENTS: u32 = 0x20;
+const LNK_IS_UNICODE: u32 = 0x80;
+const LNK_VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x01;
+
+#[derive(Debug)]
+pub struct ArtifactError {
+    pub kind: String,
+    pub message: String,
+}
+
+impl Display for ArtifactError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "ArtifactError(kind: {}, message: {})", self.kind, self.message)
+    }
+}
+
+impl From<roxmltree::Error> for ArtifactError {
+    fn from(error: roxmltree::Error) -> Self {
+        ArtifactError {
+            kind: "XML parsing".to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskAction {
+    pub command: String,
+    pub arguments: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledTask {
+    pub name: String,
+    pub author: Option<String>,
+    pub actions: Vec<TaskAction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shortcut {
+    pub target: Option<String>,
+    pub arguments: Option<String>,
+    pub working_dir: Option<String>,
+}
+
+/// Service of `HKLM\SYSTEM\CurrentControlSet\Services`, svchost services name their DLL in `Parameters\ServiceDll`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Service {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub image_path: Option<String>,
+    pub service_dll: Option<String>,
+    /// 0 boot, 1 system, 2 automatic, 3 manual, 4 disabled
+    pub start: Option<u32>,
+}
+
+/// Task Scheduler stores its XML in UTF-16, collected artifacts may have been re-encoded.
+pub fn decode_text(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        decode_utf16(&bytes[2..], u16::from_le_bytes)
+    } else if bytes.starts_with(&[0xFE, 0xFF]) {
+        decode_utf16(&bytes[2..], u16::from_be_bytes)
+    } else if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        String::from_utf8_lossy(&bytes[3..]).to_string()
+    } else if bytes.len() >= 2 && bytes[0] != 0 && bytes[1] == 0 {
+        decode_utf16(bytes, u16::from_le_bytes)
+    } else {
+        String::from_utf8_lossy(bytes).to_string()
+    }
+}
+
+fn decode_utf16(bytes: &[u8], to_u16: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2)
+        .map(|pair| to_u16([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Parses a Task Scheduler task definition. The `fallback_name` is used when the XML lacks its URI.
+pub fn parse_task_xml(xml: &str, fallback_name: &str) -> Result<ScheduledTask, ArtifactError> {
+    let document = roxmltree::Document::parse(xml)?;
+    let task = document.root_element();
+    if task.tag_name().name() != "Task" {
+        return Err(ArtifactError {
+            kind: "XML parsing".to_string(),
+            message: format!("Expected root element Task, found {}", task.tag_name().name()),
+        });
+    }
+    let registration_info = child_element(task, "RegistrationInfo");
+    let name = registration_info
+        .and_then(|it| child_text(it, "URI"))
+        .unwrap_or_else(|| fallback_name.to_string());
+    let author = registration_info.and_then(|it| child_text(it, "Author"));
+    let actions = match child_element(task, "Actions") {
+        None => vec![],
+        Some(actions) => actions.children()
+            .filter(|it| it.is_element() && it.tag_name().name() == "Exec")
+            .map(|exec| TaskAction {
+                command: child_text(exec, "Command").unwrap_or_default(),
+                arguments: child_text(exec, "Arguments"),
+            })
+            .collect()
+    };
+    Ok(ScheduledTask { name, author, actions })
+}
+
+fn child_element<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|it| it.is_element() && it.tag_name().name() == name)
+}
+
+fn child_text(node: roxmltree::Node, name: &str) -> Option<String> {
+    child_element(node, name)
+        .and_then(|it| it.text())
+        .map(|it| it.trim().to_string())
+}
+
+/// Parses a shell link and extracts the target path, arguments and working directory.
+pub fn parse_lnk(data: &[u8]) -> Result<Shortcut, ArtifactError> {
+    if read_u32(data, 0) != Some(LNK_HEADER_SIZE) || data.get(4..20) != Some(&LNK_CLSID[..]) {
+        return Err(ArtifactError {
+            kind: "LNK parsing".to_string(),
+            message: "Not a shell link".to_string(),
+        });
+    }
+    parse_lnk_body(data).ok_or(ArtifactError {
+        kind: "LNK parsing".to_string(),
+        message: "Truncated or malformed shell link".to_string(),
+    })
+}
+
+fn parse_lnk_body(data: &[u8]) -> Option<Shortcut> {
+    let flags = read_u32(data, 0x14)?;
+    let mut offset = LNK_HEADER_SIZE as usize;
+    if flags & LNK_HAS_LINK_TARGET_ID_LIST != 0 {
+        offset += 2 + read_u16(data, offset)? as usize;
+    }
+    let mut target = None;
+    if flags & LNK_HAS_LINK_INFO != 0 {
+        let link_info_size = read_u32(data, offset)? as usize;
+        target = parse_link_info(data.get(offset..offset + link_info_size)?);
+        offset += link_info_size;
+    }
+    let is_unicode = flags & LNK_IS_UNICODE != 0;
+    let mut string_data = |flag: u32| -> Option<Option<String>> {
+        if flags & flag == 0 {
+            return Some(None);
+        }
+        let (value, next_offset) = read_string_data(data, offset, is_unicode)?;
+        offset = next_offset;
+        Some(Some(value))
+    };
+    // String data structures are stored in this exact order
+    let _name = string_data(LNK_HAS_NAME)?;
+    let relative_path = string_data(LNK_HAS_RELATIVE_PATH)?;
+    let working_dir = string_data(LNK_HAS_WORKING_DIR)?;
+    let arguments = string_data(LNK_HAS_ARGUMENTS)?;
+    Some(Shortcut {
+        target: target.or(relative_path),
+        arguments,
+        working_dir,
+    })
+}
+
+fn parse_link_info(link_info: &[u8]) -> Option<String> {
+    let header_size = read_u32(link_info, 0x04)?;
+    let link_info_flags = read_u32(link_info, 0x08)?;
+    if link_info_flags & LNK_VOLUME_ID_AND_LOCAL_BASE_PATH == 0 {
+        return None;
+    }
+    if header_size >= 0x24 {
+        let base_path = read_c_wide_string(link_info, read_u32(link_info, 0x1C)? as usize)?;
+        let suffix = read_c_wide_string(link_info, read_u32(link_info, 0x20)? as usize).unwrap_or_default();
+        Some(base_path + &suffix)
+    } else {
+        let base_path = read_c_string(link_info, read_u32(link_info, 0x10)? as usize)?;
+        let suffix = read_c_string(link_info, read_u32(link_info, 0x18)? as usize).unwrap_or_default();
+        Some(base_path + &suffix)
+    }
+}
+
+fn read_string_data(data: &[u8], offset: usize, is_unicode: bool) -> Option<(String, usize)> {
+    let count = read_u16(data, offset)? as usize;
+    let start = offset + 2;
+    if is_unicode {
+        let bytes = data.get(start..start + count * 2)?;
+        Some((decode_utf16(bytes, u16::from_le_bytes), start + count * 2))
+    } else {
+        let bytes = data.get(start..start + count)?;
+        Some((String::from_utf8_lossy(bytes).to_string(), start + count))
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|it| u16::from_le_bytes([it[0], it[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|it| u32::from_le_bytes([it[0], it[1], it[2], it[3]]))
+}
+
+fn read_c_string(data: &[u8], offset: usize) -> Option<String> {
+    let bytes = data.get(offset..)?;
+    let end = bytes.iter().position(|it| *it == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+}
+
+fn read_c_wide_string(data: &[u8], offset: usize) -> Option<String> {
+    let bytes = data.get(offset..)?;
+    let units: Vec<u16> = bytes.chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|it| *it != 0)
+        .collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+enum RegValue {
+    Text(String),
+    Dword(u32),
+}
+
+/// Parses the services of a `regedit` export (`.reg`) of a Services key, other keys are ignored.
+/// Services exported from several control sets are merged by name.
+pub fn parse_reg_services(content: &str) -> Vec<Service> {
+    let mut services: Vec<Service> = Vec::new();
+    // Service whose key or Parameters subkey the following values belong to
+    let mut current: Option<(usize, bool)> = None;
+    for line in reg_lines(content) {
+        if line.starts_with('[') && line.ends_with(']') {
+            current = service_key(&line[1..line.len() - 1]).map(|(name, parameters)| {
+                let index = match services.iter().position(|it| it.name.eq_ignore_ascii_case(name)) {
+                    Some(index) => index,
+                    None => {
+                        services.push(Service { name: name.to_string(), ..Service::default() });
+                        services.len() - 1
+                    }
+                };
+                (index, parameters)
+            });
+            continue;
+        }
+        let (index, parameters) = match current {
+            Some(current) => current,
+            None => continue,
+        };
+        let (value_name, value) = match reg_value(&line) {
+            Some(value) => value,
+            None => continue,
+        };
+        let service = &mut services[index];
+        match (parameters, value_name.to_ascii_lowercase().as_str(), value) {
+            (false, "imagepath", RegValue::Text(text)) => service.image_path = Some(text),
+            (false, "displayname", RegValue::Text(text)) => service.display_name = Some(text),
+            (false, "start", RegValue::Dword(start)) => service.start = Some(start),
+            (true, "servicedll", RegValue::Text(text)) => service.service_dll = Some(text),
+            _ => {}
+        }
+    }
+    services
+}
+
+/// Lines with hex values continued by a trailing backslash are joined.
+fn reg_lines(content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for part in content.lines().map(|it| it.trim()) {
+        if part.ends_with('\\') && !part.starts_with('[') {
+            line.push_str(&part[..part.len() - 1]);
+        } else {
+            line.push_str(part);
+            lines.push(std::mem::take(&mut line));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Name of the service the key path belongs to and whether it is its Parameters subkey, deleted keys are none.
+fn service_key(path: &str) -> Option<(&str, bool)> {
+    if path.starts_with('-') {
+        return None;
+    }
+    let start = path.to_ascii_lowercase().find("\\services\\")? + "\\services\\".len();
+    let parts: Vec<&str> = path[start..].split('\\').collect();
+    match parts.as_slice() {
+        [name] if !name.is_empty() => Some((name, false)),
+        [name, subkey] if subkey.eq_ignore_ascii_case("Parameters") => Some((name, true)),
+        _ => None,
+    }
+}
+
+/// `"name"="text"`, `"name"=dword:0000002` and `"name"=hex(2):..` (expandable UTF-16 text) values.
+fn reg_value(line: &str) -> Option<(String, RegValue)> {
+    let (name, rest) = reg_string(line)?;
+    let rest = rest.trim_start();
+    if !rest.starts_with('=') {
+        return None;
+    }
+    let data = rest[1..].trim();
+    let value = if data.starts_with('"') {
+        RegValue::Text(reg_string(data)?.0)
+    } else if let Some(dword) = data.strip_prefix("dword:") {
+        RegValue::Dword(u32::from_str_radix(dword, 16).ok()?)
+    } else if let Some(hex) = data.strip_prefix("hex(2):") {
+        let bytes = hex.split(',')
+            .map(|it| u8::from_str_radix(it.trim(), 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .ok()?;
+        let text = decode_utf16(&bytes, u16::from_le_bytes);
+        RegValue::Text(text.trim_end_matches('\0').to_string())
+    } else {
+        return None;
+    };
+    Some((name, value))
+}
+
+/// Quoted string at the start of `text` without its escapes, and the rest after the closing quote.
+fn reg_string(text: &str) -> Option<(String, &str)> {
+    if !text.starts_with('"') {
+        return None;
+    }
+    let mut value = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((position, c)) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?.1),
+            '"' => return Some((value, &text[position + 1..])),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::artifact_parser::{parse_task_xml, parse_lnk, parse_reg_services, decode_text, LNK_CLSID};
+
+    const TASK_XML: &str = r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Author>WORKGROUP\admin</Author>
+    <URI>\Microsoft\Windows\Updater</URI>
+  </RegistrationInfo>
+  <Actions Context="Author">
+    <Exec>
+      <Command>C:\Users\Public\upd.exe</Command>
+      <Arguments>-k &quot;netsvcs&quot;</Arguments>
+    </Exec>
+  </Actions>
+</Task>"#;
+
+    fn utf16_le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(|it| it.to_le_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_parse_task_xml() {
+        let task = parse_task_xml(TASK_XML, "\\Updater").unwrap();
+        assert_eq!(task.name, "\\Microsoft\\Windows\\Updater");
+        assert_eq!(task.author.as_deref(), Some("WORKGROUP\\admin"));
+        assert_eq!(task.actions.len(), 1);
+        assert_eq!(task.actions[0].command, "C:\\Users\\Public\\upd.exe");
+        assert_eq!(task.actions[0].arguments.as_deref(), Some("-k \"netsvcs\""));
+    }
+
+    #[test]
+    fn test_decode_utf16_task_xml() {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(utf16_le(TASK_XML));
+        assert_eq!(decode_text(&bytes), TASK_XML);
+    }
+
+    #[test]
+    fn test_parse_lnk() {
+        let mut lnk = vec![0u8; 0x4C];
+        lnk[0] = 0x4C;
+        lnk[4..20].copy_from_slice(&LNK_CLSID);
+        // HasLinkInfo | HasArguments | IsUnicode
+        lnk[0x14..0x18].copy_from_slice(&(0x02u32 | 0x20 | 0x80).to_le_bytes());
+
+        let base_path = b"C:\\Windows\\System32\\cmd.exe\0";
+        let mut link_info = vec![0u8; 0x1C];
+        let link_info_size = (0x1C + base_path.len() + 1) as u32;
+        link_info[0x00..0x04].copy_from_slice(&link_info_size.to_le_bytes());
+        link_info[0x04..0x08].copy_from_slice(&0x1Cu32.to_le_bytes());
+        link_info[0x08..0x0C].copy_from_slice(&1u32.to_le_bytes());
+        link_info[0x10..0x14].copy_from_slice(&0x1Cu32.to_le_bytes());
+        link_info[0x18..0x1C].copy_from_slice(&((0x1C + base_path.len()) as u32).to_le_bytes());
+        link_info.extend_from_slice(base_path);
+        link_info.push(0);
+        lnk.extend(link_info);
+
+        let arguments = "/c powershell -enc AAAA";
+        lnk.extend_from_slice(&(arguments.len() as u16).to_le_bytes());
+        lnk.extend(utf16_le(arguments));
+
+        let shortcut = parse_lnk(&lnk).unwrap();
+        assert_eq!(shortcut.target.as_deref(), Some("C:\\Windows\\System32\\cmd.exe"));
+        assert_eq!(shortcut.arguments.as_deref(), Some(arguments));
+        assert_eq!(shortcut.working_dir, None);
+    }
+
+    #[test]
+    fn test_parse_lnk_rejects_other_files() {
+        assert!(parse_lnk(b"MZ\x90\x00").is_err());
+    }
+
+    #[test]
+    fn test_parse_reg_services() {
+        let export = "Windows Registry Editor Version 5.00\r\n\
+            \r\n\
+            [HKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Services\\UpdSvc]\r\n\
+            \"DisplayName\"=\"Windows \\\"Update\\\" Helper\"\r\n\
+            \"Start\"=dword:00000002\r\n\
+            \"ImagePath\"=hex(2):25,00,53,00,79,00,73,00,74,00,65,00,6d,00,52,00,6f,00,6f,00,\\\r\n\
+              74,00,25,00,5c,00,73,00,76,00,63,00,68,00,6f,00,73,00,74,00,2e,00,65,00,78,\\\r\n\
+              00,65,00,00,00\r\n\
+            \r\n\
+            [HKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Services\\UpdSvc\\Parameters]\r\n\
+            \"ServiceDll\"=\"C:\\\\Users\\\\Public\\\\upd.dll\"\r\n\
+            \r\n\
+            [HKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Services\\UpdSvc\\Security]\r\n\
+            \"ServiceDll\"=\"ignored.dll\"\r\n\
+            \r\n\
+            [HKEY_LOCAL_MACHINE\\SYSTEM\\ControlSet001\\Services\\Tcpip]\r\n\
+            \"ImagePath\"=\"System32\\\\drivers\\\\tcpip.sys\"\r\n";
+        let services = parse_reg_services(export);
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].name, "UpdSvc");
+        assert_eq!(services[0].display_name.as_deref(), Some("Windows \"Update\" Helper"));
+        assert_eq!(services[0].start, Some(2));
+        assert_eq!(services[0].image_path.as_deref(), Some("%SystemRoot%\\svchost.exe"));
+        assert_eq!(services[0].service_dll.as_deref(), Some("C:\\Users\\Public\\upd.dll"));
+        assert_eq!(services[1].name, "Tcpip");
+        assert_eq!(services[1].image_path.as_deref(), Some("System32\\drivers\\tcpip.sys"));
+    }
+}
@@ -0,0 +1,521 @@
+use crate::certificate::{CertificateDetails, CertificateError, parse_certificate};
+use crate::der::{der_element, der_elements, der_raw_elements, explicit_content, DER_BIT_STRING, DER_EXPLICIT_0,
+                 DER_OCTET_STRING, DER_OID, DER_SEQUENCE, DER_SET};
+use ring::digest;
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use std::fmt::{Display, Formatter};
+use std::fmt;
+use std::path::Path;
+use x509_parser::certificate::X509Certificate;
+
+const PE_SECURITY_DIRECTORY: usize = 4;
+const WIN_CERT_TYPE_PKCS_SIGNED_DATA: u16 = 2;
+
+/// 1.2.840.113549.1.7.2
+const OID_PKCS7_SIGNED_DATA: [u8; 9] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+/// 1.3.6.1.4.1.311.2.1.4
+const OID_SPC_INDIRECT_DATA: [u8; 10] = [0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x01, 0x04];
+/// 1.2.840.113549.1.9.4
+const OID_MESSAGE_DIGEST: [u8; 9] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x04];
+/// 1.3.14.3.2.26
+const OID_SHA1: [u8; 5] = [0x2B, 0x0E, 0x03, 0x02, 0x1A];
+/// 2.16.840.1.101.3.4.2.1
+const OID_SHA256: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+/// 2.16.840.1.101.3.4.2.2
+const OID_SHA384: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+/// 2.16.840.1.101.3.4.2.3
+const OID_SHA512: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+/// 1.2.840.113549.1.1.1
+const OID_RSA_ENCRYPTION: [u8; 9] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01];
+/// 1.2.840.10045.2.1
+const OID_EC_PUBLIC_KEY: [u8; 7] = [0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+/// 1.2.840.10045.3.1.7
+const OID_EC_P256: [u8; 8] = [0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+/// 1.3.132.0.34
+const OID_EC_P384: [u8; 5] = [0x2B, 0x81, 0x04, 0x00, 0x22];
+
+#[derive(Debug)]
+pub struct AuthenticodeError {
+    pub kind: String,
+    pub message: String,
+}
+
+impl Display for AuthenticodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "AuthenticodeError(kind: {}, message: {})", self.kind, self.message)
+    }
+}
+
+impl From<CertificateError> for AuthenticodeError {
+    fn from(error: CertificateError) -> Self {
+        AuthenticodeError {
+            kind: error.kind,
+            message: error.message,
+        }
+    }
+}
+
+fn pe_error(message: &str) -> AuthenticodeError {
+    AuthenticodeError {
+        kind: "PE parsing".to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn signature_error(message: &str) -> AuthenticodeError {
+    AuthenticodeError {
+        kind: "Signature parsing".to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Signature embedded in a PE file. Trust in the chain root and revocation are not checked,
+/// IOCs name stolen or revoked certificates by their thumbprint or serial.
+#[derive(Debug, Clone)]
+pub struct AuthenticodeSignature {
+    pub signer: CertificateDetails,
+    /// Signer first, then its issuers as far as they are embedded
+    pub chain: Vec<CertificateDetails>,
+    /// The image hash equals the signed one
+    pub digest_valid: bool,
+    /// The signer signed the image hash and every certificate of the chain is signed by the next one
+    pub signature_valid: bool,
+}
+
+impl AuthenticodeSignature {
+    pub fn is_valid(&self) -> bool {
+        self.digest_valid && self.signature_valid
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    fn from_oid(oid: &[u8]) -> Option<Self> {
+        if oid == OID_SHA1 {
+            Some(DigestAlgorithm::Sha1)
+        } else if oid == OID_SHA256 {
+            Some(DigestAlgorithm::Sha256)
+        } else if oid == OID_SHA384 {
+            Some(DigestAlgorithm::Sha384)
+        } else if oid == OID_SHA512 {
+            Some(DigestAlgorithm::Sha512)
+        } else {
+            None
+        }
+    }
+
+    fn ring_algorithm(self) -> &'static digest::Algorithm {
+        match self {
+            DigestAlgorithm::Sha1 => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+            DigestAlgorithm::Sha256 => &digest::SHA256,
+            DigestAlgorithm::Sha384 => &digest::SHA384,
+            DigestAlgorithm::Sha512 => &digest::SHA512,
+        }
+    }
+}
+
+/// Offsets of the parts the image hash skips.
+struct PeLayout {
+    checksum: usize,
+    security_entry: usize,
+    /// File offset and size of the attribute certificate table
+    certificate_table: Option<(usize, usize)>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|it| u16::from_le_bytes([it[0], it[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|it| u32::from_le_bytes([it[0], it[1], it[2], it[3]]))
+}
+
+fn pe_layout(pe: &[u8]) -> Result<PeLayout, AuthenticodeError> {
+    if !pe.starts_with(b"MZ") {
+        return Err(pe_error("Missing MZ header"));
+    }
+    let pe_header = read_u32(pe, 0x3C).ok_or_else(|| pe_error("Truncated DOS header"))? as usize;
+    if pe.get(pe_header..pe_header + 4) != Some(b"PE\0\0") {
+        return Err(pe_error("Missing PE signature"));
+    }
+    let optional_header = pe_header + 24;
+    let directory_count_offset = match read_u16(pe, optional_header) {
+        Some(0x10B) => optional_header + 92,
+        Some(0x20B) => optional_header + 108,
+        _ => return Err(pe_error("Unknown optional header magic")),
+    };
+    let directory_count = read_u32(pe, directory_count_offset)
+        .ok_or_else(|| pe_error("Truncated optional header"))? as usize;
+    let checksum = optional_header + 64;
+    let security_entry = directory_count_offset + 4 + PE_SECURITY_DIRECTORY * 8;
+    if directory_count <= PE_SECURITY_DIRECTORY {
+        return Ok(PeLayout { checksum, security_entry, certificate_table: None });
+    }
+    let offset = read_u32(pe, security_entry).ok_or_else(|| pe_error("Truncated data directories"))? as usize;
+    let size = read_u32(pe, security_entry + 4).ok_or_else(|| pe_error("Truncated data directories"))? as usize;
+    if offset == 0 || size == 0 {
+        return Ok(PeLayout { checksum, security_entry, certificate_table: None });
+    }
+    if offset < security_entry + 8 || offset.checked_add(size).map(|end| end > pe.len()).unwrap_or(true) {
+        return Err(pe_error("Certificate table lies outside of the file"));
+    }
+    Ok(PeLayout { checksum, security_entry, certificate_table: Some((offset, size)) })
+}
+
+/// Authenticode image hash, the whole file except the checksum, the certificate table and its directory entry.
+fn image_digest(pe: &[u8], layout: &PeLayout, algorithm: DigestAlgorithm) -> Vec<u8> {
+    let (table_start, table_end) = layout.certificate_table
+        .map(|(offset, size)| (offset, offset + size))
+        .unwrap_or((pe.len(), pe.len()));
+    let mut context = digest::Context::new(algorithm.ring_algorithm());
+    context.update(&pe[..layout.checksum]);
+    context.update(&pe[layout.checksum + 4..layout.security_entry]);
+    context.update(&pe[layout.security_entry + 8..table_start]);
+    context.update(&pe[table_end..]);
+    context.finish().as_ref().to_vec()
+}
+
+/// Parts of the PKCS#7 SignedData of an Authenticode signature.
+struct SignedData<'a> {
+    /// SpcIndirectDataContent without its tag and length, as covered by the message digest
+    indirect_data: &'a [u8],
+    image_digest_algorithm: Option<DigestAlgorithm>,
+    image_digest: &'a [u8],
+    /// (DER, parsed) pairs
+    certificates: Vec<(&'a [u8], X509Certificate<'a>)>,
+    signer_issuer: &'a [u8],
+    signer_serial: &'a [u8],
+    digest_algorithm: Option<DigestAlgorithm>,
+    /// Complete encoding of the `[0] IMPLICIT` authenticated attributes
+    authenticated_attributes: Option<&'a [u8]>,
+    message_digest: Option<&'a [u8]>,
+    signature: &'a [u8],
+}
+
+/// `Ok(None)` for a PE file without an embedded signature.
+pub fn read_signature(pe: &[u8]) -> Result<Option<AuthenticodeSignature>, AuthenticodeError> {
+    let layout = pe_layout(pe)?;
+    let (offset, size) = match layout.certificate_table {
+        Some(table) => table,
+        None => return Ok(None),
+    };
+    // Only the first WIN_CERTIFICATE is read, nested signatures live in its unauthenticated attributes
+    let table = &pe[offset..offset + size];
+    let length = read_u32(table, 0).ok_or_else(|| signature_error("Truncated certificate table"))? as usize;
+    let certificate_type = read_u16(table, 6).ok_or_else(|| signature_error("Truncated certificate table"))?;
+    if certificate_type != WIN_CERT_TYPE_PKCS_SIGNED_DATA {
+        return Err(signature_error(&format!("Unsupported certificate type {}", certificate_type)));
+    }
+    let pkcs7 = table.get(8..length).ok_or_else(|| signature_error("Truncated certificate table"))?;
+    let signed_data = parse_signed_data(pkcs7)?;
+
+    let signer = signed_data.certificates.iter()
+        .position(|(_, certificate)| certificate.issuer().as_raw() == signed_data.signer_issuer
+            && certificate.raw_serial() == signed_data.signer_serial)
+        .ok_or_else(|| signature_error("Signer certificate is not embedded"))?;
+    let (chain, chain_valid) = signer_chain(&signed_data.certificates, signer);
+    let digest_valid = match signed_data.image_digest_algorithm {
+        Some(algorithm) => image_digest(pe, &layout, algorithm) == signed_data.image_digest,
+        None => {
+            debug!("Authenticode: Unsupported image digest algorithm");
+            false
+        }
+    };
+    let signature_valid = chain_valid && signer_signature_valid(&signed_data, &signed_data.certificates[signer].1);
+    let chain = chain.iter()
+        .map(|index| parse_certificate(signed_data.certificates[*index].0))
+        .collect::<Result<Vec<CertificateDetails>, CertificateError>>()?;
+    Ok(Some(AuthenticodeSignature {
+        signer: chain[0].clone(),
+        chain,
+        digest_valid,
+        signature_valid,
+    }))
+}
+
+/// Catalog of the system catalog database listing the file, Windows verifies the catalogs themselves.
+/// Catalogs are looked up by the SHA1 member hash of `CryptCATAdminCalcHashFromFileHandle`.
+#[cfg(windows)]
+pub fn catalog_signature(path: &Path) -> Option<String> {
+    use std::os::windows::io::AsRawHandle;
+    use std::ptr::null_mut;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::um::mscat::{CryptCATAdminAcquireContext, CryptCATAdminCalcHashFromFileHandle, CryptCATAdminEnumCatalogFromHash,
+                            CryptCATAdminReleaseCatalogContext, CryptCATAdminReleaseContext, CryptCATCatalogInfoFromContext,
+                            CATALOG_INFO, HCATADMIN};
+    use winapi::um::winnt::HANDLE;
+
+    let file = std::fs::File::open(path).ok()?;
+    let file_handle = file.as_raw_handle() as HANDLE;
+    unsafe {
+        let mut admin: HCATADMIN = null_mut();
+        if CryptCATAdminAcquireContext(&mut admin, std::ptr::null(), 0) == 0 {
+            debug!("Authenticode: Cannot open the catalog database");
+            return None;
+        }
+        let mut hash_length: DWORD = 0;
+        CryptCATAdminCalcHashFromFileHandle(file_handle, &mut hash_length, null_mut(), 0);
+        let mut hash = vec![0u8; hash_length as usize];
+        let catalog = if hash_length > 0
+            && CryptCATAdminCalcHashFromFileHandle(file_handle, &mut hash_length, hash.as_mut_ptr(), 0) != 0 {
+            let catalog_context = CryptCATAdminEnumCatalogFromHash(admin, hash.as_mut_ptr(), hash_length, 0, null_mut());
+            if catalog_context.is_null() {
+                None
+            } else {
+                let mut catalog_info: CATALOG_INFO = std::mem::zeroed();
+                catalog_info.cbStruct = std::mem::size_of::<CATALOG_INFO>() as DWORD;
+                let catalog_file = if CryptCATCatalogInfoFromContext(catalog_context, &mut catalog_info, 0) != 0 {
+                    widestring::WideCString::from_ptr_str(catalog_info.wszCatalogFile.as_ptr()).to_string_lossy()
+                } else {
+                    "UNKNOWN".to_string()
+                };
+                CryptCATAdminReleaseCatalogContext(admin, catalog_context, 0);
+                Some(catalog_file)
+            }
+        } else {
+            None
+        };
+        CryptCATAdminReleaseContext(admin, 0);
+        catalog
+    }
+}
+
+#[cfg(not(windows))]
+pub fn catalog_signature(_path: &Path) -> Option<String> {
+    None
+}
+
+fn algorithm_identifier(algorithm_identifier: &[u8]) -> Option<DigestAlgorithm> {
+    match der_element(algorithm_identifier)? {
+        (DER_OID, oid, _) => DigestAlgorithm::from_oid(oid),
+        _ => None,
+    }
+}
+
+fn parse_signed_data(pkcs7: &[u8]) -> Result<SignedData<'_>, AuthenticodeError> {
+    let malformed = || signature_error("Malformed PKCS#7 signed data");
+    let content_info = match der_element(pkcs7) {
+        Some((DER_SEQUENCE, content_info, _)) => der_elements(content_info),
+        _ => return Err(malformed()),
+    };
+    match content_info.first() {
+        Some((DER_OID, oid)) if *oid == OID_PKCS7_SIGNED_DATA => {}
+        _ => return Err(signature_error("Not a PKCS#7 signed data")),
+    }
+    // version, digestAlgorithms, contentInfo, [0] certificates, [1] crls, signerInfos
+    let signed_data = der_elements(explicit_content(&content_info, DER_SEQUENCE).ok_or_else(malformed)?);
+    let content = signed_data.iter()
+        .find(|(tag, _)| *tag == DER_SEQUENCE)
+        .map(|(_, content)| der_elements(content))
+        .ok_or_else(malformed)?;
+    match content.first() {
+        Some((DER_OID, oid)) if *oid == OID_SPC_INDIRECT_DATA => {}
+        _ => return Err(signature_error("Signed content is not an Authenticode indirect data")),
+    }
+    let indirect_data = explicit_content(&content, DER_SEQUENCE).ok_or_else(malformed)?;
+    let (image_digest_algorithm, image_digest) = match der_elements(indirect_data).get(1) {
+        Some((DER_SEQUENCE, digest_info)) => match der_elements(digest_info).as_slice() {
+            [(DER_SEQUENCE, algorithm), (DER_OCTET_STRING, digest), ..] => (algorithm_identifier(algorithm), *digest),
+            _ => return Err(malformed()),
+        },
+        _ => return Err(malformed()),
+    };
+    let certificates = signed_data.iter()
+        .find(|(tag, _)| *tag == DER_EXPLICIT_0)
+        .map(|(_, certificates)| parse_certificates(certificates))
+        .unwrap_or_default();
+
+    let signer_info = match signed_data.last().and_then(|(tag, signer_infos)| match tag {
+        &DER_SET => der_element(signer_infos),
+        _ => None,
+    }) {
+        Some((DER_SEQUENCE, signer_info, _)) => signer_info,
+        _ => return Err(signature_error("Missing signer info")),
+    };
+    // version, issuerAndSerialNumber, digestAlgorithm, [0] authenticatedAttributes, digestEncryptionAlgorithm,
+    // encryptedDigest, [1] unauthenticatedAttributes
+    let signer_info: Vec<(u8, &[u8], &[u8])> = der_raw_elements(signer_info).into_iter()
+        .filter_map(|raw| der_element(raw).map(|(tag, content, _)| (tag, content, raw)))
+        .collect();
+    let (signer_issuer, signer_serial) = match signer_info.get(1) {
+        Some((DER_SEQUENCE, issuer_and_serial, _)) => match der_raw_elements(issuer_and_serial).as_slice() {
+            [issuer, serial, ..] => (*issuer, der_element(serial).map(|(_, serial, _)| serial).ok_or_else(malformed)?),
+            _ => return Err(malformed()),
+        },
+        _ => return Err(malformed()),
+    };
+    let digest_algorithm = match signer_info.get(2) {
+        Some((DER_SEQUENCE, algorithm, _)) => algorithm_identifier(algorithm),
+        _ => return Err(malformed()),
+    };
+    let authenticated_attributes = signer_info.iter()
+        .find(|(tag, _, _)| *tag == DER_EXPLICIT_0)
+        .map(|(_, attributes, raw)| (*attributes, *raw));
+    let message_digest = authenticated_attributes.and_then(|(attributes, _)|
+        der_elements(attributes).iter().find_map(|(_, attribute)| match der_elements(attribute).as_slice() {
+            [(DER_OID, oid), (DER_SET, values), ..] if *oid == OID_MESSAGE_DIGEST => match der_element(values)? {
+                (DER_OCTET_STRING, digest, _) => Some(digest),
+                _ => None,
+            },
+            _ => None,
+        })
+    );
+    let signature = signer_info.iter()
+        .skip(3)
+        .find(|(tag, _, _)| *tag == DER_OCTET_STRING)
+        .map(|(_, signature, _)| *signature)
+        .ok_or_else(malformed)?;
+
+    Ok(SignedData {
+        indirect_data,
+        image_digest_algorithm,
+        image_digest,
+        certificates,
+        signer_issuer,
+        signer_serial,
+        digest_algorithm,
+        authenticated_attributes: authenticated_attributes.map(|(_, raw)| raw),
+        message_digest,
+        signature,
+    })
+}
+
+fn parse_certificates(mut data: &[u8]) -> Vec<(&[u8], X509Certificate<'_>)> {
+    let mut certificates = Vec::new();
+    while let Ok((rest, certificate)) = x509_parser::parse_x509_certificate(data) {
+        certificates.push((&data[..data.len() - rest.len()], certificate));
+        data = rest;
+    }
+    certificates
+}
+
+/// Indexes of the signer and its embedded issuers, and whether each of them signed the previous one.
+/// A chain ending without its root is usual, roots come from the verifying system's store.
+fn signer_chain(certificates: &[(&[u8], X509Certificate)], signer: usize) -> (Vec<usize>, bool) {
+    let mut chain = vec![signer];
+    loop {
+        let current = &certificates[chain[chain.len() - 1]].1;
+        if current.subject().as_raw() == current.issuer().as_raw() {
+            return (chain, current.verify_signature(None).is_ok());
+        }
+        let candidates: Vec<usize> = (0..certificates.len())
+            .filter(|index| !chain.contains(index)
+                && certificates[*index].1.subject().as_raw() == current.issuer().as_raw())
+            .collect();
+        match candidates.iter()
+            .find(|index| current.verify_signature(Some(certificates[**index].1.public_key())).is_ok()) {
+            Some(issuer) => chain.push(*issuer),
+            None => return (chain, candidates.is_empty()),
+        }
+    }
+}
+
+fn signer_signature_valid(signed_data: &SignedData, signer: &X509Certificate) -> bool {
+    let digest_algorithm = match signed_data.digest_algorithm {
+        Some(algorithm) => algorithm,
+        None => {
+            debug!("Authenticode: Unsupported signer digest algorithm");
+            return false;
+        }
+    };
+    let signed = match signed_data.authenticated_attributes {
+        // The signature covers the attributes encoded as a SET, their message digest covers the indirect data
+        Some(attributes) => {
+            let indirect_data_digest = digest::digest(digest_algorithm.ring_algorithm(), signed_data.indirect_data);
+            if signed_data.message_digest != Some(indirect_data_digest.as_ref()) {
+                return false;
+            }
+            let mut signed = attributes.to_vec();
+            signed[0] = DER_SET;
+            signed
+        }
+        None => signed_data.indirect_data.to_vec(),
+    };
+    match verification_algorithm(signer.public_key().raw, digest_algorithm) {
+        Some((algorithm, public_key)) =>
+            UnparsedPublicKey::new(algorithm, public_key).verify(&signed, signed_data.signature).is_ok(),
+        None => {
+            debug!("Authenticode: Unsupported signer key");
+            false
+        }
+    }
+}
+
+fn verification_algorithm(subject_public_key_info: &[u8], digest_algorithm: DigestAlgorithm)
+                          -> Option<(&'static dyn VerificationAlgorithm, &[u8])> {
+    let (algorithm, public_key) = match der_element(subject_public_key_info)? {
+        (DER_SEQUENCE, subject_public_key_info, _) => match der_elements(subject_public_key_info).as_slice() {
+            // The first byte of the bit string is the count of unused bits
+            [(DER_SEQUENCE, algorithm), (DER_BIT_STRING, public_key), ..] => (der_elements(algorithm), public_key.get(1..)?),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let (key_type, parameters) = match algorithm.as_slice() {
+        [(DER_OID, key_type), (DER_OID, parameters), ..] => (*key_type, Some(*parameters)),
+        [(DER_OID, key_type), ..] => (*key_type, None),
+        _ => return None,
+    };
+    let verification: &'static dyn VerificationAlgorithm = if key_type == OID_RSA_ENCRYPTION {
+        match digest_algorithm {
+            DigestAlgorithm::Sha1 => &signature::RSA_PKCS1_2048_8192_SHA1_FOR_LEGACY_USE_ONLY,
+            DigestAlgorithm::Sha256 => &signature::RSA_PKCS1_2048_8192_SHA256,
+            DigestAlgorithm::Sha384 => &signature::RSA_PKCS1_2048_8192_SHA384,
+            DigestAlgorithm::Sha512 => &signature::RSA_PKCS1_2048_8192_SHA512,
+        }
+    } else if key_type == OID_EC_PUBLIC_KEY {
+        match (parameters?, digest_algorithm) {
+            (curve, DigestAlgorithm::Sha256) if curve == OID_EC_P256 => &signature::ECDSA_P256_SHA256_ASN1,
+            (curve, DigestAlgorithm::Sha384) if curve == OID_EC_P256 => &signature::ECDSA_P256_SHA384_ASN1,
+            (curve, DigestAlgorithm::Sha256) if curve == OID_EC_P384 => &signature::ECDSA_P384_SHA256_ASN1,
+            (curve, DigestAlgorithm::Sha384) if curve == OID_EC_P384 => &signature::ECDSA_P384_SHA384_ASN1,
+            _ => return None,
+        }
+    } else {
+        return None;
+    };
+    Some((verification, public_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::authenticode::read_signature;
+    use crate::data::HashType;
+
+    const SIGNED_PE: &[u8] = include_bytes!("../tests/fixtures/authenticode_signed.exe");
+    const SECURITY_ENTRY: usize = 0xE8;
+
+    #[test]
+    fn test_read_signature() {
+        let signature = read_signature(SIGNED_PE).unwrap().unwrap();
+        assert!(signature.is_valid());
+        assert_eq!(signature.signer.subject.text, "C=XX, O=Evil Corp, CN=Evil Software Ltd");
+        assert_eq!(signature.signer.serial, "5ca1ab1e");
+        assert_eq!(signature.chain.len(), 2);
+        assert_eq!(signature.chain[1].thumbprint(&HashType::Sha1).unwrap().value, "7cda2b2407e4d19edcbfca95cc5d460cf1bf3e6f");
+
+        // The checksum is not covered by the image hash, the rest of the headers is
+        let mut patched = SIGNED_PE.to_vec();
+        patched[0x98] = 0xFF;
+        assert!(read_signature(&patched).unwrap().unwrap().is_valid());
+        patched[0x1F0] = b'E';
+        let signature = read_signature(&patched).unwrap().unwrap();
+        assert!(!signature.digest_valid);
+        assert!(signature.signature_valid);
+    }
+
+    #[test]
+    fn test_read_unsigned() {
+        let mut unsigned = SIGNED_PE[..0x200].to_vec();
+        unsigned[SECURITY_ENTRY..SECURITY_ENTRY + 8].copy_from_slice(&[0; 8]);
+        assert!(read_signature(&unsigned).unwrap().is_none());
+        assert!(read_signature(b"#!/bin/sh").is_err());
+    }
+}
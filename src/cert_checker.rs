@@ -1,57 +1,195 @@
-use crate::data::{IocEntryId, IocId};
-use crate::ioc_evaluator::IocEntrySearchResult;
-use rustls_native_certs::load_native_certs;
-use rustls::RootCertStore;
+use crate::cert_sources::stored_certificates;
+use crate::certificate::{CertificateDetails, parse_certificate, normalize_serial};
+use crate::data::{IocEntryId, IocId, SearchType, Hashed, Evidence};
+use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult};
+use crate::search_pattern::{SearchPattern, optional_match};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 
 pub struct CertificateParameters {
     pub ioc_id: IocId,
     pub ioc_entry_id: IocEntryId,
-    pub name: String,
+    pub search: SearchType,
+    pub name: Option<String>,
+    pub subject: Option<String>,
+    pub issuer: Option<String>,
+    pub serial: Option<String>,
+    pub thumbprint: Option<Hashed>,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+    pub valid_at: Option<DateTime<Utc>>,
+    pub issued_after: Option<DateTime<Utc>>,
+    pub expires_before: Option<DateTime<Utc>>,
+    pub key_usage: Vec<String>,
 }
 
-pub fn check_certs(search_parameters: Vec<CertificateParameters>) -> Vec<IocEntrySearchResult> {
+struct CertificateParametersRegexed {
+    cert_param: CertificateParameters,
+    name: Option<SearchPattern>,
+    /// Regex search matches the whole distinguished name, exact search its components
+    subject: Option<SearchPattern>,
+    issuer: Option<SearchPattern>,
+}
+
+/// Checks the native certificate store and certificate files under `certificate_paths`.
+pub fn check_certs(search_parameters: Vec<CertificateParameters>, certificate_paths: &[String]) -> Vec<IocEntrySearchResult> {
     if search_parameters.is_empty() {
         return vec![];
     }
-    let search_parameters: Vec<CertificateParameters> = search_parameters
-        .into_iter()
-        .map(|sp| CertificateParameters {
-            ioc_id: sp.ioc_id,
-            ioc_entry_id: sp.ioc_entry_id,
-            name: sp.name.to_ascii_lowercase(),
-        }).collect();
     info!("Certificate search: Searching IOCs using certificate search.");
-    let certs = match load_native_certs() {
-        Ok(ok) => ok,
-        Err((maybe, err)) => {
-            error!("Connection search: {}", err);
-            match maybe {
-                None => RootCertStore::empty(),
-                Some(ok) => ok,
+    let search_parameters: Vec<CertificateParametersRegexed> = search_parameters.into_iter()
+        .filter_map(compile_parameters)
+        .collect();
+
+    // The same certificate is often in several bundles, it is parsed once and reported with all of them
+    let mut certs: Vec<(Vec<u8>, Vec<String>)> = Vec::new();
+    let mut cert_indexes: HashMap<Vec<u8>, usize> = HashMap::new();
+    for stored in stored_certificates(certificate_paths) {
+        match cert_indexes.get(&stored.der) {
+            Some(index) => certs[*index].1.push(stored.source),
+            None => {
+                cert_indexes.insert(stored.der.clone(), certs.len());
+                certs.push((stored.der, vec![stored.source]));
             }
         }
-    };
+    }
 
-    certs.roots.iter().flat_map(|cert| {
-        let cert = cert.to_trust_anchor();
-        let cert_subject_bytes = cert.subject;
-        let cert_subject = unsafe { std::str::from_utf8_unchecked(cert_subject_bytes) };
-        let cert_subject = cert_subject.to_ascii_lowercase();
+    certs.iter().flat_map(|(der, sources)| {
+        let cert = match parse_certificate(der) {
+            Ok(cert) => cert,
+            Err(err) => {
+                debug!("Certificate search: {} in {}", err, sources.join(", "));
+                return vec![];
+            }
+        };
+        debug!("Certificate search: Checking certificate {}", cert.subject.text);
 
         search_parameters.iter()
-            .filter(|sp| cert_subject.contains(&sp.name))
+            .filter(|sp| certificate_matches(sp, &cert))
             .map(|sp| {
                 let message =
-                    format!("Certificate search: Found certificate {} for IOC {}",
-                            sp.name,
-                            sp.ioc_id
+                    format!("Certificate search: Found certificate {} (serial {}) issued by {} in {} for IOC {}",
+                            cert.subject.text,
+                            cert.serial,
+                            cert.issuer.text,
+                            sources.join(", "),
+                            sp.cert_param.ioc_id
                     );
                 info!("{}", message);
                 IocEntrySearchResult {
-                    ioc_id: sp.ioc_id,
-                    ioc_entry_id: sp.ioc_entry_id,
+                    ioc_id: sp.cert_param.ioc_id,
+                    ioc_entry_id: sp.cert_param.ioc_entry_id,
+                    check: CheckKind::Certificate,
                     description: message,
+                    timestamp: None,
+                    evidence: Evidence::Certificate {
+                        subject: cert.subject.text.clone(),
+                        issuer: cert.issuer.text.clone(),
+                        serial: cert.serial.clone(),
+                        thumbprints: cert.thumbprints.clone(),
+                        sources: sources.clone(),
+                    },
                 }
             }).collect::<Vec<IocEntrySearchResult>>()
     }).collect()
 }
+
+fn compile_parameters(sp: CertificateParameters) -> Option<CertificateParametersRegexed> {
+    if sp.name.is_none() && sp.subject.is_none() && sp.issuer.is_none() && sp.serial.is_none()
+        && sp.thumbprint.is_none() && sp.not_before.is_none() && sp.not_after.is_none()
+        && sp.valid_at.is_none() && sp.issued_after.is_none() && sp.expires_before.is_none() && sp.key_usage.is_empty() {
+        warn!("Certificate search: IOC {} specifies nothing to search for, skipping", sp.ioc_id);
+        return None;
+    }
+    let compile = |pattern: &Option<String>| pattern.as_ref()
+        .map(|it| SearchPattern::new_ignore_case(sp.search, it))
+        .transpose();
+    match (compile(&sp.name), compile(&sp.subject), compile(&sp.issuer)) {
+        (Ok(name), Ok(subject), Ok(issuer)) =>
+            Some(CertificateParametersRegexed { cert_param: sp, name, subject, issuer }),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+            error!("Certificate search: {}", err);
+            None
+        }
+    }
+}
+
+fn certificate_matches(sp: &CertificateParametersRegexed, cert: &CertificateDetails) -> bool {
+    let cert_param = &sp.cert_param;
+    let name_matches = match &sp.name {
+        None => true,
+        Some(name) => cert.subject.components.iter().any(|(_, value)| name.is_match(value)),
+    };
+    let subject_matches = match (&cert_param.subject, cert_param.search) {
+        (Some(subject), SearchType::Exact) => cert.subject.contains_components(subject),
+        _ => optional_match(&sp.subject, Some(&cert.subject.text)),
+    };
+    let issuer_matches = match (&cert_param.issuer, cert_param.search) {
+        (Some(issuer), SearchType::Exact) => cert.issuer.contains_components(issuer),
+        _ => optional_match(&sp.issuer, Some(&cert.issuer.text)),
+    };
+    name_matches && subject_matches && issuer_matches
+        && cert_param.serial.as_ref().map(|it| normalize_serial(it) == cert.serial).unwrap_or(true)
+        && cert_param.thumbprint.as_ref()
+            .map(|it| cert.thumbprint(&it.algorithm) == Some(it))
+            .unwrap_or(true)
+        && cert_param.not_before.map(|it| it == cert.not_before).unwrap_or(true)
+        && cert_param.not_after.map(|it| it == cert.not_after).unwrap_or(true)
+        && cert_param.valid_at.map(|it| cert.not_before <= it && it <= cert.not_after).unwrap_or(true)
+        && cert_param.issued_after.map(|it| cert.not_before >= it).unwrap_or(true)
+        && cert_param.expires_before.map(|it| cert.not_after <= it).unwrap_or(true)
+        && cert_param.key_usage.iter()
+            .all(|usage| cert.key_usage.iter().any(|it| it.eq_ignore_ascii_case(usage)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cert_checker::{certificate_matches, compile_parameters, CertificateParameters};
+    use crate::certificate::parse_certificate;
+    use crate::data::SearchType;
+
+    fn parameters(name: Option<&str>) -> CertificateParameters {
+        CertificateParameters {
+            ioc_id: 1,
+            ioc_entry_id: 1,
+            search: SearchType::Exact,
+            name: name.map(|it| it.to_string()),
+            subject: None,
+            issuer: None,
+            serial: None,
+            thumbprint: None,
+            not_before: None,
+            not_after: None,
+            valid_at: None,
+            issued_after: None,
+            expires_before: None,
+            key_usage: vec![],
+        }
+    }
+
+    #[test]
+    fn test_compile_parameters() {
+        assert!(compile_parameters(parameters(None)).is_none());
+        assert!(compile_parameters(parameters(Some("Evil Root CA"))).is_some());
+    }
+
+    #[test]
+    fn test_validity_bounds() {
+        let pem = include_str!("../tests/fixtures/evil_root_ca.pem");
+        let base64: String = pem.lines().filter(|it| !it.starts_with("-----")).collect();
+        let cert = parse_certificate(&base64::decode(&base64).unwrap()).unwrap();
+        let matches = |edit: &dyn Fn(&mut CertificateParameters)| {
+            let mut parameters = parameters(None);
+            edit(&mut parameters);
+            certificate_matches(&compile_parameters(parameters).unwrap(), &cert)
+        };
+        let date = |text: &str| Some(text.parse().unwrap());
+        // Valid from 2020-01-01 to 2030-01-01
+        assert!(matches(&|it| it.valid_at = date("2025-01-01T00:00:00Z")));
+        assert!(!matches(&|it| it.valid_at = date("2031-01-01T00:00:00Z")));
+        assert!(matches(&|it| it.issued_after = date("2019-06-01T00:00:00Z")));
+        assert!(!matches(&|it| it.issued_after = date("2020-06-01T00:00:00Z")));
+        assert!(matches(&|it| it.expires_before = date("2030-01-01T00:00:00Z")));
+        assert!(!matches(&|it| it.expires_before = date("2029-01-01T00:00:00Z")));
+    }
+}
@@ -0,0 +1,310 @@
+use crate::certificate::CertificateError;
+use crate::der::{der_element, der_elements, explicit_content, DER_INTEGER, DER_OCTET_STRING, DER_OID, DER_SEQUENCE};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use x509_parser::pem::Pem;
+
+const MAX_CERTIFICATE_FILE_SIZE: u64 = 16 * 1024 * 1024;
+const CERTIFICATE_EXTENSIONS: [&str; 9] = ["pem", "crt", "cer", "der", "p12", "pfx", "jks", "keystore", "truststore"];
+/// Labels of certificate PEM blocks, `TRUSTED CERTIFICATE` is reported by its first word
+const PEM_CERTIFICATE_LABELS: [&str; 3] = ["CERTIFICATE", "TRUSTED", "X509"];
+
+const JKS_MAGIC: u32 = 0xFEED_FEED;
+const JCEKS_MAGIC: u32 = 0xCECE_CECE;
+
+/// 1.2.840.113549.1.7.1
+const OID_PKCS7_DATA: [u8; 9] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x01];
+/// 1.2.840.113549.1.7.6
+const OID_PKCS7_ENCRYPTED_DATA: [u8; 9] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x06];
+/// 1.2.840.113549.1.12.10.1.3
+const OID_PKCS12_CERT_BAG: [u8; 11] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x0C, 0x0A, 0x01, 0x03];
+/// 1.2.840.113549.1.9.22.1
+const OID_X509_CERTIFICATE: [u8; 10] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x16, 0x01];
+
+/// DER encoded certificate and where it was found, e.g. a file path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredCertificate {
+    pub der: Vec<u8>,
+    pub source: String,
+}
+
+/// Certificates of the native store (not on Linux, where it is made of the same files)
+/// and of the given files and directories.
+pub fn stored_certificates(paths: &[String]) -> Vec<StoredCertificate> {
+    let mut certificates = native_certificates();
+    let mut visited = HashSet::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_file() {
+            certificates.extend(read_certificate_file(path, &mut visited));
+            continue;
+        }
+        for entry in WalkDir::new(path).into_iter().filter_map(|it| it.ok()) {
+            // Symlinks are followed by is_file, their targets are read only once thanks to `visited`
+            if entry.path().is_file() && is_certificate_file_name(entry.path()) {
+                certificates.extend(read_certificate_file(entry.path(), &mut visited));
+            }
+        }
+    }
+    certificates
+}
+
+#[cfg(not(target_os = "linux"))]
+fn native_certificates() -> Vec<StoredCertificate> {
+    match rustls_native_certs::load_native_certs() {
+        Ok(certs) => certs.into_iter()
+            .map(|cert| StoredCertificate { der: cert.0, source: "native certificate store".to_string() })
+            .collect(),
+        Err(err) => {
+            error!("Certificate search: {}", err);
+            vec![]
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn native_certificates() -> Vec<StoredCertificate> {
+    vec![]
+}
+
+fn is_certificate_file_name(path: &Path) -> bool {
+    let extension_matches = path.extension()
+        .and_then(|it| it.to_str())
+        .map(|extension| CERTIFICATE_EXTENSIONS.iter().any(|it| it.eq_ignore_ascii_case(extension)))
+        .unwrap_or(false);
+    extension_matches || path.file_name().map(|it| it == "cacerts").unwrap_or(false)
+}
+
+fn read_certificate_file(path: &Path, visited: &mut HashSet<PathBuf>) -> Vec<StoredCertificate> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if !visited.insert(canonical) {
+        return vec![];
+    }
+    match path.metadata() {
+        Ok(metadata) if metadata.len() > MAX_CERTIFICATE_FILE_SIZE => {
+            debug!("Certificate search: Skipping {}, file is too large", path.display());
+            return vec![];
+        }
+        _ => {}
+    }
+    let content = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(err) => {
+            debug!("Certificate search: Cannot read {}: {}", path.display(), err);
+            return vec![];
+        }
+    };
+    match certificates_in_file(&content) {
+        Ok(certificates) => certificates.into_iter()
+            .map(|(der, alias)| StoredCertificate {
+                der,
+                source: match alias {
+                    Some(alias) => format!("{} (alias {})", path.display(), alias),
+                    None => path.display().to_string(),
+                },
+            })
+            .collect(),
+        Err(err) => {
+            debug!("Certificate search: Cannot read certificates of {}: {}", path.display(), err);
+            vec![]
+        }
+    }
+}
+
+/// DER encoded certificate and its keystore alias.
+pub type AliasedCertificate = (Vec<u8>, Option<String>);
+
+/// Certificates of a PEM bundle, a DER file, a Java keystore or a PKCS#12 file, with keystore aliases.
+pub fn certificates_in_file(content: &[u8]) -> Result<Vec<AliasedCertificate>, CertificateError> {
+    let magic = content.get(..4).map(|it| u32::from_be_bytes([it[0], it[1], it[2], it[3]]));
+    if magic == Some(JKS_MAGIC) || magic == Some(JCEKS_MAGIC) {
+        return parse_java_keystore(content)
+            .map(|certs| certs.into_iter().map(|(alias, der)| (der, Some(alias))).collect());
+    }
+    if content.windows(11).any(|it| it == b"-----BEGIN ") {
+        return Ok(Pem::iter_from_buffer(content)
+            .take_while(|it| it.is_ok())
+            .filter_map(|it| it.ok())
+            .filter(|pem| PEM_CERTIFICATE_LABELS.contains(&pem.label.as_str()))
+            .map(|pem| (pem.contents, None))
+            .collect());
+    }
+    if x509_parser::parse_x509_certificate(content).is_ok() {
+        return Ok(vec![(content.to_vec(), None)]);
+    }
+    parse_pkcs12(content).map(|certs| certs.into_iter().map(|der| (der, None)).collect())
+}
+
+fn keystore_error(message: &str) -> CertificateError {
+    CertificateError {
+        kind: "Keystore parsing".to_string(),
+        message: message.to_string(),
+    }
+}
+
+struct KeystoreReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> KeystoreReader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], CertificateError> {
+        if self.data.len() < count {
+            return Err(keystore_error("Unexpected end of keystore"));
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, CertificateError> {
+        self.bytes(2).map(|it| u16::from_be_bytes([it[0], it[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, CertificateError> {
+        self.bytes(4).map(|it| u32::from_be_bytes([it[0], it[1], it[2], it[3]]))
+    }
+
+    /// Java modified UTF-8, the same as UTF-8 for the aliases and certificate types met in practice
+    fn utf(&mut self) -> Result<String, CertificateError> {
+        let length = self.u16()? as usize;
+        self.bytes(length).map(|it| String::from_utf8_lossy(it).to_string())
+    }
+
+    fn certificate(&mut self, version: u32) -> Result<(String, &'a [u8]), CertificateError> {
+        let certificate_type = if version == 2 { self.utf()? } else { "X.509".to_string() };
+        let length = self.u32()? as usize;
+        Ok((certificate_type, self.bytes(length)?))
+    }
+}
+
+/// Trusted certificates and private key chains of a JKS or JCEKS keystore as (alias, DER) pairs.
+/// The keystore integrity hash is not verified, it needs the store password.
+pub fn parse_java_keystore(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, CertificateError> {
+    let mut reader = KeystoreReader { data };
+    let magic = reader.u32()?;
+    if magic != JKS_MAGIC && magic != JCEKS_MAGIC {
+        return Err(keystore_error("Not a Java keystore"));
+    }
+    let version = reader.u32()?;
+    if version != 1 && version != 2 {
+        return Err(keystore_error(&format!("Unsupported keystore version {}", version)));
+    }
+    let count = reader.u32()?;
+    let mut certificates = Vec::new();
+    for _ in 0..count {
+        let tag = reader.u32()?;
+        let alias = reader.utf()?;
+        let _timestamp = reader.bytes(8)?;
+        let mut entry_certificates = Vec::new();
+        match tag {
+            1 => {
+                let key_length = reader.u32()? as usize;
+                reader.bytes(key_length)?;
+                let chain_length = reader.u32()?;
+                for _ in 0..chain_length {
+                    entry_certificates.push(reader.certificate(version)?);
+                }
+            }
+            2 => entry_certificates.push(reader.certificate(version)?),
+            3 => {
+                // JCEKS secret keys are serialized Java objects of unknown length, later entries cannot be located
+                debug!("Certificate search: Keystore entry {} is a secret key, skipping the rest of the keystore", alias);
+                break;
+            }
+            _ => return Err(keystore_error(&format!("Unknown keystore entry tag {}", tag))),
+        }
+        certificates.extend(entry_certificates.into_iter()
+            .filter(|(certificate_type, _)| certificate_type == "X.509")
+            .map(|(_, der)| (alias.clone(), der.to_vec())));
+    }
+    Ok(certificates)
+}
+
+/// Certificates of a PKCS#12 file. Certificates encrypted with the store password are skipped,
+/// keystores created for trust (like Java `cacerts`) keep them unencrypted.
+pub fn parse_pkcs12(data: &[u8]) -> Result<Vec<Vec<u8>>, CertificateError> {
+    let pfx = match der_element(data) {
+        Some((DER_SEQUENCE, pfx, _)) => der_elements(pfx),
+        _ => return Err(keystore_error("Not a PKCS#12 file")),
+    };
+    let auth_safe = match pfx.as_slice() {
+        [(DER_INTEGER, [3]), (DER_SEQUENCE, auth_safe), ..] => der_elements(auth_safe),
+        _ => return Err(keystore_error("Not a PKCS#12 file")),
+    };
+    let content_infos = match auth_safe.first() {
+        Some((DER_OID, oid)) if *oid == OID_PKCS7_DATA => explicit_content(&auth_safe, DER_OCTET_STRING)
+            .and_then(der_element)
+            .filter(|(tag, _, _)| *tag == DER_SEQUENCE)
+            .map(|(_, content_infos, _)| der_elements(content_infos))
+            .ok_or_else(|| keystore_error("Malformed PKCS#12 authenticated safe"))?,
+        _ => return Err(keystore_error("PKCS#12 files protected by public keys are not supported")),
+    };
+
+    let mut certificates = Vec::new();
+    for (_, content_info) in content_infos.iter().filter(|(tag, _)| *tag == DER_SEQUENCE) {
+        let content_info = der_elements(content_info);
+        match content_info.first() {
+            Some((DER_OID, oid)) if *oid == OID_PKCS7_DATA => {}
+            Some((DER_OID, oid)) if *oid == OID_PKCS7_ENCRYPTED_DATA => {
+                debug!("Certificate search: Skipping password encrypted PKCS#12 content");
+                continue;
+            }
+            _ => continue,
+        }
+        let safe_bags = match explicit_content(&content_info, DER_OCTET_STRING).and_then(der_element) {
+            Some((DER_SEQUENCE, safe_bags, _)) => der_elements(safe_bags),
+            _ => continue,
+        };
+        for (_, safe_bag) in safe_bags.iter().filter(|(tag, _)| *tag == DER_SEQUENCE) {
+            let safe_bag = der_elements(safe_bag);
+            match safe_bag.first() {
+                Some((DER_OID, oid)) if *oid == OID_PKCS12_CERT_BAG => {}
+                _ => continue,
+            }
+            let cert_bag = match explicit_content(&safe_bag, DER_SEQUENCE) {
+                Some(cert_bag) => der_elements(cert_bag),
+                None => continue,
+            };
+            if let Some((DER_OID, oid)) = cert_bag.first() {
+                if *oid == OID_X509_CERTIFICATE {
+                    certificates.extend(explicit_content(&cert_bag, DER_OCTET_STRING).map(|it| it.to_vec()));
+                }
+            }
+        }
+    }
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cert_sources::{certificates_in_file, parse_java_keystore};
+
+    const EVIL_ROOT_CA_DER: &[u8] = include_bytes!("../tests/fixtures/evil_root_ca.der");
+
+    #[test]
+    fn test_certificates_in_file() {
+        let pem = include_bytes!("../tests/fixtures/evil_root_ca.pem");
+        let p12 = include_bytes!("../tests/fixtures/evil_root_ca.p12");
+        for content in &[&pem[..], EVIL_ROOT_CA_DER, &p12[..]] {
+            assert_eq!(certificates_in_file(content).unwrap(), vec![(EVIL_ROOT_CA_DER.to_vec(), None)]);
+        }
+        assert!(certificates_in_file(b"not a certificate").is_err());
+    }
+
+    #[test]
+    fn test_parse_java_keystore() {
+        let mut jks = vec![0xFE, 0xED, 0xFE, 0xED, 0, 0, 0, 2, 0, 0, 0, 1];
+        jks.extend(&[0, 0, 0, 2, 0, 4]);
+        jks.extend(b"evil");
+        jks.extend(&[0; 8]);
+        jks.extend(&[0, 5]);
+        jks.extend(b"X.509");
+        jks.extend(&(EVIL_ROOT_CA_DER.len() as u32).to_be_bytes());
+        jks.extend(EVIL_ROOT_CA_DER);
+        jks.extend(&[0; 20]);
+        assert_eq!(parse_java_keystore(&jks).unwrap(), vec![("evil".to_string(), EVIL_ROOT_CA_DER.to_vec())]);
+        assert!(parse_java_keystore(&jks[..40]).is_err());
+        assert_eq!(certificates_in_file(&jks).unwrap(), vec![(EVIL_ROOT_CA_DER.to_vec(), Some("evil".to_string()))]);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::data::{Hashed, HashType};
+use crate::hasher::Hasher;
+use chrono::{DateTime, TimeZone, Utc};
+use std::fmt::{Display, Formatter};
+use std::fmt;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::objects::{oid2abbrev, oid_registry};
+use x509_parser::x509::X509Name;
+
+#[derive(Debug)]
+pub struct CertificateError {
+    pub kind: String,
+    pub message: String,
+}
+
+impl Display for CertificateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "CertificateError(kind: {}, message: {})", self.kind, self.message)
+    }
+}
+
+impl<E: std::fmt::Debug> From<x509_parser::nom::Err<E>> for CertificateError {
+    fn from(error: x509_parser::nom::Err<E>) -> Self {
+        CertificateError {
+            kind: "X.509 parsing".to_string(),
+            message: format!("{:?}", error),
+        }
+    }
+}
+
+/// Subject or issuer, `components` are (attribute, value) pairs like ("CN", "Evil Root CA").
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistinguishedName {
+    pub text: String,
+    pub components: Vec<(String, String)>,
+}
+
+impl DistinguishedName {
+    fn new(name: &X509Name) -> Self {
+        let components = name.iter_attributes()
+            .map(|attribute| {
+                let attribute_type = oid2abbrev(attribute.attr_type(), oid_registry())
+                    .map(|it| it.to_string())
+                    .unwrap_or_else(|_| attribute.attr_type().to_id_string());
+                let value = attribute.as_str()
+                    .map(|it| it.to_string())
+                    .or_else(|_| attribute.as_slice().map(hex::encode))
+                    .unwrap_or_default();
+                (attribute_type, value)
+            })
+            .collect();
+        DistinguishedName { text: name.to_string(), components }
+    }
+
+    /// Every component of `other`, given like `CN=Evil Root CA, O=Evil Corp`, is present in any order.
+    pub fn contains_components(&self, other: &str) -> bool {
+        parse_components(other).iter().all(|(attribute_type, value)|
+            self.components.iter().any(|(own_type, own_value)|
+                own_type.eq_ignore_ascii_case(attribute_type) && own_value.eq_ignore_ascii_case(value)
+            )
+        )
+    }
+}
+
+fn parse_components(name: &str) -> Vec<(String, String)> {
+    let mut components = Vec::new();
+    let mut component = String::new();
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => component.extend(chars.next()),
+            ',' | '+' => components.push(std::mem::take(&mut component)),
+            _ => component.push(c),
+        }
+    }
+    components.push(component);
+    components.iter()
+        .filter_map(|it| {
+            let mut parts = it.splitn(2, '=');
+            Some((parts.next()?.trim().to_string(), parts.next()?.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Fields of a certificate IOCs can match on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificateDetails {
+    pub subject: DistinguishedName,
+    pub issuer: DistinguishedName,
+    /// Lowercase hex without separators and leading zeros
+    pub serial: String,
+    pub thumbprints: Vec<Hashed>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    /// Key usage and extended key usage names as in RFC 5280, e.g. `keyCertSign` or `codeSigning`
+    pub key_usage: Vec<String>,
+}
+
+impl CertificateDetails {
+    pub fn thumbprint(&self, algorithm: &HashType) -> Option<&Hashed> {
+        self.thumbprints.iter().find(|it| &it.algorithm == algorithm)
+    }
+}
+
+pub fn parse_certificate(der: &[u8]) -> Result<CertificateDetails, CertificateError> {
+    let (_, certificate) = x509_parser::parse_x509_certificate(der)?;
+    let thumbprints = [HashType::Md5, HashType::Sha1, HashType::Sha256].iter()
+        .filter_map(|algorithm| Hasher::new(algorithm.clone()).hash(der).ok())
+        .collect();
+    Ok(CertificateDetails {
+        subject: DistinguishedName::new(certificate.subject()),
+        issuer: DistinguishedName::new(certificate.issuer()),
+        serial: normalize_serial(&hex::encode(certificate.raw_serial())),
+        thumbprints,
+        not_before: to_date_time(certificate.validity().not_before.timestamp())?,
+        not_after: to_date_time(certificate.validity().not_after.timestamp())?,
+        key_usage: key_usage(&certificate),
+    })
+}
+
+fn to_date_time(timestamp: i64) -> Result<DateTime<Utc>, CertificateError> {
+    Utc.timestamp_opt(timestamp, 0).single().ok_or_else(|| CertificateError {
+        kind: "X.509 parsing".to_string(),
+        message: format!("Validity out of range: {}", timestamp),
+    })
+}
+
+/// Serials are written with or without colons and leading zeros.
+pub fn normalize_serial(serial: &str) -> String {
+    let serial: String = serial.chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let serial = serial.trim_start_matches('0');
+    if serial.is_empty() { "0".to_string() } else { serial.to_string() }
+}
+
+fn key_usage(certificate: &X509Certificate) -> Vec<String> {
+    let mut usages = Vec::new();
+    if let Ok(Some(key_usage)) = certificate.key_usage() {
+        let key_usage = key_usage.value;
+        let flags = [
+            (key_usage.digital_signature(), "digitalSignature"),
+            (key_usage.non_repudiation(), "nonRepudiation"),
+            (key_usage.key_encipherment(), "keyEncipherment"),
+            (key_usage.data_encipherment(), "dataEncipherment"),
+            (key_usage.key_agreement(), "keyAgreement"),
+            (key_usage.key_cert_sign(), "keyCertSign"),
+            (key_usage.crl_sign(), "cRLSign"),
+            (key_usage.encipher_only(), "encipherOnly"),
+            (key_usage.decipher_only(), "decipherOnly"),
+        ];
+        usages.extend(flags.iter().filter(|(set, _)| *set).map(|(_, name)| name.to_string()));
+    }
+    if let Ok(Some(extended_key_usage)) = certificate.extended_key_usage() {
+        let extended_key_usage = extended_key_usage.value;
+        let flags = [
+            (extended_key_usage.any, "anyExtendedKeyUsage"),
+            (extended_key_usage.server_auth, "serverAuth"),
+            (extended_key_usage.client_auth, "clientAuth"),
+            (extended_key_usage.code_signing, "codeSigning"),
+            (extended_key_usage.email_protection, "emailProtection"),
+            (extended_key_usage.time_stamping, "timeStamping"),
+            (extended_key_usage.ocsp_signing, "OCSPSigning"),
+        ];
+        usages.extend(flags.iter().filter(|(set, _)| *set).map(|(_, name)| name.to_string()));
+    }
+    usages
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::certificate::{parse_certificate, normalize_serial};
+    use crate::data::HashType;
+    use chrono::{DateTime, Utc};
+
+    fn evil_root_ca_der() -> Vec<u8> {
+        let pem = include_str!("../tests/fixtures/evil_root_ca.pem");
+        let base64: String = pem.lines().filter(|it| !it.starts_with("-----")).collect();
+        base64::decode(&base64).unwrap()
+    }
+
+    #[test]
+    fn test_parse_certificate() {
+        let certificate = parse_certificate(&evil_root_ca_der()).unwrap();
+        assert_eq!(certificate.subject.text, "C=XX, O=Evil Corp, CN=Evil Root CA");
+        assert!(certificate.subject.contains_components("cn=evil root ca, O=Evil Corp"));
+        assert!(!certificate.issuer.contains_components("CN=Evil Root CA, O=Good Corp"));
+        assert_eq!(certificate.serial, "1337c0de");
+        assert_eq!(certificate.thumbprint(&HashType::Sha1).unwrap().value, "058df3c954232a19f728eb124353ddeb338e81da");
+        assert_eq!(certificate.not_before, "2020-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(certificate.not_after, "2030-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(certificate.key_usage, vec!["keyCertSign", "cRLSign", "codeSigning"]);
+    }
+
+    #[test]
+    fn test_normalize_serial() {
+        assert_eq!(normalize_serial("00:13:37:C0:DE"), "1337c0de");
+        assert_eq!(normalize_serial("00"), "0");
+    }
+}
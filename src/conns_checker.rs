@@ -1,7 +1,18 @@
-use crate::data::{IocEntryId, SearchType, IocId};
-use crate::ioc_evaluator::IocEntrySearchResult;
-use self::netstat::ProtocolSocketInfo;
-use regex::Regex;
+use crate::data::{IocEntryId, SearchType, IocId, ConnectionProtocol, ConnectionState, ConnectionDirection, PortRange,
+                  ProcessInfo, Hashed, HashType, Evidence, ProcessEvidence};
+use crate::hasher::Hasher;
+use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult};
+use crate::pcap_parser::{Capture, Flow};
+use crate::reverse_dns::ReverseDns;
+use crate::search_pattern::{SearchPattern, optional_match};
+use chrono::{DateTime, Utc};
+use self::netstat::{ProtocolSocketInfo, TcpState};
+use ipnetwork::IpNetwork;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use sysinfo::{ProcessExt, SystemExt};
 
 extern crate netstat;
 
@@ -9,84 +20,514 @@ pub struct ConnectionParameters {
     pub ioc_id: IocId,
     pub ioc_entry_id: IocEntryId,
     pub search: SearchType,
-    pub name: String,
+    pub name: Option<String>,
+    pub address: Option<String>,
+    pub protocol: Option<ConnectionProtocol>,
+    pub local_port: Option<PortRange>,
+    pub remote_port: Option<PortRange>,
+    pub state: Option<ConnectionState>,
+    pub direction: Option<ConnectionDirection>,
+    pub process: Option<ProcessInfo>,
 }
 
 struct ConnectionParametersRegexed {
     conn_param: ConnectionParameters,
-    regex: Option<Regex>,
+    name: Option<SearchPattern>,
+    network: Option<IpNetwork>,
+    process_name: Option<SearchPattern>,
+}
+
+/// Process owning a socket, hashes are computed only for the algorithms some IOC asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwningProcess {
+    pub pid: u32,
+    pub name: String,
+    pub exe: PathBuf,
+    pub hashes: Vec<Hashed>,
+}
+
+impl OwningProcess {
+    fn evidence(&self) -> ProcessEvidence {
+        ProcessEvidence {
+            pid: self.pid,
+            name: self.name.clone(),
+            exe: Some(self.exe.display().to_string()),
+            command_line: vec![],
+            hashes: self.hashes.clone(),
+            started: None,
+        }
+    }
+}
+
+/// Socket as seen by the checker, netstat does not report the remote end of UDP sockets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+    pub protocol: ConnectionProtocol,
+    pub local_addr: IpAddr,
+    pub local_port: u16,
+    pub remote_addr: Option<IpAddr>,
+    pub remote_port: Option<u16>,
+    pub state: Option<ConnectionState>,
+    pub direction: Option<ConnectionDirection>,
+}
+
+impl Connection {
+    fn remote(&self) -> String {
+        match (self.remote_addr, self.remote_port) {
+            (Some(addr), Some(port)) if !addr.is_unspecified() => format!("{}:{}", addr, port),
+            _ => "-".to_string(),
+        }
+    }
+
+    /// Remote address worth matching by address or name, listening sockets have none.
+    fn remote_peer(&self) -> Option<&IpAddr> {
+        self.remote_addr.as_ref().filter(|it| !it.is_unspecified())
+    }
 }
 
-pub fn check_conns(search_parameters: Vec<ConnectionParameters>) -> Vec<IocEntrySearchResult> {
+/// Checks open sockets and, when given, the flows of a capture file.
+pub fn check_conns(
+    search_parameters: Vec<ConnectionParameters>,
+    reverse_dns: &mut ReverseDns,
+    capture: Option<&Capture>,
+) -> Vec<IocEntrySearchResult> {
     if search_parameters.is_empty() {
         return vec![]
     }
     info!("Connection search: Searching IOCs using open network connection search.");
     let mut result: Vec<IocEntrySearchResult> = Vec::new();
-    let search_parameters: Vec<ConnectionParametersRegexed> = search_parameters.into_iter().filter_map(|sp| {
-        match sp.search {
-            SearchType::Exact => Some(ConnectionParametersRegexed { conn_param: sp, regex: None }),
-            SearchType::Regex => {
-                match Regex::new(&sp.name) {
-                    Ok(regex) => Some(ConnectionParametersRegexed { conn_param: sp, regex: Some(regex) }),
-                    Err(err) => {
-                        error!("Connection search: {}", err);
-                        None
-                    }
+    let search_parameters: Vec<ConnectionParametersRegexed> = search_parameters.into_iter()
+        .filter_map(compile_parameters)
+        .collect();
+    let reverse_dns_needed = search_parameters.iter().any(|sp| sp.name.is_some());
+    if reverse_dns_needed && !reverse_dns.is_enabled() {
+        warn!("Connection search: Reverse DNS is disabled, IOCs searching by name will not match");
+    }
+
+    let af_flags = netstat::AddressFamilyFlags::IPV4 | netstat::AddressFamilyFlags::IPV6;
+    let proto_flags = netstat::ProtocolFlags::TCP | netstat::ProtocolFlags::UDP;
+
+    let sockets = match netstat::get_sockets_info(af_flags, proto_flags) {
+        Ok(sockets) => sockets,
+        Err(err) => {
+            error!("Connection search: Cannot list sockets: {}", err);
+            vec![]
+        }
+    };
+    let (sockets, pids): (Vec<ProtocolSocketInfo>, Vec<Vec<u32>>) = sockets.into_iter()
+        .map(|it| (it.protocol_socket_info, it.associated_pids))
+        .unzip();
+    let hash_types: Vec<HashType> = search_parameters.iter()
+        .filter_map(|sp| sp.conn_param.process.as_ref().and_then(|it| it.hash.as_ref()))
+        .map(|it| it.algorithm.clone())
+        .collect();
+    let processes = owning_processes(&pids, &hash_types);
+    let connections: Vec<(Connection, &Vec<u32>)> = to_connections(&sockets).into_iter()
+        .zip(pids.iter())
+        .filter(|(conn, _)| !conn.remote_addr.map(|it| it.is_loopback()).unwrap_or(false))
+        .collect();
+    if reverse_dns_needed {
+        reverse_dns.resolve_all(connections.iter().filter_map(|(conn, _)| conn.remote_peer()));
+    }
+    connections.iter()
+        .for_each(|(conn, pids)| {
+            let remote_address_name = conn.remote_peer().and_then(|it| reverse_dns.name_of(it));
+            let owners: Vec<&OwningProcess> = pids.iter().filter_map(|pid| processes.get(pid)).collect();
+
+            search_parameters.iter().for_each(|sp| {
+                debug!("Connection search: Checking {:?} {}:{} -> {} ({}) for IOC {}",
+                       conn.protocol,
+                       conn.local_addr,
+                       conn.local_port,
+                       conn.remote(),
+                       remote_address_name.unwrap_or("-"),
+                       sp.conn_param.ioc_id
+                );
+                check_item(conn, remote_address_name, &owners, None, None, sp, &mut result)
+            });
+        });
+    if let Some(capture) = capture {
+        check_captured_flows(capture, &search_parameters, reverse_dns, reverse_dns_needed, &mut result);
+    }
+    result
+}
+
+/// Names of captured peers come from the DNS answers in the same capture first.
+fn check_captured_flows(
+    capture: &Capture,
+    search_parameters: &[ConnectionParametersRegexed],
+    reverse_dns: &mut ReverseDns,
+    reverse_dns_needed: bool,
+    result: &mut Vec<IocEntrySearchResult>,
+) {
+    let captured_names: HashMap<IpAddr, &str> = capture.dns.iter()
+        .filter_map(|entry| Some((IpAddr::from_str(entry.address.as_ref()?).ok()?, entry.name.as_str())))
+        .collect();
+    let connections: Vec<(Connection, Option<DateTime<Utc>>)> = capture.flows.iter()
+        .map(|flow| (flow_to_connection(flow), flow.first_seen))
+        .collect();
+    if reverse_dns_needed {
+        reverse_dns.resolve_all(connections.iter()
+            .filter_map(|(conn, _)| conn.remote_peer())
+            .filter(|it| !captured_names.contains_key(it))
+        );
+    }
+    for (conn, first_seen) in &connections {
+        let remote_address_name = conn.remote_peer()
+            .and_then(|it| captured_names.get(it).cloned().or_else(|| reverse_dns.name_of(it)));
+        for sp in search_parameters {
+            check_item(conn, remote_address_name, &[], Some(&capture.source), *first_seen, sp, result);
+        }
+    }
+}
+
+/// The client of a captured flow is treated as the local end.
+fn flow_to_connection(flow: &Flow) -> Connection {
+    Connection {
+        protocol: flow.protocol,
+        local_addr: flow.client.0,
+        local_port: flow.client.1,
+        remote_addr: Some(flow.server.0),
+        remote_port: Some(flow.server.1),
+        state: None,
+        direction: if flow.handshake_seen { Some(ConnectionDirection::Outbound) } else { None },
+    }
+}
+
+/// Processes of the sockets' pids, the process list is only read when some socket has an owner.
+fn owning_processes(pids: &[Vec<u32>], hash_types: &[HashType]) -> HashMap<u32, OwningProcess> {
+    let mut processes: HashMap<u32, OwningProcess> = HashMap::new();
+    if pids.iter().all(|it| it.is_empty()) {
+        return processes;
+    }
+    let mut system = sysinfo::System::new();
+    system.refresh_processes();
+    for pid in pids.iter().flatten() {
+        if processes.contains_key(pid) {
+            continue;
+        }
+        let process = match system.get_process(*pid as sysinfo::Pid) {
+            Some(process) => process,
+            None => continue,
+        };
+        let hashes = hash_types.iter()
+            .filter_map(|hash_type| match Hasher::new(hash_type.clone()).hash_file_by_path(process.exe()) {
+                Ok(hash) => Some(hash),
+                Err(err) => {
+                    debug!("Connection search: Cannot hash {}: {}", process.exe().display(), err);
+                    None
+                }
+            })
+            .collect();
+        processes.insert(*pid, OwningProcess {
+            pid: *pid,
+            name: process.name().to_string(),
+            exe: process.exe().to_path_buf(),
+            hashes,
+        });
+    }
+    processes
+}
+
+/// Normalizes netstat sockets, an established TCP socket is inbound when a local socket listens on its port.
+pub fn to_connections(sockets: &[ProtocolSocketInfo]) -> Vec<Connection> {
+    let listening: Vec<(IpAddr, u16)> = sockets.iter()
+        .filter_map(|socket| match socket {
+            ProtocolSocketInfo::Tcp(tcp) if matches!(tcp.state, TcpState::Listen) => Some((tcp.local_addr, tcp.local_port)),
+            _ => None,
+        })
+        .collect();
+    sockets.iter()
+        .map(|socket| match socket {
+            ProtocolSocketInfo::Tcp(tcp) => {
+                let state = tcp_state(&tcp.state);
+                let direction = match state {
+                    Some(ConnectionState::Listen) => Some(ConnectionDirection::Listening),
+                    _ if listening.iter().any(|(addr, port)|
+                        *port == tcp.local_port && (addr.is_unspecified() || *addr == tcp.local_addr)
+                    ) => Some(ConnectionDirection::Inbound),
+                    _ => Some(ConnectionDirection::Outbound),
+                };
+                Connection {
+                    protocol: ConnectionProtocol::Tcp,
+                    local_addr: tcp.local_addr,
+                    local_port: tcp.local_port,
+                    remote_addr: Some(tcp.remote_addr),
+                    remote_port: Some(tcp.remote_port),
+                    state,
+                    direction,
                 }
             }
-        }
-    }).collect();
+            ProtocolSocketInfo::Udp(udp) => Connection {
+                protocol: ConnectionProtocol::Udp,
+                local_addr: udp.local_addr,
+                local_port: udp.local_port,
+                remote_addr: None,
+                remote_port: None,
+                state: None,
+                direction: None,
+            },
+        })
+        .collect()
+}
 
-    let af_flags = netstat::AddressFamilyFlags::IPV4 | netstat::AddressFamilyFlags::IPV6;
-    let proto_flags = netstat::ProtocolFlags::TCP;
-
-    netstat::get_sockets_info(
-        af_flags,
-        proto_flags,
-    ).unwrap().iter()
-        .filter_map(|socket|
-            match &socket.protocol_socket_info {
-                ProtocolSocketInfo::Tcp(tcp_socket) => Some(tcp_socket),
-                ProtocolSocketInfo::Udp(_) => None,
+fn tcp_state(state: &TcpState) -> Option<ConnectionState> {
+    match state {
+        TcpState::Closed => Some(ConnectionState::Closed),
+        TcpState::Listen => Some(ConnectionState::Listen),
+        TcpState::SynSent => Some(ConnectionState::SynSent),
+        TcpState::SynReceived => Some(ConnectionState::SynReceived),
+        TcpState::Established => Some(ConnectionState::Established),
+        TcpState::FinWait1 => Some(ConnectionState::FinWait1),
+        TcpState::FinWait2 => Some(ConnectionState::FinWait2),
+        TcpState::CloseWait => Some(ConnectionState::CloseWait),
+        TcpState::Closing => Some(ConnectionState::Closing),
+        TcpState::LastAck => Some(ConnectionState::LastAck),
+        TcpState::TimeWait => Some(ConnectionState::TimeWait),
+        TcpState::DeleteTcb => None,
+    }
+}
+
+/// A `name` which is an IP address or CIDR block is matched as `address`, older IOCs were written like that.
+fn compile_parameters(sp: ConnectionParameters) -> Option<ConnectionParametersRegexed> {
+    let name_as_network = match (&sp.address, &sp.name, sp.search) {
+        (None, Some(name), SearchType::Exact) => IpNetwork::from_str(name).ok(),
+        _ => None,
+    };
+    let network = match (&sp.address, name_as_network) {
+        (_, Some(network)) => Some(network),
+        (None, None) => None,
+        (Some(address), None) => match IpNetwork::from_str(address.trim()) {
+            Ok(network) => Some(network),
+            Err(err) => {
+                error!("Connection search: Cannot parse address {} of IOC {}: {:?}", address, sp.ioc_id, err);
+                return None;
             }
-        )
-        .filter(|socket| !socket.remote_addr.is_loopback())
-        .filter(|socket| !socket.remote_addr.is_unspecified())
-        .for_each(|socket| {
-                let remote_address = &socket.remote_addr;
-                let remote_address_name = dns_lookup::lookup_addr(remote_address);
-
-                search_parameters.iter().for_each(|sp| {
-                    if remote_address_name.is_ok() {
-                        debug!("Connection search: Checking address {} for IOC {}", remote_address_name.as_ref().unwrap(), &sp.conn_param.name);
-                        check_item(remote_address_name.as_ref().unwrap(), sp, &mut result)
-                    }
-                });
-    });
-    result
+        },
+    };
+    let process_name = match sp.process.as_ref()
+        .and_then(|process| process.name.as_ref().map(|name| SearchPattern::new_ignore_case(process.search, name)))
+        .transpose() {
+        Ok(process_name) => process_name,
+        Err(err) => {
+            error!("Connection search: {}", err);
+            return None;
+        }
+    };
+    let name = if name_as_network.is_some() {
+        None
+    } else {
+        match sp.name.as_ref().map(|it| SearchPattern::new_ignore_case(sp.search, it)).transpose() {
+            Ok(name) => name,
+            Err(err) => {
+                error!("Connection search: {}", err);
+                return None;
+            }
+        }
+    };
+    if network.is_none() && name.is_none() && sp.protocol.is_none() && sp.local_port.is_none()
+        && sp.remote_port.is_none() && sp.state.is_none() && sp.direction.is_none() && sp.process.is_none() {
+        warn!("Connection search: IOC {} specifies nothing to search for, skipping", sp.ioc_id);
+        return None;
+    }
+    Some(ConnectionParametersRegexed { conn_param: sp, name, network, process_name })
 }
 
 fn check_item(
-    address_name: &String,
+    conn: &Connection,
+    address_name: Option<&str>,
+    owners: &[&OwningProcess],
+    captured_in: Option<&str>,
+    first_seen: Option<DateTime<Utc>>,
     sp: &ConnectionParametersRegexed,
     result: &mut Vec<IocEntrySearchResult>,
 ) {
-    let matches = match &sp.regex {
-        None => &sp.conn_param.name == address_name,
-        Some(regex) => regex.is_match(address_name),
+    if !connection_matches(conn, address_name, sp) {
+        return;
+    }
+    let owners: Vec<&&OwningProcess> = match &sp.conn_param.process {
+        None => owners.iter().collect(),
+        Some(process) => owners.iter()
+            .filter(|owner| process_matches(owner, &sp.process_name, &process.hash))
+            .collect(),
     };
-    if matches {
-        let message =
-            format!("Connection search: Found connection {} for IOC {}",
-              address_name.clone(),
-              sp.conn_param.ioc_id
-        );
-        info!("{}", message);
-        result.push(IocEntrySearchResult {
-            ioc_id: sp.conn_param.ioc_id,
-            ioc_entry_id: sp.conn_param.ioc_entry_id,
-            description: message
-        });
+    if sp.conn_param.process.is_some() && owners.is_empty() {
+        return;
     }
-}
\ No newline at end of file
+    let owner_description = if let Some(capture) = captured_in {
+        format!("capture {}", capture)
+    } else if owners.is_empty() {
+        "unknown process".to_string()
+    } else {
+        owners.iter()
+            .map(|it| format!("process {} (pid {}, {})", it.name, it.pid, it.exe.display()))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let message =
+        format!("Connection search: Found {:?} connection {}:{} -> {} ({}) of {} for IOC {}",
+          conn.protocol,
+          conn.local_addr,
+          conn.local_port,
+          conn.remote(),
+          address_name.unwrap_or("-"),
+          owner_description,
+          sp.conn_param.ioc_id
+    );
+    info!("{}", message);
+    result.push(IocEntrySearchResult {
+        ioc_id: sp.conn_param.ioc_id,
+        ioc_entry_id: sp.conn_param.ioc_entry_id,
+        check: CheckKind::Connection,
+        description: message,
+        timestamp: first_seen,
+        evidence: Evidence::Connection {
+            protocol: conn.protocol,
+            local_address: conn.local_addr,
+            local_port: conn.local_port,
+            remote_address: conn.remote_addr,
+            remote_port: conn.remote_port,
+            remote_name: address_name.map(|it| it.to_string()),
+            state: conn.state,
+            direction: conn.direction,
+            processes: owners.iter().map(|it| it.evidence()).collect(),
+            capture: captured_in.map(|it| it.to_string()),
+        },
+    });
+}
+
+pub fn process_matches(process: &OwningProcess, name: &Option<SearchPattern>, hash: &Option<Hashed>) -> bool {
+    optional_match(name, Some(&process.name))
+        && hash.as_ref().map(|hash| process.hashes.contains(hash)).unwrap_or(true)
+}
+
+fn connection_matches(conn: &Connection, address_name: Option<&str>, sp: &ConnectionParametersRegexed) -> bool {
+    let param = &sp.conn_param;
+    let address_matches = match (&sp.network, conn.remote_peer()) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(network), Some(address)) => network_contains(network, address),
+    };
+    let name_matches = match (&sp.name, address_name) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(name), Some(address_name)) => name.is_match(address_name),
+    };
+    address_matches && name_matches
+        && param.protocol.map(|it| it == conn.protocol).unwrap_or(true)
+        && param.local_port.map(|it| it.contains(conn.local_port)).unwrap_or(true)
+        && param.remote_port.map(|it| conn.remote_port.map(|port| it.contains(port)).unwrap_or(false)).unwrap_or(true)
+        && param.state.map(|it| conn.state == Some(it)).unwrap_or(true)
+        && param.direction.map(|it| conn.direction == Some(it)).unwrap_or(true)
+}
+
+/// Dual stack sockets report IPv4 peers as IPv4-mapped IPv6 addresses (::ffff:a.b.c.d).
+pub fn network_contains(network: &IpNetwork, address: &IpAddr) -> bool {
+    if network.contains(*address) {
+        return true;
+    }
+    match address {
+        IpAddr::V6(address) => {
+            let segments = address.segments();
+            if segments[..5].iter().all(|it| *it == 0) && segments[5] == 0xFFFF {
+                let mapped = Ipv4Addr::new(
+                    (segments[6] >> 8) as u8, segments[6] as u8,
+                    (segments[7] >> 8) as u8, segments[7] as u8,
+                );
+                network.contains(IpAddr::V4(mapped))
+            } else {
+                false
+            }
+        }
+        IpAddr::V4(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conns_checker::{network_contains, to_connections, process_matches, OwningProcess};
+    use crate::data::{ConnectionDirection, ConnectionProtocol, ConnectionState, PortRange, Hashed, HashType, SearchType};
+    use crate::search_pattern::SearchPattern;
+    use std::path::PathBuf;
+    use ipnetwork::IpNetwork;
+    use netstat::{ProtocolSocketInfo, TcpSocketInfo, TcpState, UdpSocketInfo};
+    use std::net::IpAddr;
+    use std::str::FromStr;
+
+    fn contains(network: &str, address: &str) -> bool {
+        network_contains(&IpNetwork::from_str(network).unwrap(), &IpAddr::from_str(address).unwrap())
+    }
+
+    #[test]
+    fn test_network_contains() {
+        assert!(contains("185.220.101.4", "185.220.101.4"));
+        assert!(!contains("185.220.101.4", "185.220.101.5"));
+        assert!(contains("185.220.100.0/22", "185.220.101.5"));
+        assert!(contains("185.220.100.0/22", "::ffff:185.220.101.5"));
+        assert!(contains("2001:db8::/32", "2001:db8:1::1"));
+        assert!(!contains("2001:db8::/32", "2001:db9::1"));
+        assert!(!contains("10.0.0.0/8", "2001:db8::1"));
+    }
+
+    fn tcp(local: &str, local_port: u16, remote: &str, remote_port: u16, state: TcpState) -> ProtocolSocketInfo {
+        ProtocolSocketInfo::Tcp(TcpSocketInfo {
+            local_addr: IpAddr::from_str(local).unwrap(),
+            local_port,
+            remote_addr: IpAddr::from_str(remote).unwrap(),
+            remote_port,
+            state,
+        })
+    }
+
+    #[test]
+    fn test_to_connections() {
+        let connections = to_connections(&[
+            tcp("0.0.0.0", 4444, "0.0.0.0", 0, TcpState::Listen),
+            tcp("10.0.0.5", 4444, "203.0.113.7", 51234, TcpState::Established),
+            tcp("10.0.0.5", 49822, "198.51.100.1", 443, TcpState::Established),
+            ProtocolSocketInfo::Udp(UdpSocketInfo {
+                local_addr: IpAddr::from_str("0.0.0.0").unwrap(),
+                local_port: 53,
+            }),
+        ]);
+        let summary: Vec<_> = connections.iter()
+            .map(|it| (it.protocol, it.local_port, it.state, it.direction))
+            .collect();
+        assert_eq!(summary, vec![
+            (ConnectionProtocol::Tcp, 4444, Some(ConnectionState::Listen), Some(ConnectionDirection::Listening)),
+            (ConnectionProtocol::Tcp, 4444, Some(ConnectionState::Established), Some(ConnectionDirection::Inbound)),
+            (ConnectionProtocol::Tcp, 49822, Some(ConnectionState::Established), Some(ConnectionDirection::Outbound)),
+            (ConnectionProtocol::Udp, 53, None, None),
+        ]);
+        assert_eq!(connections[3].remote_port, None);
+    }
+
+    #[test]
+    fn test_port_range() {
+        assert!(PortRange::Port(4444).contains(4444));
+        assert!(!PortRange::Port(4444).contains(4445));
+        assert!(PortRange::Range { from: 8000, to: 8100 }.contains(8100));
+        assert!(!PortRange::Range { from: 8000, to: 8100 }.contains(7999));
+        let range: PortRange = serde_json::from_str(r#"{"from": 1, "to": 2}"#).unwrap();
+        assert_eq!(range, PortRange::Range { from: 1, to: 2 });
+        assert_eq!(serde_json::from_str::<PortRange>("53").unwrap(), PortRange::Port(53));
+    }
+
+    #[test]
+    fn test_process_matches() {
+        let process = OwningProcess {
+            pid: 812,
+            name: "svchost.exe".to_string(),
+            exe: PathBuf::from("C:\\Windows\\System32\\svchost.exe"),
+            hashes: vec![Hashed { algorithm: HashType::Md5, value: "8A0A29438052FAED8A2532DA50455756".to_string() }],
+        };
+        let svchost = Some(SearchPattern::new_ignore_case(SearchType::Exact, "SVCHOST.EXE").unwrap());
+        let md5 = |value: &str| Some(Hashed { algorithm: HashType::Md5, value: value.to_string() });
+        assert!(process_matches(&process, &None, &None));
+        assert!(process_matches(&process, &svchost, &None));
+        assert!(process_matches(&process, &svchost, &md5("8a0a29438052faed8a2532da50455756")));
+        assert!(!process_matches(&process, &svchost, &md5("00000000000000000000000000000000")));
+        let other = Some(SearchPattern::new_ignore_case(SearchType::Regex, "^powershell").unwrap());
+        assert!(!process_matches(&process, &other, &None));
+    }
+}
@@ -1,11 +1,108 @@
-use crate::data::{IocEntryId};
+use crate::data::{DetailedIoc, Finding, Ioc, IocId, ScoredIoc, Severity};
+use crate::ioc_evaluator::{IocEntrySearchResult, IocEvaluator, IocScore};
 use chrono::Utc;
 
 impl crate::data::ReportUploadRequest {
-    pub fn new(found_iocs: Vec<IocEntryId>) -> Self {
+    pub fn new(ioc_scores: &[IocScore], iocs: &[Ioc]) -> Self {
+        let scored = |hit: bool| ioc_scores.iter()
+            .filter(|it| it.hit == hit)
+            .map(|it| ScoredIoc {
+                ioc_id: it.ioc_id,
+                severity: severity_of(iocs, it.ioc_id),
+                score: it.score,
+            })
+            .collect();
         crate::data::ReportUploadRequest {
             datetime: Utc::now(),
-            found_iocs
+            found_iocs: scored(true),
+            near_misses: scored(false),
         }
     }
 }
+
+impl crate::data::DetailedReportUploadRequest {
+    pub fn new(ioc_scores: &[IocScore], iocs: &[Ioc], evaluator: &IocEvaluator, results: &[IocEntrySearchResult]) -> Self {
+        let detailed = |hit: bool| ioc_scores.iter()
+            .filter(|it| it.hit == hit)
+            .map(|it| DetailedIoc {
+                ioc_id: it.ioc_id,
+                severity: severity_of(iocs, it.ioc_id),
+                score: it.score,
+                findings: findings_of(results, evaluator, it.ioc_id),
+            })
+            .collect();
+        crate::data::DetailedReportUploadRequest {
+            datetime: Utc::now(),
+            found_iocs: detailed(true),
+            near_misses: detailed(false),
+        }
+    }
+}
+
+impl From<&IocEntrySearchResult> for Finding {
+    fn from(result: &IocEntrySearchResult) -> Self {
+        Finding {
+            ioc_entry_id: result.ioc_entry_id,
+            description: result.description.clone(),
+            timestamp: result.timestamp,
+            evidence: result.evidence.clone(),
+        }
+    }
+}
+
+/// Results of the IOC's entries, except those of negated entries which count against the IOC.
+pub fn findings_of(results: &[IocEntrySearchResult], evaluator: &IocEvaluator, ioc_id: IocId) -> Vec<Finding> {
+    let negated = evaluator.negated_entries(ioc_id);
+    results.iter()
+        .filter(|it| it.ioc_id == ioc_id && !negated.contains(&it.ioc_entry_id))
+        .map(Finding::from)
+        .collect()
+}
+
+fn severity_of(iocs: &[Ioc], ioc_id: IocId) -> Severity {
+    iocs.iter().find(|ioc| ioc.id == ioc_id).map(|ioc| ioc.severity).unwrap_or(Severity::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{DetailedReportUploadRequest, Evidence, Ioc, IocEntryId, Severity};
+    use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult, IocEvaluator, IocScore};
+    use crate::ioc_graph::IocGraphBuilder;
+
+    #[test]
+    fn test_detailed_report() {
+        let iocs: Vec<Ioc> = serde_json::from_str(r#"[
+            {"id": 1, "severity": "HIGH", "definition": {"childEvalPolicy": "ALL", "offspring": [{}, {"evalPolicy": "NONE"}]}},
+            {"id": 2, "definition": {}}
+        ]"#).unwrap();
+        let mut builder = IocGraphBuilder::new();
+        for ioc in &iocs {
+            builder.add_ioc(ioc, |_, _, _| 1).unwrap();
+        }
+        let ioc_scores = [
+            IocScore { ioc_id: 1, score: 100, hit: true },
+            IocScore { ioc_id: 2, score: 60, hit: false },
+        ];
+        let result = |ioc_entry_id: IocEntryId, name: &str| IocEntrySearchResult {
+            ioc_id: 1,
+            ioc_entry_id,
+            check: CheckKind::KernelModule,
+            description: format!("Kernel module search: Found module {} for IOC 1", name),
+            timestamp: None,
+            evidence: Evidence::KernelModule { name: name.to_string(), hidden: true },
+        };
+        // Entry 3 is negated, its result counts against the IOC
+        let results = [result(2, "diamorphine"), result(3, "reptile")];
+        let evaluator = IocEvaluator::new(builder.build().unwrap(), &results);
+        let request = DetailedReportUploadRequest::new(&ioc_scores, &iocs, &evaluator, &results);
+        assert_eq!(request.found_iocs.len(), 1);
+        assert_eq!(request.found_iocs[0].severity, Severity::High);
+        assert_eq!(request.found_iocs[0].findings.len(), 1);
+        assert_eq!(request.found_iocs[0].findings[0].ioc_entry_id, 2);
+        assert_eq!(request.near_misses[0].severity, Severity::Medium);
+        assert!(request.near_misses[0].findings.is_empty());
+
+        let json = serde_json::to_value(&request.found_iocs[0].findings[0]).unwrap();
+        assert_eq!(json["evidence"], serde_json::json!({"type": "KERNEL_MODULE", "name": "diamorphine", "hidden": true}));
+    }
+}
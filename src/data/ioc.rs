@@ -2,6 +2,7 @@ use serde::{Serialize, Deserialize};
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter, Result};
 use chrono::{DateTime, Utc};
+use std::net::IpAddr;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -63,19 +64,111 @@ pub struct FileInfo {
     pub search: SearchType,
     pub name: Option<String>,
     pub hash: Option<Hashed>,
+    #[serde(default)]
+    pub signature: Option<SignatureInfo>,
 }
 
+/// Authenticode signature of a PE file. An unsigned file matches only a block without signer conditions,
+/// with `signed` or `valid` not being `true`. Files signed in a Windows catalog count as signed and valid, but
+/// match no signer conditions. Files over 256 MiB never match.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureInfo {
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    #[serde(default)]
+    pub signed: Option<bool>,
+    /// Image hash, signer signature and the embedded chain verify; trust and revocation are not checked
+    #[serde(default)]
+    pub valid: Option<bool>,
+    /// Signer subject; exact search requires the given components, regex matches the whole name
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// Signer certificate serial
+    #[serde(default)]
+    pub serial: Option<String>,
+    /// Thumbprint of the signer or any embedded certificate of its chain
+    #[serde(default)]
+    pub thumbprint: Option<Hashed>,
+}
+
+/// How the found checks and children of an entry confirm it, e.g. `"ALL"`, `{"AT_LEAST": 3}` or `{"PERCENTAGE": 60}`.
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum EvaluationPolicy {
     All,
     One,
+    /// Negation, nothing may be found
+    None,
+    AtLeast(u32),
+    /// Found share in percent, IOCs with values over 100 are rejected
+    Percentage(u8),
 }
 
 impl EvaluationPolicy {
     pub fn default() -> EvaluationPolicy { EvaluationPolicy::One }
 }
 
+impl Display for EvaluationPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            EvaluationPolicy::All => write!(f, "ALL"),
+            EvaluationPolicy::One => write!(f, "ONE"),
+            EvaluationPolicy::None => write!(f, "NONE"),
+            EvaluationPolicy::AtLeast(count) => write!(f, "AT_LEAST {}", count),
+            EvaluationPolicy::Percentage(percentage) => write!(f, "PERCENTAGE {}", percentage),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn default() -> Severity { Severity::Medium }
+}
+
+/// Order of the timestamps of an entry relative to a sibling entry.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TemporalRelation {
+    Before,
+    After,
+    /// Either way, `seconds` apart at most
+    Within,
+}
+
+impl Display for TemporalRelation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            TemporalRelation::Before => write!(f, "BEFORE"),
+            TemporalRelation::After => write!(f, "AFTER"),
+            TemporalRelation::Within => write!(f, "WITHIN"),
+        }
+    }
+}
+
+/// Something found by the entry has to be timed relative to something found by the sibling entry named `sibling`,
+/// e.g. a file written at most 300 seconds after a process started.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TemporalConstraint {
+    pub relation: TemporalRelation,
+    pub sibling: String,
+    /// Largest gap between the timestamps, required by `WITHIN`
+    #[serde(default)]
+    pub seconds: Option<u64>,
+}
+
 pub type IocId = u64;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash)]
@@ -84,6 +177,12 @@ pub struct Ioc {
     pub id: IocId,
     #[serde(default)]
     pub name: Option<String>,
+    #[serde(default = "Severity::default")]
+    pub severity: Severity,
+    /// Score from 0 to 100 from which the IOC is reported as found, 100 by default, i.e. the definition
+    /// has to be confirmed by its evaluation policies
+    #[serde(default)]
+    pub score_threshold: Option<u8>,
     pub definition: IocEntry,
 }
 
@@ -120,6 +219,12 @@ pub struct IocEntry {
     pub child_eval_policy: EvaluationPolicy,
     #[serde(default)]
     pub offspring: Option<Vec<IocEntry>>,
+    /// Share of the entry in the score of its parent relative to its siblings, 1 by default
+    #[serde(default)]
+    pub weight: Option<u32>,
+    /// Timing relative to sibling entries, all of them have to hold for the entry to be confirmed
+    #[serde(default)]
+    pub temporal_constraints: Vec<TemporalConstraint>,
     #[serde(default)]
     pub registry_check: Option<RegistryInfo>,
     #[serde(default)]
@@ -127,13 +232,31 @@ pub struct IocEntry {
     #[serde(default)]
     pub mutex_check: Option<MutexInfo>,
     #[serde(default)]
+    pub object_check: Option<ObjectInfo>,
+    #[serde(default)]
     pub process_check: Option<ProcessInfo>,
     #[serde(default)]
     pub dns_check: Option<DnsInfo>,
     #[serde(default)]
     pub conns_check: Option<ConnectionsInfo>,
     #[serde(default)]
-    pub certs_check: Option<CertsInfo>
+    pub certs_check: Option<CertsInfo>,
+    #[serde(default)]
+    pub scheduled_task_check: Option<ScheduledTaskInfo>,
+    #[serde(default)]
+    pub shortcut_check: Option<ShortcutInfo>,
+    #[serde(default)]
+    pub service_check: Option<ServiceInfo>,
+    #[serde(default)]
+    pub ssh_key_check: Option<SshKeyInfo>,
+    #[serde(default)]
+    pub account_check: Option<AccountInfo>,
+    #[serde(default)]
+    pub kernel_module_check: Option<KernelModuleInfo>,
+    #[serde(default)]
+    pub preload_check: Option<PreloadInfo>,
+    #[serde(default)]
+    pub environment_check: Option<EnvironmentInfo>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash)]
@@ -151,7 +274,174 @@ pub struct GetIocResponse
 #[serde(rename_all = "camelCase")]
 pub struct ReportUploadRequest {
     pub datetime: DateTime<Utc>,
-    pub found_iocs: Vec<IocId>,
+    pub found_iocs: Vec<ScoredIoc>,
+    /// IOCs scoring under their threshold but at least the reporting floor
+    pub near_misses: Vec<ScoredIoc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoredIoc {
+    pub ioc_id: IocId,
+    pub severity: Severity,
+    pub score: u8,
+}
+
+/// Upload carrying what was found for each IOC, sent instead of [`ReportUploadRequest`] when `detailed_report` is set.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailedReportUploadRequest {
+    pub datetime: DateTime<Utc>,
+    pub found_iocs: Vec<DetailedIoc>,
+    pub near_misses: Vec<DetailedIoc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailedIoc {
+    pub ioc_id: IocId,
+    pub severity: Severity,
+    pub score: u8,
+    pub findings: Vec<Finding>,
+}
+
+/// Result of a single check of an IOC entry.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    pub ioc_entry_id: IocEntryId,
+    pub description: String,
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    pub evidence: Evidence,
+}
+
+/// What a check found, tagged by `type`, e.g. `{"type": "FILE", "path": "/tmp/dropper", ...}`.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Evidence {
+    #[serde(rename_all = "camelCase")]
+    File {
+        path: String,
+        /// MD5, SHA1 and SHA256 of the content, missing when the file cannot be read
+        hashes: Vec<Hashed>,
+        size: Option<u64>,
+        created: Option<DateTime<Utc>>,
+        modified: Option<DateTime<Utc>>,
+    },
+    Process(ProcessEvidence),
+    #[serde(rename_all = "camelCase")]
+    Connection {
+        protocol: ConnectionProtocol,
+        local_address: IpAddr,
+        local_port: u16,
+        remote_address: Option<IpAddr>,
+        remote_port: Option<u16>,
+        /// Resolved name of the remote address
+        remote_name: Option<String>,
+        state: Option<ConnectionState>,
+        direction: Option<ConnectionDirection>,
+        processes: Vec<ProcessEvidence>,
+        /// Packet capture the connection was seen in
+        capture: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Dns {
+        name: String,
+        address: Option<String>,
+        source: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Registry {
+        key: String,
+        value_name: String,
+        value: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Certificate {
+        subject: String,
+        issuer: String,
+        serial: String,
+        thumbprints: Vec<Hashed>,
+        sources: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Object {
+        kind: String,
+        name: String,
+        location: Option<String>,
+        /// Processes holding the object
+        pids: Vec<u32>,
+    },
+    #[serde(rename_all = "camelCase")]
+    ScheduledTask {
+        path: String,
+        name: String,
+        author: Option<String>,
+        /// Command line of each action
+        actions: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Shortcut {
+        path: String,
+        target: Option<String>,
+        arguments: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Service {
+        /// Registry key of the live service or the `.reg` file it was exported to
+        source: String,
+        name: String,
+        display_name: Option<String>,
+        image_path: Option<String>,
+        service_dll: Option<String>,
+        start: Option<u32>,
+    },
+    #[serde(rename_all = "camelCase")]
+    SshKey {
+        path: String,
+        user: String,
+        line: usize,
+        key_type: String,
+        /// SHA256 fingerprint as printed by `ssh-keygen -l`
+        fingerprint: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Account {
+        name: String,
+        uid: u32,
+        gid: u32,
+        shell: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    KernelModule {
+        name: String,
+        hidden: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    Preload {
+        library: String,
+        source: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Environment {
+        pid: u32,
+        process_name: String,
+        name: String,
+        value: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessEvidence {
+    pub pid: u32,
+    pub name: String,
+    pub exe: Option<String>,
+    pub command_line: Vec<String>,
+    /// MD5, SHA1 and SHA256 of the executable
+    pub hashes: Vec<Hashed>,
+    pub started: Option<DateTime<Utc>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash)]
@@ -159,13 +449,41 @@ pub struct ReportUploadRequest {
 pub struct PrettyReport {
     pub ioc_id: IocId,
     pub name: String,
-    pub search_reports: Vec<String>
+    pub severity: Severity,
+    pub score: u8,
+    pub findings: Vec<Finding>,
+    pub evaluation: EvaluationTrace,
+}
+
+/// How an IOC entry was evaluated, with the traces of its offspring.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationTrace {
+    pub ioc_entry_id: IocEntryId,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub eval_policy: EvaluationPolicy,
+    pub child_eval_policy: EvaluationPolicy,
+    pub checks_specified: u32,
+    pub checks_found: u32,
+    /// Descriptions of what the checks of this entry found
+    pub search_reports: Vec<String>,
+    /// Whether the offspring satisfied the child evaluation policy, none without offspring
+    #[serde(default)]
+    pub offspring_satisfied: Option<bool>,
+    /// Whether the temporal constraints held, none without constraints
+    #[serde(default)]
+    pub temporal_constraints_satisfied: Option<bool>,
+    pub offspring: Vec<EvaluationTrace>,
+    pub confirmed: bool,
+    pub score: u8,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct PrettyReportList {
     pub found_iocs: Vec<PrettyReport>,
+    pub near_misses: Vec<PrettyReport>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash)]
@@ -178,26 +496,168 @@ pub struct IocSearchResult {
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct DnsInfo {
-    pub name: String
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    /// Domain in ASCII (punycode) or Unicode form
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Exact search also matches subdomains of `name`
+    #[serde(default)]
+    pub include_subdomains: bool,
+    /// IPv4/IPv6 address or CIDR block the name resolved to
+    #[serde(default)]
+    pub address: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct CertsInfo {
-    pub name: String
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    /// Value of any subject component, e.g. the common name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Distinguished name components like `CN=Evil Root CA, O=Evil Corp`, matched in any order
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// Hex serial number, colons and leading zeros are ignored
+    #[serde(default)]
+    pub serial: Option<String>,
+    #[serde(default)]
+    pub thumbprint: Option<Hashed>,
+    /// Exact start of the validity period
+    #[serde(default)]
+    pub not_before: Option<DateTime<Utc>>,
+    /// Exact end of the validity period
+    #[serde(default)]
+    pub not_after: Option<DateTime<Utc>>,
+    /// Time within the validity period
+    #[serde(default)]
+    pub valid_at: Option<DateTime<Utc>>,
+    /// Validity starting at or after this time
+    #[serde(default)]
+    pub issued_after: Option<DateTime<Utc>>,
+    /// Validity ending at or before this time
+    #[serde(default)]
+    pub expires_before: Option<DateTime<Utc>>,
+    /// Key usages and extended key usages the certificate must all have, e.g. `keyCertSign`, `codeSigning`
+    #[serde(default)]
+    pub key_usage: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct MutexInfo {
+    /// Exact search matches name prefixes, regex searches the whole name. Windows names are matched
+    /// as the kernel reports them, e.g. `\BaseNamedObjects\M_<guid>` for `Global\M_<guid>`.
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
     pub name: String
 }
 
+/// Named kernel object, a mutex check is an object check of the `MUTANT` type.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectInfo {
+    /// Exact search matches name prefixes, regex searches the whole name
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    pub object_type: ObjectType,
+    pub name: String,
+}
+
+/// Windows object types. On Linux mutants are matched by semaphores, shared memory, abstract sockets
+/// and lock files, sections by shared memory and named pipes by FIFOs and UNIX sockets.
+/// Linux has no named events.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ObjectType {
+    Mutant,
+    Event,
+    Section,
+    Semaphore,
+    NamedPipe,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ConnectionsInfo {
+    #[serde(default = "SearchType::default")]
     pub search: SearchType,
-    pub name: String
+    /// Reverse DNS name of the remote address
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Remote IPv4/IPv6 address or CIDR block. Open UDP sockets have no remote end, `name`, `address` and
+    /// `remotePort` only match their flows in packet captures.
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub protocol: Option<ConnectionProtocol>,
+    #[serde(default)]
+    pub local_port: Option<PortRange>,
+    #[serde(default)]
+    pub remote_port: Option<PortRange>,
+    /// TCP state, UDP sockets have none
+    #[serde(default)]
+    pub state: Option<ConnectionState>,
+    #[serde(default)]
+    pub direction: Option<ConnectionDirection>,
+    /// Name and/or executable hash of the process owning the socket
+    #[serde(default)]
+    pub process: Option<ProcessInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConnectionProtocol {
+    Tcp,
+    Udp,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConnectionState {
+    Closed,
+    Listen,
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    Closing,
+    LastAck,
+    TimeWait,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConnectionDirection {
+    /// Socket waiting for connections
+    Listening,
+    /// Connection accepted by a local listening socket
+    Inbound,
+    /// Connection initiated from this machine
+    Outbound,
+}
+
+/// Either a single port (`4444`) or an inclusive range (`{"from": 8000, "to": 8100}`).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum PortRange {
+    Port(u16),
+    Range { from: u16, to: u16 },
+}
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        match *self {
+            PortRange::Port(expected) => port == expected,
+            PortRange::Range { from, to } => from <= port && port <= to,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
@@ -209,4 +669,123 @@ pub struct ProcessInfo {
     pub hash: Option<Hashed>,
     #[serde(default)]
     pub name: Option<String>
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledTaskInfo {
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub arguments: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutInfo {
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub arguments: Option<String>,
+}
+
+/// Windows service, offline from `regedit` exports of `HKLM\SYSTEM\CurrentControlSet\Services`.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceInfo {
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    /// Service name or display name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Command line the service runs, as stored, e.g. `%SystemRoot%\system32\svchost.exe -k netsvcs`
+    #[serde(default)]
+    pub image_path: Option<String>,
+    /// `Parameters\ServiceDll` loaded by svchost
+    #[serde(default)]
+    pub service_dll: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SshKeyInfo {
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AccountPredicate {
+    /// Account other than root with UID 0
+    AdditionalUidZero,
+    /// Account which can log in without password, requires readable /etc/shadow
+    EmptyPassword,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountInfo {
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub uid: Option<u32>,
+    #[serde(default)]
+    pub gid: Option<u32>,
+    #[serde(default)]
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub sudo_rule: Option<String>,
+    #[serde(default)]
+    pub predicate: Option<AccountPredicate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KernelModuleInfo {
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Match only modules missing from either /proc/modules or /sys/module
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PreloadInfo {
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    /// Any preloaded library matches when not specified
+    #[serde(default)]
+    pub library: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentInfo {
+    #[serde(default = "SearchType::default")]
+    pub search: SearchType,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub value: Option<String>,
+}
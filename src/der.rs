@@ -0,0 +1,58 @@
+/// Tags of the DER reading below, which covers the PKCS#7 and PKCS#12 structures x509-parser does not
+pub const DER_SEQUENCE: u8 = 0x30;
+pub const DER_INTEGER: u8 = 0x02;
+pub const DER_BIT_STRING: u8 = 0x03;
+pub const DER_OCTET_STRING: u8 = 0x04;
+pub const DER_OID: u8 = 0x06;
+pub const DER_EXPLICIT_0: u8 = 0xA0;
+pub const DER_SET: u8 = 0x31;
+
+/// Splits a DER element into its tag, content and the data following it. Only definite lengths are supported.
+pub fn der_element(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (length, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7F) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let length = rest[..count].iter().fold(0usize, |length, byte| length << 8 | *byte as usize);
+        (length, &rest[count..])
+    };
+    if rest.len() < length {
+        return None;
+    }
+    Some((tag, &rest[..length], &rest[length..]))
+}
+
+pub fn der_elements(mut data: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut elements = Vec::new();
+    while let Some((tag, content, rest)) = der_element(data) {
+        elements.push((tag, content));
+        data = rest;
+    }
+    elements
+}
+
+/// Content of the second element wrapped in `[0] EXPLICIT`, as the value of ContentInfo and SafeBag.
+pub fn explicit_content<'a>(elements: &[(u8, &'a [u8])], expected_tag: u8) -> Option<&'a [u8]> {
+    match elements.get(1) {
+        Some((DER_EXPLICIT_0, wrapped)) => match der_element(wrapped)? {
+            (tag, content, _) if tag == expected_tag => Some(content),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Complete encodings of consecutive elements, for the parts that are hashed or signed as they are.
+pub fn der_raw_elements(mut data: &[u8]) -> Vec<&[u8]> {
+    let mut elements = Vec::new();
+    while let Some((_, _, rest)) = der_element(data) {
+        elements.push(&data[..data.len() - rest.len()]);
+        data = rest;
+    }
+    elements
+}
@@ -307,6 +307,7 @@ pub fn resolve(path: PathBuf) -> PathBuf {
     path
 }
 
+#[cfg(windows)]
 fn resolve_one(path: PathBuf, prefix: &str, prefix_resolved: &Option<PathBuf>) -> PathBuf {
     if !path.starts_with(prefix) {
         return path
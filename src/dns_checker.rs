@@ -1,52 +1,182 @@
-use crate::data::{IocEntryId, IocId};
-use crate::ioc_evaluator::IocEntrySearchResult;
+use crate::conns_checker::network_contains;
+use crate::data::{IocEntryId, IocId, SearchType, Evidence};
+use crate::dns_sources::DnsEntry;
+use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult};
+use crate::pcap_parser::Capture;
+use crate::search_pattern::SearchPattern;
+use ipnetwork::IpNetwork;
+use std::net::IpAddr;
+#[cfg(windows)]
 use std::process::Command;
+use std::str::FromStr;
 
 pub struct DnsParameters {
     pub ioc_id: IocId,
     pub ioc_entry_id: IocEntryId,
-    pub name: String,
+    pub search: SearchType,
+    pub name: Option<String>,
+    pub include_subdomains: bool,
+    pub address: Option<String>,
 }
 
-#[cfg(windows)]
-pub fn check_dns(search_parameters: Vec<DnsParameters>) -> Vec<IocEntrySearchResult> {
+enum DnsPattern {
+    /// ASCII (punycode) form of the domain
+    Domain { name: String, include_subdomains: bool },
+    Regex(SearchPattern),
+}
+
+impl DnsPattern {
+    fn is_match(&self, name: &DomainForms) -> bool {
+        match self {
+            DnsPattern::Domain { name: domain, include_subdomains } =>
+                domain_matches(&name.ascii, domain, *include_subdomains),
+            DnsPattern::Regex(regex) => regex.is_match(&name.ascii) || regex.is_match(&name.unicode),
+        }
+    }
+}
+
+/// Both forms of a domain, so that `xn--` encoded and Unicode IOCs match either.
+pub struct DomainForms {
+    pub ascii: String,
+    pub unicode: String,
+}
+
+impl DomainForms {
+    pub fn new(name: &str) -> Self {
+        let name = name.trim().trim_end_matches('.');
+        let ascii = idna::domain_to_ascii(name).unwrap_or_else(|_| name.to_lowercase());
+        let (unicode, _) = idna::domain_to_unicode(&ascii);
+        DomainForms { ascii, unicode }
+    }
+}
+
+/// Checks the local DNS sources and, when given, the DNS traffic of a capture file.
+pub fn check_dns(search_parameters: Vec<DnsParameters>, capture: Option<&Capture>) -> Vec<IocEntrySearchResult> {
     if search_parameters.is_empty() {
         return vec![];
     }
     info!("DNS search: Searching IOCs using open DNS search.");
-    let output = Command::new("ipconfig")
-        .args(&["/displaydns"])
-        .output()
-        .expect("failed to execute process");
-    let output_str = std::str::from_utf8(&output.stdout);
-    if output_str.is_err() {
-        error!("DNS search: {}", output_str.err().unwrap());
-        return vec![];
-    }
-    let output_str = output_str.unwrap();
-    let lines: Vec<&str> = output_str.lines().collect();
-
-    let dns_names: Vec<&str> = lines.iter().enumerate()
-        .filter(|(_, line)| line.trim().starts_with("----------"))
-        .map(|(i, _)| lines[i - 1].trim()).collect();
+    let search_parameters: Vec<(DnsParameters, Option<DnsPattern>, Option<IpNetwork>)> = search_parameters.into_iter()
+        .filter_map(|sp| {
+            if sp.name.is_none() && sp.address.is_none() {
+                warn!("DNS search: IOC {} specifies nothing to search for, skipping", sp.ioc_id);
+                return None;
+            }
+            let pattern = match (&sp.name, sp.search) {
+                (None, _) => None,
+                (Some(name), SearchType::Exact) => Some(DnsPattern::Domain {
+                    name: DomainForms::new(name).ascii,
+                    include_subdomains: sp.include_subdomains,
+                }),
+                (Some(name), SearchType::Regex) => match SearchPattern::new_ignore_case(sp.search, name) {
+                    Ok(regex) => Some(DnsPattern::Regex(regex)),
+                    Err(err) => {
+                        error!("DNS search: {}", err);
+                        return None;
+                    }
+                }
+            };
+            let network = match sp.address.as_ref().map(|it| IpNetwork::from_str(it.trim())).transpose() {
+                Ok(network) => network,
+                Err(err) => {
+                    error!("DNS search: Cannot parse address of IOC {}: {:?}", sp.ioc_id, err);
+                    return None;
+                }
+            };
+            Some((sp, pattern, network))
+        })
+        .collect();
+    let captured_entries = capture.map(|it| it.dns.clone()).unwrap_or_default();
+    let dns_entries: Vec<(DnsEntry, DomainForms)> = dns_entries().into_iter()
+        .chain(captured_entries)
+        .map(|entry| {
+            let forms = DomainForms::new(&entry.name);
+            (entry, forms)
+        })
+        .collect();
 
     search_parameters.iter()
-        .filter(|search_param| dns_names.iter().any(|dns| *dns == search_param.name.as_str()))
-        .map(|search_param| {
-            let message = format!("DNS search: Found DNS {} for IOC {}",
-                  search_param.name.clone(),
+        .filter_map(|(search_param, pattern, network)| {
+            let (entry, _) = dns_entries.iter().find(|(entry, forms)|
+                pattern.as_ref().map(|it| it.is_match(forms)).unwrap_or(true)
+                    && network.as_ref().map(|it| address_matches(it, entry)).unwrap_or(true)
+            )?;
+            let message = format!("DNS search: Found DNS {} ({}) in {} for IOC {}",
+                  entry.name,
+                  entry.address.as_deref().unwrap_or("-"),
+                  entry.source,
                   search_param.ioc_id
             );
             info!("{}", message);
-            IocEntrySearchResult {
+            Some(IocEntrySearchResult {
                 ioc_id: search_param.ioc_id,
                 ioc_entry_id: search_param.ioc_entry_id,
-                description: message
-            }
+                check: CheckKind::Dns,
+                description: message,
+                timestamp: None,
+                evidence: Evidence::Dns {
+                    name: entry.name.clone(),
+                    address: entry.address.clone(),
+                    source: entry.source.clone(),
+                },
+            })
         }).collect()
 }
 
+fn address_matches(network: &IpNetwork, entry: &DnsEntry) -> bool {
+    entry.address.as_ref()
+        .and_then(|it| IpAddr::from_str(it).ok())
+        .map(|it| network_contains(network, &it))
+        .unwrap_or(false)
+}
+
+/// Suffix matching respects label boundaries, `evil.com` matches `x.evil.com` but not `notevil.com`.
+pub fn domain_matches(name: &str, domain: &str, include_subdomains: bool) -> bool {
+    name == domain || (include_subdomains && name.len() > domain.len()
+        && name.ends_with(domain) && name[..name.len() - domain.len()].ends_with('.'))
+}
+
+#[cfg(windows)]
+fn dns_entries() -> Vec<DnsEntry> {
+    let output = match Command::new("ipconfig").args(&["/displaydns"]).output() {
+        Ok(output) => output,
+        Err(err) => {
+            error!("DNS search: Cannot run ipconfig: {}", err);
+            return vec![];
+        }
+    };
+    // Output is in the OEM code page, only the localized labels suffer from the lossy conversion
+    crate::dns_sources::parse_displaydns(&String::from_utf8_lossy(&output.stdout)).iter()
+        .map(|record| record.to_dns_entry())
+        .collect()
+}
+
 #[cfg(not(windows))]
-pub fn check_dns(search_parameters: Vec<DnsParameters>) -> Vec<IocEntrySearchResult> {
-    vec![]
+fn dns_entries() -> Vec<DnsEntry> {
+    crate::dns_sources::linux_dns_entries()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dns_checker::{domain_matches, DomainForms};
+
+    #[test]
+    fn test_domain_matches() {
+        assert!(domain_matches("evil.com", "evil.com", false));
+        assert!(!domain_matches("x.evil.com", "evil.com", false));
+        assert!(domain_matches("x.evil.com", "evil.com", true));
+        assert!(domain_matches("a.b.evil.com", "evil.com", true));
+        assert!(!domain_matches("notevil.com", "evil.com", true));
+        assert!(!domain_matches("evil.com.attacker.net", "evil.com", true));
+    }
+
+    #[test]
+    fn test_domain_forms() {
+        let unicode = DomainForms::new("Bücher.Example.");
+        assert_eq!(unicode.ascii, "xn--bcher-kva.example");
+        assert_eq!(unicode.unicode, "bücher.example");
+        let ascii = DomainForms::new("xn--bcher-kva.example");
+        assert_eq!(ascii.ascii, unicode.ascii);
+        assert_eq!(ascii.unicode, "bücher.example");
+    }
 }
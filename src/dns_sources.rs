@@ -0,0 +1,381 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::Path;
+use std::process::Command;
+
+pub const HOSTS_PATH: &str = "/etc/hosts";
+pub const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+/// Files network managers generate, `/etc/resolv.conf` normally links to or copies one of them.
+pub const MANAGED_RESOLV_CONF_PATHS: [&str; 4] = [
+    "/run/systemd/resolve/stub-resolv.conf",
+    "/run/systemd/resolve/resolv.conf",
+    "/run/NetworkManager/resolv.conf",
+    "/run/resolvconf/resolv.conf",
+];
+pub const NSCD_HOSTS_CACHE_PATH: &str = "/var/cache/nscd/hosts";
+pub const DNSMASQ_LOG_PATHS: [&str; 3] = ["/var/log/dnsmasq.log", "/var/log/syslog", "/var/log/messages"];
+/// Only the end of the logs is read, system logs grow to gigabytes between rotations.
+const DNSMASQ_LOG_TAIL: u64 = 32 * 1024 * 1024;
+
+/// Domains malware likes to redirect or block in the hosts file, subdomains included.
+const WELL_KNOWN_DOMAINS: [&str; 12] = [
+    "microsoft.com",
+    "windowsupdate.com",
+    "windows.com",
+    "update.microsoft.com",
+    "google.com",
+    "googleapis.com",
+    "apple.com",
+    "github.com",
+    "ubuntu.com",
+    "debian.org",
+    "virustotal.com",
+    "clamav.net",
+];
+
+/// Name known to the local resolver, `address` is the IP it resolves to where the source tells it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsEntry {
+    pub name: String,
+    pub address: Option<String>,
+    pub source: String,
+}
+
+impl DnsEntry {
+    fn new(name: &str, address: Option<&str>, source: &str) -> Self {
+        DnsEntry {
+            name: name.trim_end_matches('.').to_ascii_lowercase(),
+            address: address.map(|it| it.to_string()),
+            source: source.to_string(),
+        }
+    }
+}
+
+/// All Linux sources, unavailable ones are skipped.
+pub fn linux_dns_entries() -> Vec<DnsEntry> {
+    let mut entries = Vec::new();
+    match std::fs::read_to_string(HOSTS_PATH) {
+        Ok(content) => entries.extend(parse_hosts(&content, HOSTS_PATH)),
+        Err(err) => debug!("DNS search: Cannot read {}: {}", HOSTS_PATH, err),
+    }
+    match std::fs::read_to_string(RESOLV_CONF_PATH) {
+        Ok(content) => {
+            let mut resolv_conf = parse_resolv_conf(&content, RESOLV_CONF_PATH);
+            let managed: Vec<Vec<DnsEntry>> = MANAGED_RESOLV_CONF_PATHS.iter()
+                .filter_map(|path| std::fs::read_to_string(path).ok().map(|it| parse_resolv_conf(&it, path)))
+                .collect();
+            if !managed.is_empty() {
+                mark_changed_nameservers(&mut resolv_conf, &managed.concat());
+            }
+            entries.extend(resolv_conf);
+        }
+        Err(err) => debug!("DNS search: Cannot read {}: {}", RESOLV_CONF_PATH, err),
+    }
+    match Command::new("resolvectl").arg("show-cache").output() {
+        Ok(output) if output.status.success() =>
+            entries.extend(parse_resolvectl_cache(&String::from_utf8_lossy(&output.stdout))),
+        Ok(output) => debug!("DNS search: resolvectl show-cache failed: {}", String::from_utf8_lossy(&output.stderr).trim()),
+        Err(err) => debug!("DNS search: Cannot run resolvectl: {}", err),
+    }
+    match std::fs::read(NSCD_HOSTS_CACHE_PATH) {
+        Ok(content) => entries.extend(parse_nscd_cache(&content, NSCD_HOSTS_CACHE_PATH)),
+        Err(err) => debug!("DNS search: Cannot read {}: {}", NSCD_HOSTS_CACHE_PATH, err),
+    }
+    for log_path in DNSMASQ_LOG_PATHS.iter().filter(|it| Path::new(it).exists()) {
+        match log_tail(log_path, DNSMASQ_LOG_TAIL) {
+            Ok(reader) => entries.extend(parse_dnsmasq_log(reader, log_path)),
+            Err(err) => debug!("DNS search: Cannot read {}: {}", log_path, err),
+        }
+    }
+    entries
+}
+
+/// Reader of the last `tail` bytes of the log starting at a whole line.
+fn log_tail(path: &str, tail: u64) -> std::io::Result<BufReader<File>> {
+    let mut file = File::open(path)?;
+    let length = file.metadata()?.len();
+    if length <= tail {
+        return Ok(BufReader::new(file));
+    }
+    file.seek(SeekFrom::Start(length - tail))?;
+    let mut reader = BufReader::new(file);
+    reader.read_until(b'\n', &mut Vec::new())?;
+    Ok(reader)
+}
+
+/// Record of the Windows DNS client cache.
+#[cfg(any(windows, test))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayDnsRecord {
+    pub name: String,
+    pub record_type: u16,
+    pub ttl: u32,
+    pub data: String,
+}
+
+#[cfg(any(windows, test))]
+const DNS_TYPE_A: u16 = 1;
+#[cfg(any(windows, test))]
+const DNS_TYPE_AAAA: u16 = 28;
+
+#[cfg(any(windows, test))]
+impl DisplayDnsRecord {
+    pub fn to_dns_entry(&self) -> DnsEntry {
+        let address = if self.record_type == DNS_TYPE_A || self.record_type == DNS_TYPE_AAAA {
+            Some(self.data.as_str())
+        } else {
+            None
+        };
+        DnsEntry::new(&self.name, address, "Windows DNS cache")
+    }
+}
+
+/// Parses `ipconfig /displaydns`. Labels are localized, so every record is read by the position of
+/// its `label . . . : value` lines: name, type, TTL, data length, section and data.
+#[cfg(any(windows, test))]
+pub fn parse_displaydns(output: &str) -> Vec<DisplayDnsRecord> {
+    let mut records = Vec::new();
+    let mut values: Vec<&str> = Vec::new();
+    for line in output.lines().chain(std::iter::once("")) {
+        match line.find(" : ").map(|i| line[i + 3..].trim()) {
+            Some(value) if !line.trim().starts_with("---") => values.push(value),
+            _ => {
+                if let [name, record_type, ttl, _, _, data] = values.as_slice() {
+                    match (record_type.parse::<u16>(), ttl.parse::<u32>()) {
+                        (Ok(record_type), Ok(ttl)) => records.push(DisplayDnsRecord {
+                            name: name.to_string(),
+                            record_type,
+                            ttl,
+                            data: data.to_string(),
+                        }),
+                        _ => debug!("DNS search: Unexpected ipconfig record {:?}", values),
+                    }
+                }
+                values.clear();
+            }
+        }
+    }
+    records
+}
+
+/// Every hostname and alias of `address hostname [aliases...]` lines, overrides of well-known domains are reported.
+pub fn parse_hosts(content: &str, source: &str) -> Vec<DnsEntry> {
+    let mut entries = Vec::new();
+    for line in content.lines() {
+        let mut fields = line.split('#').next().unwrap_or_default().split_whitespace();
+        let address = match fields.next() {
+            Some(address) => address,
+            None => continue,
+        };
+        for name in fields {
+            let mut entry = DnsEntry::new(name, Some(address), source);
+            if is_well_known(&entry.name) {
+                warn!("DNS search: {} overrides well-known domain {} with {}", source, entry.name, address);
+                entry.source = format!("{} (override of well-known domain)", source);
+            }
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+fn is_well_known(name: &str) -> bool {
+    WELL_KNOWN_DOMAINS.iter().any(|domain| name == *domain || name.ends_with(&format!(".{}", domain)))
+}
+
+/// Nameservers and search domains, a nameserver is matched by its address as the name.
+pub fn parse_resolv_conf(content: &str, source: &str) -> Vec<DnsEntry> {
+    let mut entries = Vec::new();
+    for line in content.lines() {
+        let mut fields = line.split(['#', ';']).next().unwrap_or_default().split_whitespace();
+        match fields.next() {
+            Some("nameserver") => if let Some(address) = fields.next() {
+                info!("DNS search: Nameserver {} configured in {}", address, source);
+                entries.push(DnsEntry::new(address, Some(address), &format!("nameserver in {}", source)));
+            },
+            Some("search") | Some("domain") => entries.extend(
+                fields.map(|domain| DnsEntry::new(domain, None, &format!("search domain in {}", source)))
+            ),
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// Nameservers of `entries` which none of the `managed` configurations name were set by hand or by malware.
+pub fn mark_changed_nameservers(entries: &mut [DnsEntry], managed: &[DnsEntry]) {
+    for entry in entries.iter_mut().filter(|it| it.address.is_some()) {
+        if !managed.iter().any(|it| it.address == entry.address) {
+            warn!("DNS search: Nameserver {} is not set by the network configuration", entry.name);
+            entry.source = format!("{} (changed nameserver)", entry.source);
+        }
+    }
+}
+
+/// `resolvectl show-cache` prints resource records as `name IN type data`.
+pub fn parse_resolvectl_cache(content: &str) -> Vec<DnsEntry> {
+    content.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [name, "IN", record_type, data, ..] => Some(DnsEntry::new(
+                    name,
+                    if *record_type == "A" || *record_type == "AAAA" { Some(data) } else { None },
+                    "systemd-resolved cache",
+                )),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// dnsmasq with `log-queries` logs `reply <name> is <address>` and `cached <name> is <address>`.
+/// The log is read line by line, lines which are not UTF-8 are read lossily.
+pub fn parse_dnsmasq_log<R: BufRead>(mut reader: R, source: &str) -> Vec<DnsEntry> {
+    let mut entries = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => entries.extend(parse_dnsmasq_line(&String::from_utf8_lossy(&line), source)),
+            Err(err) => {
+                debug!("DNS search: Cannot read {}: {}", source, err);
+                break;
+            }
+        }
+    }
+    entries
+}
+
+fn parse_dnsmasq_line(line: &str, source: &str) -> Option<DnsEntry> {
+    let message = &line[line.find("dnsmasq[")?..];
+    let message = &message[message.find("]: ")? + 3..];
+    let fields: Vec<&str> = message.split_whitespace().collect();
+    match fields.as_slice() {
+        ["reply", name, "is", address, ..] | ["cached", name, "is", address, ..] => {
+            let address = if address.starts_with('<') { None } else { Some(*address) };
+            Some(DnsEntry::new(name, address, &format!("dnsmasq log {}", source)))
+        }
+        _ => None,
+    }
+}
+
+/// The nscd database is binary, hostnames are stored as NUL terminated strings.
+pub fn parse_nscd_cache(content: &[u8], source: &str) -> Vec<DnsEntry> {
+    let mut entries: Vec<DnsEntry> = content.split(|it| *it == 0)
+        .filter_map(|it| std::str::from_utf8(it).ok())
+        .filter(|it| looks_like_domain(it))
+        .map(|name| DnsEntry::new(name, None, &format!("nscd cache {}", source)))
+        .collect();
+    entries.dedup();
+    entries
+}
+
+fn looks_like_domain(value: &str) -> bool {
+    value.len() >= 4 && value.len() <= 253
+        && value.contains('.')
+        && value.split('.').all(|label| !label.is_empty() && label.len() <= 63)
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+        && value.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dns_sources::{parse_hosts, parse_resolv_conf, parse_resolvectl_cache, parse_dnsmasq_log, parse_nscd_cache,
+                             parse_displaydns, log_tail, mark_changed_nameservers, DisplayDnsRecord};
+    use std::io::BufRead;
+
+    #[test]
+    fn test_parse_hosts() {
+        let entries = parse_hosts(
+            "127.0.0.1 localhost\n\
+             # comment\n\
+             203.0.113.9 update.microsoft.com  mirror.Evil.com. # redirected\n",
+            "/etc/hosts",
+        );
+        let names: Vec<(&str, &str)> = entries.iter().map(|it| (it.name.as_str(), it.source.as_str())).collect();
+        assert_eq!(names, vec![
+            ("localhost", "/etc/hosts"),
+            ("update.microsoft.com", "/etc/hosts (override of well-known domain)"),
+            ("mirror.evil.com", "/etc/hosts"),
+        ]);
+        assert_eq!(entries[2].address.as_deref(), Some("203.0.113.9"));
+    }
+
+    #[test]
+    fn test_parse_resolv_conf() {
+        let entries = parse_resolv_conf("# generated\nnameserver 203.0.113.53\nsearch corp.example\noptions edns0\n", "/etc/resolv.conf");
+        let names: Vec<&str> = entries.iter().map(|it| it.name.as_str()).collect();
+        assert_eq!(names, vec!["203.0.113.53", "corp.example"]);
+
+        let mut changed = parse_resolv_conf("nameserver 127.0.0.53\nnameserver 203.0.113.53\n", "/etc/resolv.conf");
+        let managed = parse_resolv_conf("nameserver 127.0.0.53\nsearch corp.example\n", "/run/systemd/resolve/stub-resolv.conf");
+        mark_changed_nameservers(&mut changed, &managed);
+        let sources: Vec<&str> = changed.iter().map(|it| it.source.as_str()).collect();
+        assert_eq!(sources, vec!["nameserver in /etc/resolv.conf", "nameserver in /etc/resolv.conf (changed nameserver)"]);
+    }
+
+    #[test]
+    fn test_parse_caches() {
+        let resolved = parse_resolvectl_cache(
+            "Scope protocol=dns interface=eth0:\n\
+             c2.example.com IN A 198.51.100.7\n\
+             example.org IN CNAME www.example.org\n"
+        );
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved[0].address.as_deref(), Some("198.51.100.7"));
+        assert_eq!(resolved[1].address, None);
+
+        let dnsmasq = parse_dnsmasq_log(
+            "Mar  1 10:00:00 host dnsmasq[812]: query[A] c2.example.com from 10.0.0.5\n\
+             Mar  1 10:00:00 host dnsmasq[812]: reply c2.example.com is 198.51.100.7\n\
+             Mar  1 10:00:01 host dnsmasq[812]: cached gone.example.com is <NXDOMAIN>\n".as_bytes(),
+            "/var/log/syslog",
+        );
+        let names: Vec<(&str, Option<&str>)> = dnsmasq.iter().map(|it| (it.name.as_str(), it.address.as_deref())).collect();
+        assert_eq!(names, vec![("c2.example.com", Some("198.51.100.7")), ("gone.example.com", None)]);
+
+        let nscd = parse_nscd_cache(b"\x01\x00\x00\x00c2.example.com\x00\x7f\x00\x00\x01\x00nope\x00", "/var/cache/nscd/hosts");
+        assert_eq!(nscd.len(), 1);
+        assert_eq!(nscd[0].name, "c2.example.com");
+    }
+
+    #[test]
+    fn test_log_tail() {
+        let path = std::env::temp_dir().join("ioc-checker-probe-log-tail.log");
+        std::fs::write(&path, "first line\nsecond line\nthird line\n").unwrap();
+        let lines = |tail: u64| -> Vec<String> {
+            log_tail(path.to_str().unwrap(), tail).unwrap().lines().map(|it| it.unwrap()).collect()
+        };
+        assert_eq!(lines(15), vec!["third line"]);
+        assert_eq!(lines(1024).len(), 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn record(name: &str, record_type: u16, ttl: u32, data: &str) -> DisplayDnsRecord {
+        DisplayDnsRecord { name: name.to_string(), record_type, ttl, data: data.to_string() }
+    }
+
+    #[test]
+    fn test_parse_displaydns() {
+        let expected = vec![
+            record("c2.example.com", 1, 103, "198.51.100.7"),
+            record("www.example.org", 5, 3591, "example.org"),
+            record("example.org", 1, 3591, "93.184.216.34"),
+            record("ipv6.example.net", 28, 52, "2001:db8::7"),
+        ];
+        let fixtures = [
+            include_str!("../tests/fixtures/displaydns_en.txt"),
+            include_str!("../tests/fixtures/displaydns_de.txt"),
+            include_str!("../tests/fixtures/displaydns_fr.txt"),
+            include_str!("../tests/fixtures/displaydns_cs.txt"),
+        ];
+        for fixture in fixtures.iter() {
+            assert_eq!(parse_displaydns(fixture), expected);
+        }
+        let entry = expected[3].to_dns_entry();
+        assert_eq!(entry.address.as_deref(), Some("2001:db8::7"));
+        assert_eq!(expected[1].to_dns_entry().address, None);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::data::{IocEntryId, IocId, SearchType, Evidence};
+use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult};
+use crate::search_pattern::{SearchPattern, optional_match};
+use crate::proc_fs;
+
+pub struct EnvironmentParameters {
+    pub ioc_id: IocId,
+    pub ioc_entry_id: IocEntryId,
+    pub search: SearchType,
+    pub name: Option<String>,
+    pub value: Option<String>,
+}
+
+struct EnvironmentParametersRegexed {
+    env_param: EnvironmentParameters,
+    name: Option<SearchPattern>,
+    value: Option<SearchPattern>,
+}
+
+pub fn check_environment(search_parameters: Vec<EnvironmentParameters>) -> Vec<IocEntrySearchResult> {
+    if search_parameters.is_empty() {
+        return vec![];
+    }
+    info!("Environment search: Searching IOCs using process environment search.");
+    let search_parameters: Vec<EnvironmentParametersRegexed> = search_parameters.into_iter().filter_map(|sp| {
+        if sp.name.is_none() && sp.value.is_none() {
+            warn!("Environment search: IOC {} specifies nothing to search for, skipping", sp.ioc_id);
+            return None;
+        }
+        let name = sp.name.as_ref().map(|it| SearchPattern::new(sp.search, it)).transpose();
+        let value = sp.value.as_ref().map(|it| SearchPattern::new(sp.search, it)).transpose();
+        match (name, value) {
+            (Ok(name), Ok(value)) => Some(EnvironmentParametersRegexed { env_param: sp, name, value }),
+            (Err(err), _) | (_, Err(err)) => {
+                error!("Environment search: {}", err);
+                None
+            }
+        }
+    }).collect();
+
+    let mut result: Vec<IocEntrySearchResult> = Vec::new();
+    for pid in proc_fs::list_pids() {
+        let environ = match proc_fs::process_environ(pid) {
+            Some(environ) => environ,
+            None => continue,
+        };
+        let process_name = proc_fs::process_name(pid);
+        debug!("Environment search: Checking process {} (pid {})", process_name, pid);
+        for sp in &search_parameters {
+            let found = environ.iter().find(|(name, value)|
+                optional_match(&sp.name, Some(name)) && optional_match(&sp.value, Some(value))
+            );
+            if let Some((name, value)) = found {
+                let message = format!("Environment search: Found variable {}={} in process {} (pid {}) for IOC {}",
+                                      name,
+                                      value,
+                                      process_name,
+                                      pid,
+                                      sp.env_param.ioc_id
+                );
+                info!("{}", message);
+                result.push(IocEntrySearchResult {
+                    ioc_id: sp.env_param.ioc_id,
+                    ioc_entry_id: sp.env_param.ioc_entry_id,
+                    check: CheckKind::Environment,
+                    description: message,
+                    timestamp: None,
+                    evidence: Evidence::Environment {
+                        pid,
+                        process_name: process_name.clone(),
+                        name: name.clone(),
+                        value: value.clone(),
+                    },
+                })
+            }
+        }
+    }
+    result
+}
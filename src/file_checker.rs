@@ -1,14 +1,22 @@
-use crate::hasher::Hasher;
+use crate::hasher::{Hasher, file_hashes};
 use std::ffi::CString;
 use std::path::{Path, PathBuf};
 use walkdir::{WalkDir, DirEntry};
 use regex::Regex;
 use std::collections::HashSet;
-use crate::data::{SearchType, Hashed, IocEntryId, IocId};
-use crate::ioc_evaluator::IocEntrySearchResult;
+use crate::data::{SearchType, Hashed, IocEntryId, IocId, SignatureInfo, Evidence};
+use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult};
 use crate::dir_resolver;
+use crate::authenticode::{catalog_signature, read_signature, AuthenticodeSignature};
+use crate::certificate::{DistinguishedName, normalize_serial};
+use crate::search_pattern::{SearchPattern, optional_match};
+use crate::utils::modified_time;
+use chrono::{DateTime, Utc};
 
 
+/// Signatures of larger files are not checked, verifying one needs the whole image in memory
+const MAX_SIGNED_FILE_SIZE: u64 = 256 * 1024 * 1024;
+
 #[derive(Clone)]
 pub struct FileParameters {
     pub ioc_id: IocId,
@@ -16,6 +24,30 @@ pub struct FileParameters {
     pub search_type: SearchType,
     pub file_path_or_name: Option<String>,
     pub hash: Option<Hashed>,
+    pub signature: Option<SignatureInfo>,
+}
+
+struct FileParametersRegexed {
+    file_param: FileParameters,
+    signature: Option<SignatureParameters>,
+}
+
+struct SignatureParameters {
+    signature_info: SignatureInfo,
+    /// Regex search matches the whole distinguished name, exact search its components
+    subject: Option<SearchPattern>,
+    issuer: Option<SearchPattern>,
+}
+
+impl SignatureParameters {
+    fn new(signature_info: SignatureInfo) -> Result<Self, regex::Error> {
+        let compile = |pattern: &Option<String>| pattern.as_ref()
+            .map(|it| SearchPattern::new_ignore_case(signature_info.search, it))
+            .transpose();
+        let subject = compile(&signature_info.subject)?;
+        let issuer = compile(&signature_info.issuer)?;
+        Ok(SignatureParameters { signature_info, subject, issuer })
+    }
 }
 
 pub fn check_files(search_parameters: Vec<FileParameters>, deep_search_enabled: bool) -> Vec<IocEntrySearchResult> {
@@ -36,25 +68,26 @@ pub fn check_files(search_parameters: Vec<FileParameters>, deep_search_enabled:
                     None => None
                 },
                 hash: sp.hash,
+                signature: sp.signature,
             },
             SearchType::Regex => sp
         }
-        ).collect::<Vec<FileParameters>>();
+        )
+        .filter_map(|sp| match sp.signature.clone().map(SignatureParameters::new).transpose() {
+            Ok(signature) => Some(FileParametersRegexed { file_param: sp, signature }),
+            Err(err) => {
+                error!("File search: Cannot parse signature name of IOC {} as regex: {}", sp.ioc_id, err);
+                None
+            }
+        })
+        .collect::<Vec<FileParametersRegexed>>();
 
     let search_by_exact = search_parameters.iter().filter(
-        |search_parameter|
-            match search_parameter.search_type {
-                SearchType::Exact => true,
-                _ => false
-            }).filter(|search_parameter| !search_parameter.file_path_or_name.clone().unwrap_or("".to_string()).is_empty());
+        |search_parameter| matches!(search_parameter.file_param.search_type, SearchType::Exact))
+        .filter(|search_parameter| !search_parameter.file_param.file_path_or_name.clone().unwrap_or("".to_string()).is_empty());
     let ok_results = search_by_exact.filter_map(|search_parameter| {
-        let path = search_parameter.file_path_or_name.as_deref().map(|it| Path::new(it));
-        check_file_by_hash(
-            &search_parameter.hash,
-            path,
-            search_parameter.ioc_id,
-            search_parameter.ioc_entry_id,
-        )
+        let path = search_parameter.file_param.file_path_or_name.as_deref().map(Path::new);
+        check_file(search_parameter, path)
     });
     let results = ok_results.collect::<Vec<IocEntrySearchResult>>();
     if search_parameters.len() == results.len() {
@@ -69,9 +102,9 @@ pub fn check_files(search_parameters: Vec<FileParameters>, deep_search_enabled:
     let roots = all_drives();
     let found_ioc_entries = results.iter()
         .map(|ok_res| ok_res.ioc_entry_id).collect::<HashSet<IocEntryId>>();
-    let remaining_search_parameters: Vec<FileParameters> = search_parameters
+    let remaining_search_parameters: Vec<FileParametersRegexed> = search_parameters
         .into_iter()
-        .filter(|fp| !found_ioc_entries.contains(&fp.ioc_entry_id))
+        .filter(|fp| !found_ioc_entries.contains(&fp.file_param.ioc_entry_id))
         .collect();
     let deep_results = roots.iter()
         .flat_map(|root| deep_search(
@@ -83,7 +116,7 @@ pub fn check_files(search_parameters: Vec<FileParameters>, deep_search_enabled:
     return final_results;
 }
 
-fn deep_search(path: &Path, search_parameters: &[FileParameters]) -> Vec<IocEntrySearchResult> {
+fn deep_search(path: &Path, search_parameters: &[FileParametersRegexed]) -> Vec<IocEntrySearchResult> {
     debug!("File search: Searching files in {}", path.display());
 
     let walker = WalkDir::new(path);
@@ -100,7 +133,7 @@ fn deep_search(path: &Path, search_parameters: &[FileParameters]) -> Vec<IocEntr
         debug!("File search: Checking file {}", file_entry.path().display());
         for (i, search_parameter) in search_parameters.iter().enumerate() {
             if !found_file_parameters.contains(&i) {
-                let maybe_query_result = match search_parameter.search_type {
+                let maybe_query_result = match search_parameter.file_param.search_type {
                     SearchType::Exact => { check_file_by_name(search_parameter, &file_entry) }
                     SearchType::Regex => { check_file_by_regex(search_parameter, &file_entry) }
                 };
@@ -122,8 +155,8 @@ fn deep_search(path: &Path, search_parameters: &[FileParameters]) -> Vec<IocEntr
 }
 
 
-fn check_file_by_name(search_parameter: &FileParameters, file_entry: &DirEntry) -> Option<IocEntrySearchResult> {
-    let searched_path = search_parameter.file_path_or_name.as_deref().map(|it| Path::new(it));
+fn check_file_by_name(search_parameter: &FileParametersRegexed, file_entry: &DirEntry) -> Option<IocEntrySearchResult> {
+    let searched_path = search_parameter.file_param.file_path_or_name.as_deref().map(Path::new);
     let file_entry_path = file_entry.path();
     match searched_path {
         None => {
@@ -135,12 +168,7 @@ fn check_file_by_name(search_parameter: &FileParameters, file_entry: &DirEntry)
     }
 
     if searched_path.is_none() {
-        return check_file_by_hash(
-            &search_parameter.hash,
-            Some(file_entry_path),
-            search_parameter.ioc_id,
-            search_parameter.ioc_entry_id,
-        );
+        return check_file(search_parameter, Some(file_entry_path));
     }
     let searched_path = searched_path.unwrap();
     let searched_path_parent = searched_path.parent().unwrap_or(Path::new(""));
@@ -153,20 +181,15 @@ fn check_file_by_name(search_parameter: &FileParameters, file_entry: &DirEntry)
     if file_entry.file_name() != searched_filename {
         return None;
     }
-    check_file_by_hash(
-        &search_parameter.hash,
-        Some(file_entry_path),
-        search_parameter.ioc_id,
-        search_parameter.ioc_entry_id,
-    )
+    check_file(search_parameter, Some(file_entry_path))
 }
 
 fn check_file_by_regex(
-    search_parameter: &FileParameters,
+    search_parameter: &FileParametersRegexed,
     file_entry: &DirEntry,
 ) -> Option<IocEntrySearchResult> {
     let empty_str = "".to_string();
-    let searched_path = search_parameter.file_path_or_name.as_ref().unwrap_or(&empty_str).as_str();
+    let searched_path = search_parameter.file_param.file_path_or_name.as_ref().unwrap_or(&empty_str).as_str();
     debug!("File search: Checking file paths {} and {} by regex", searched_path, file_entry.path().display());
     let regex_path = Regex::new(searched_path);
     if regex_path.is_err() {
@@ -184,12 +207,100 @@ fn check_file_by_regex(
     debug!("File search: Regex match by file name {} and {:?} successful: {}", searched_path, file_entry.file_name(), file_name_matched);
 
     if !file_name_matched && !file_path_empty && !file_name_matched { None } else {
-        check_file_by_hash(
-            &search_parameter.hash,
-            Some(file_entry.path()),
-            search_parameter.ioc_id,
-            search_parameter.ioc_entry_id,
-        )
+        check_file(search_parameter, Some(file_entry.path()))
+    }
+}
+
+fn check_file(search_parameter: &FileParametersRegexed, file_path: Option<&Path>) -> Option<IocEntrySearchResult> {
+    let file_param = &search_parameter.file_param;
+    let result = check_file_by_hash(
+        &file_param.hash,
+        file_path,
+        file_param.ioc_id,
+        file_param.ioc_entry_id,
+    )?;
+    match &search_parameter.signature {
+        None => Some(result),
+        Some(signature_parameters) => check_file_signature(signature_parameters, file_path.unwrap_or(Path::new("")), result),
+    }
+}
+
+fn check_file_signature(
+    signature_parameters: &SignatureParameters,
+    file_path: &Path,
+    result: IocEntrySearchResult,
+) -> Option<IocEntrySearchResult> {
+    debug!("File search: Checking signature of file {}", file_path.display());
+    match file_path.metadata() {
+        Ok(metadata) if metadata.len() > MAX_SIGNED_FILE_SIZE => {
+            warn!("File search: Skipping signature of {}, file is too large", file_path.display());
+            return None;
+        }
+        _ => {}
+    }
+    let content = match std::fs::read(file_path) {
+        Ok(content) => content,
+        Err(error) => {
+            error!("File search: Cannot read \"{}\": {}", file_path.display(), error);
+            return None;
+        }
+    };
+    let signature = match read_signature(&content) {
+        Ok(signature) => signature,
+        Err(error) => {
+            debug!("File search: Cannot read signature of {}: {}", file_path.display(), error);
+            return None;
+        }
+    };
+    // System files are mostly signed in catalogs rather than embedded signatures
+    let catalog = if signature.is_none() { catalog_signature(file_path) } else { None };
+    if !signature_matches(signature_parameters, signature.as_ref(), catalog.is_some()) {
+        debug!("File search: Signature of {} does not match", file_path.display());
+        return None;
+    }
+    let signature_description = match (&signature, &catalog) {
+        (None, Some(catalog)) => format!("signed in catalog {}", catalog),
+        (None, None) => "not signed".to_string(),
+        (Some(signature), _) => format!("signed by {} ({})",
+                                   signature.signer.subject.text,
+                                   if signature.is_valid() { "valid" } else { "invalid" }
+        ),
+    };
+    Some(IocEntrySearchResult {
+        description: format!("{}, {}", result.description, signature_description),
+        ..result
+    })
+}
+
+/// Files signed in a catalog only match blocks without signer conditions, the catalog signer is not read.
+fn signature_matches(signature_parameters: &SignatureParameters, signature: Option<&AuthenticodeSignature>,
+                     catalog_signed: bool) -> bool {
+    let signature_info = &signature_parameters.signature_info;
+    let signature = match signature {
+        Some(signature) => signature,
+        None => return signature_info.signed != Some(!catalog_signed)
+            && signature_info.valid != Some(!catalog_signed)
+            && signature_info.subject.is_none()
+            && signature_info.issuer.is_none()
+            && signature_info.serial.is_none()
+            && signature_info.thumbprint.is_none(),
+    };
+    signature_info.signed != Some(false)
+        && signature_info.valid.map(|it| it == signature.is_valid()).unwrap_or(true)
+        && name_matches(signature_info.search, &signature_info.subject, &signature_parameters.subject, &signature.signer.subject)
+        && name_matches(signature_info.search, &signature_info.issuer, &signature_parameters.issuer, &signature.signer.issuer)
+        && signature_info.serial.as_ref()
+            .map(|it| normalize_serial(it) == signature.signer.serial)
+            .unwrap_or(true)
+        && signature_info.thumbprint.as_ref()
+            .map(|it| signature.chain.iter().any(|certificate| certificate.thumbprint(&it.algorithm) == Some(it)))
+            .unwrap_or(true)
+}
+
+fn name_matches(search: SearchType, name: &Option<String>, pattern: &Option<SearchPattern>, distinguished_name: &DistinguishedName) -> bool {
+    match (name, search) {
+        (Some(name), SearchType::Exact) => distinguished_name.contains_components(name),
+        _ => optional_match(pattern, Some(&distinguished_name.text)),
     }
 }
 
@@ -216,7 +327,10 @@ fn check_file_by_hash(
                 IocEntrySearchResult {
                     ioc_id,
                     ioc_entry_id,
+                    check: CheckKind::File,
                     description: message,
+                    timestamp: modified_time(file_path),
+                    evidence: file_evidence(file_path),
                 }
             )
         }
@@ -235,7 +349,10 @@ fn check_file_by_hash(
                         Some(IocEntrySearchResult {
                             ioc_id,
                             ioc_entry_id,
+                            check: CheckKind::File,
                             description: message,
+                            timestamp: modified_time(file_path),
+                            evidence: file_evidence(file_path),
                         })
                     } else {
                         debug!("File search: Hashes does not match. Expected {} != {} found", searched_hash.value, file_hash.value);
@@ -256,6 +373,17 @@ fn check_file_by_hash(
     }
 }
 
+fn file_evidence(file_path: &Path) -> Evidence {
+    let metadata = std::fs::metadata(file_path).ok();
+    Evidence::File {
+        path: file_path.display().to_string(),
+        hashes: file_hashes(file_path),
+        size: metadata.as_ref().map(|it| it.len()),
+        created: metadata.as_ref().and_then(|it| it.created().ok()).map(DateTime::<Utc>::from),
+        modified: metadata.as_ref().and_then(|it| it.modified().ok()).map(DateTime::<Utc>::from),
+    }
+}
+
 /// Source: https://stackoverflow.com/questions/33654653/efficient-approach-to-get-all-logical-drive-letters-of-hdd-and-collect-root-dirs
 #[cfg(windows)]
 fn all_drives() -> Vec<PathBuf> {
@@ -289,11 +417,59 @@ fn all_drives() -> Vec<PathBuf> {
 
 #[cfg(test)]
 mod tests {
-    use crate::file_checker::all_drives;
+    use crate::authenticode::read_signature;
+    use crate::data::{HashType, Hashed, SearchType, SignatureInfo};
+    use crate::file_checker::{all_drives, signature_matches, SignatureParameters};
 
     #[test]
     fn test_all_drives() {
         let drives = all_drives();
         drives.iter().for_each(|drive| println!("[Test] Found drive {}", drive.display()))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_signature_matches() {
+        let signature = read_signature(include_bytes!("../tests/fixtures/authenticode_signed.exe")).unwrap();
+        let unsigned = SignatureInfo {
+            search: SearchType::Exact,
+            signed: Some(false),
+            valid: None,
+            subject: None,
+            issuer: None,
+            serial: None,
+            thumbprint: None,
+        };
+        let matches = |signature_info: &SignatureInfo, signature| {
+            signature_matches(&SignatureParameters::new(signature_info.clone()).unwrap(), signature, false)
+        };
+        assert!(matches(&unsigned, None));
+        assert!(!matches(&unsigned, signature.as_ref()));
+        let unsigned_parameters = SignatureParameters::new(unsigned.clone()).unwrap();
+        assert!(!signature_matches(&unsigned_parameters, None, true));
+        let catalog_signed = SignatureParameters::new(SignatureInfo { signed: Some(true), ..unsigned.clone() }).unwrap();
+        assert!(signature_matches(&catalog_signed, None, true));
+        assert!(!signature_matches(&catalog_signed, None, false));
+
+        let signed_by = SignatureInfo {
+            signed: None,
+            valid: Some(true),
+            subject: Some("CN=Evil Software Ltd".to_string()),
+            serial: Some("5C:A1:AB:1E".to_string()),
+            thumbprint: Some(Hashed {
+                algorithm: HashType::Sha1,
+                value: "7CDA2B2407E4D19EDCBFCA95CC5D460CF1BF3E6F".to_string(),
+            }),
+            ..unsigned.clone()
+        };
+        assert!(matches(&signed_by, signature.as_ref()));
+        assert!(!matches(&signed_by, None));
+        let regex = SignatureInfo {
+            search: SearchType::Regex,
+            subject: Some("Evil Soft".to_string()),
+            issuer: Some("^C=XX, O=Good Corp".to_string()),
+            ..signed_by
+        };
+        assert!(!matches(&regex, signature.as_ref()));
+        assert!(SignatureParameters::new(SignatureInfo { subject: Some("Evil (".to_string()), ..regex }).is_err());
+    }
+}
@@ -80,6 +80,39 @@ impl Hasher {
     }
 }
 
+/// MD5, SHA1 and SHA256 of the file read once, none when it cannot be read.
+pub fn file_hashes(file_path: &Path) -> Vec<Hashed> {
+    match hash_all(file_path) {
+        Ok(hashes) => hashes,
+        Err(err) => {
+            debug!("Cannot hash file {}: {}", file_path.display(), err);
+            vec![]
+        }
+    }
+}
+
+fn hash_all(file_path: &Path) -> Result<Vec<Hashed>, HashError> {
+    let mut file = File::open(file_path)?;
+    let (mut md5, mut sha1, mut sha256) = (Md5::new(), Sha1::new(), Sha256::new());
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        };
+        md5.input(&buffer[..read]);
+        sha1.input(&buffer[..read]);
+        sha256.input(&buffer[..read]);
+    }
+    Ok(vec![
+        Hashed { algorithm: HashType::Md5, value: hex::encode(md5.result()) },
+        Hashed { algorithm: HashType::Sha1, value: hex::encode(sha1.result()) },
+        Hashed { algorithm: HashType::Sha256, value: hex::encode(sha256.result()) },
+    ])
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -91,4 +124,18 @@ mod tests {
         let text = hex::encode(result);
         assert_eq!(text.as_str(), "5eb63bbbe01eeed093cb22bb8f5acdc3");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_file_hashes() {
+        use crate::data::HashType;
+        use crate::hasher::{file_hashes, Hasher};
+        use std::path::Path;
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/evil_root_ca.der");
+        let hashes = file_hashes(&path);
+        let expected: Vec<_> = [HashType::Md5, HashType::Sha1, HashType::Sha256].iter()
+            .map(|algorithm| Hasher::new(algorithm.clone()).hash_file_by_path(&path).unwrap())
+            .collect();
+        assert_eq!(hashes, expected);
+        assert!(file_hashes(&path.with_extension("missing")).is_empty());
+    }
+}
@@ -1,150 +1,299 @@
-use crate::data::{EvaluationPolicy, IocEntryId, IocId};
-use std::collections::HashMap;
+use crate::data::{EvaluationPolicy, EvaluationTrace, Evidence, IocEntryId, IocId, TemporalRelation};
+use crate::ioc_graph::IocGraph;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Clone)]
 pub struct IocEntryItem {
     pub ioc_entry_id: IocEntryId,
     pub ioc_id: IocId,
+    pub name: Option<String>,
     pub eval_policy: EvaluationPolicy,
     pub child_eval: EvaluationPolicy,
     pub children: Option<Vec<IocEntryId>>,
     pub checks_specified: u32,
+    pub weight: u32,
+    pub temporal_conditions: Vec<TemporalCondition>,
+}
+
+/// Temporal constraint with the sibling resolved to its entry id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemporalCondition {
+    pub relation: TemporalRelation,
+    pub sibling: IocEntryId,
+    pub seconds: Option<u64>,
+}
+
+impl TemporalCondition {
+    /// Whether the timestamp `own` of the entry relates to the timestamp `sibling` of the sibling entry as required.
+    fn holds(&self, own: DateTime<Utc>, sibling: DateTime<Utc>) -> bool {
+        let gap = own.signed_duration_since(sibling).num_seconds();
+        let within = |gap: i64| self.seconds.map(|seconds| gap.unsigned_abs() <= seconds).unwrap_or(true);
+        match self.relation {
+            TemporalRelation::After => gap >= 0 && within(gap),
+            TemporalRelation::Before => gap <= 0 && within(gap),
+            TemporalRelation::Within => self.seconds.is_some() && within(gap),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IocItem {
+    pub root_entry_id: IocEntryId,
+    /// Score from which the IOC is a hit
+    pub score_threshold: u8,
+}
+
+/// Score is 100 for IOCs confirmed by their evaluation policies, otherwise the weighted share of what was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IocScore {
+    pub ioc_id: IocId,
+    pub score: u8,
+    pub hit: bool,
+}
+
+/// Check of an IOC entry, an entry has at most one of each kind.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum CheckKind {
+    Certificate,
+    File,
+    /// Registry and process checks only run on Windows
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Registry,
+    Dns,
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Process,
+    Mutex,
+    Object,
+    Connection,
+    ScheduledTask,
+    Shortcut,
+    Service,
+    SshKey,
+    Account,
+    KernelModule,
+    Preload,
+    Environment,
 }
 
 #[derive(Debug, Clone)]
 pub struct IocEntrySearchResult {
     pub ioc_id: IocId,
     pub ioc_entry_id: IocEntryId,
+    /// Check which found the result, a check may find several
+    pub check: CheckKind,
     pub description: String,
+    /// When the found artifact was created or last changed, for temporal constraints
+    pub timestamp: Option<DateTime<Utc>>,
+    pub evidence: Evidence,
 }
 
 pub struct IocEvaluator {
-    root_ioc_ids: HashMap<IocId, IocEntryId>,
-    id_ioc_entries: HashMap<IocEntryId, IocEntryItem>,
-    id_successful_founds_count: HashMap<IocEntryId, u32>,
+    ioc_graph: IocGraph,
+    id_found_checks: HashMap<IocEntryId, HashSet<CheckKind>>,
+    id_found_descriptions: HashMap<IocEntryId, Vec<String>>,
+    id_timestamps: HashMap<IocEntryId, Vec<DateTime<Utc>>>,
 }
 
 impl IocEvaluator {
-    pub fn new(root_ioc_ids: HashMap<IocId, IocEntryId>,
-               id_ioc_entries: HashMap<IocEntryId, IocEntryItem>,
+    pub fn new(ioc_graph: IocGraph,
                founds: &[IocEntrySearchResult],
     ) -> Self {
-        let mut id_successful_founds_count: HashMap<IocEntryId, u32> = HashMap::new();
+        let mut id_found_checks: HashMap<IocEntryId, HashSet<CheckKind>> = HashMap::new();
+        let mut id_found_descriptions: HashMap<IocEntryId, Vec<String>> = HashMap::new();
+        let mut id_timestamps: HashMap<IocEntryId, Vec<DateTime<Utc>>> = HashMap::new();
 
         for result_value in founds {
             let entry_id = &result_value.ioc_entry_id;
-            debug!("Ioc entry {} enumerated", entry_id);
-            let success_count = id_successful_founds_count.get(entry_id).cloned();
-            match success_count {
-                None => {
-                    debug!("Ioc entry {} checked 1 time so far", entry_id);
-                    id_successful_founds_count.insert(entry_id.clone(), 1);
-                }
-                Some(success_count) => {
-                    debug!("Ioc entry {} checked {} times so far", entry_id, success_count + 1);
-                    id_successful_founds_count.insert(entry_id.clone(), success_count + 1);
-                }
+            id_found_descriptions.entry(*entry_id).or_default().push(result_value.description.clone());
+            if let Some(timestamp) = result_value.timestamp {
+                id_timestamps.entry(*entry_id).or_default().push(timestamp);
             }
+            debug!("Ioc entry {} enumerated by {:?} check", entry_id, result_value.check);
+            id_found_checks.entry(*entry_id).or_default().insert(result_value.check);
         };
 
         IocEvaluator {
-            root_ioc_ids,
-            id_ioc_entries,
-            id_successful_founds_count,
-        }
-    }
-
-    pub fn evaluate(&self) -> Vec<IocId> {
-        let ioc_ids: Vec<IocId> = self.root_ioc_ids.iter()
-            .filter(|(ioc_id, ioc_entry_id)| {
-                debug!("Evaluating IOC {} with root entry id {}", ioc_id, ioc_entry_id);
-                let ioc_entry = self.id_ioc_entries.get(ioc_entry_id);
-                let confirmed = self.evaluate_one(ioc_entry.unwrap());
-                if confirmed {
-                    debug!("IOC {} with root entry id {} confirmed", ioc_id, ioc_entry_id);
-                } else {
-                    debug!("IOC {} with root entry id {} not confirmed", ioc_id, ioc_entry_id);
-                }
-                confirmed
-            })
-            .map(|(ioc_id, _)| ioc_id.clone())
+            ioc_graph,
+            id_found_checks,
+            id_found_descriptions,
+            id_timestamps,
+        }
+    }
+
+    /// Hits and near-misses scoring at least `report_floor`, ordered by IOC id.
+    pub fn evaluate(&self, report_floor: u8) -> Vec<IocScore> {
+        let mut ioc_scores: Vec<IocScore> = self.ioc_graph.ioc_ids()
+            .filter_map(|ioc_id| self.score_ioc(*ioc_id))
+            .filter(|ioc_score| ioc_score.hit || (ioc_score.score > 0 && ioc_score.score >= report_floor))
             .collect();
-        ioc_ids
+        ioc_scores.sort_by_key(|it| it.ioc_id);
+        ioc_scores
+    }
+
+    pub fn score_ioc(&self, ioc_id: IocId) -> Option<IocScore> {
+        let ioc_item = self.ioc_graph.root(ioc_id)?;
+        let ioc_entry = self.ioc_graph.root_entry(ioc_id)?;
+        debug!("Evaluating IOC {} with root entry id {}", ioc_id, ioc_item.root_entry_id);
+        let score = self.entry_score(ioc_entry);
+        let hit = score >= ioc_item.score_threshold;
+        if hit {
+            debug!("IOC {} with root entry id {} confirmed, score {}", ioc_id, ioc_item.root_entry_id, score);
+        } else {
+            debug!("IOC {} with root entry id {} not confirmed, score {}", ioc_id, ioc_item.root_entry_id, score);
+        }
+        Some(IocScore { ioc_id, score, hit })
+    }
+
+    /// Evaluation tree of the IOC, from its root entry down.
+    pub fn explain(&self, ioc_id: IocId) -> Option<EvaluationTrace> {
+        self.ioc_graph.root_entry(ioc_id).map(|ioc_entry| self.trace_one(ioc_entry))
+    }
+
+    /// Entries of the IOC under an odd number of negating policies, what they find counts against the IOC.
+    pub fn negated_entries(&self, ioc_id: IocId) -> HashSet<IocEntryId> {
+        let mut negated = HashSet::new();
+        if let Some(ioc_entry) = self.ioc_graph.root_entry(ioc_id) {
+            self.collect_negated(ioc_entry, false, &mut negated);
+        }
+        negated
+    }
+
+    fn collect_negated(&self, ioc_entry: &IocEntryItem, negated_above: bool, negated: &mut HashSet<IocEntryId>) {
+        let entry_negated = negated_above != (ioc_entry.eval_policy == EvaluationPolicy::None);
+        if entry_negated {
+            negated.insert(ioc_entry.ioc_entry_id);
+        }
+        let children_negated = entry_negated != (ioc_entry.child_eval == EvaluationPolicy::None);
+        for child in ioc_entry.children.iter().flatten().filter_map(|id| self.ioc_graph.entry(*id)) {
+            self.collect_negated(child, children_negated, negated);
+        }
     }
 
+    fn trace_one(&self, ioc_entry: &IocEntryItem) -> EvaluationTrace {
+        let children = ioc_entry.children.as_ref().filter(|children| !children.is_empty());
+        EvaluationTrace {
+            ioc_entry_id: ioc_entry.ioc_entry_id,
+            name: ioc_entry.name.clone(),
+            eval_policy: ioc_entry.eval_policy.clone(),
+            child_eval_policy: ioc_entry.child_eval.clone(),
+            checks_specified: ioc_entry.checks_specified,
+            checks_found: self.evaluate_without_offspring(ioc_entry),
+            search_reports: self.id_found_descriptions.get(&ioc_entry.ioc_entry_id).cloned().unwrap_or_default(),
+            offspring_satisfied: children.map(|children| self.evaluate_children(ioc_entry, children)),
+            temporal_constraints_satisfied: Some(self.evaluate_temporal_conditions(ioc_entry))
+                .filter(|_| !ioc_entry.temporal_conditions.is_empty()),
+            offspring: children.iter()
+                .flat_map(|children| children.iter())
+                .filter_map(|id| self.ioc_graph.entry(*id))
+                .map(|child| self.trace_one(child))
+                .collect(),
+            confirmed: self.evaluate_one(ioc_entry),
+            score: self.entry_score(ioc_entry),
+        }
+    }
+
+    fn entry_score(&self, ioc_entry: &IocEntryItem) -> u8 {
+        if self.evaluate_one(ioc_entry) {
+            100
+        } else {
+            // Unconfirmed entries never get the full score
+            ((self.score_one(ioc_entry) * 100.0).floor() as u8).min(99)
+        }
+    }
+
+    /// 1 for confirmed entries, otherwise found checks and children scores weighted by the children weights,
+    /// inverted by negating policies.
+    fn score_one(&self, ioc_entry: &IocEntryItem) -> f64 {
+        if self.evaluate_one(ioc_entry) {
+            return 1.0;
+        }
+        let mut achieved = f64::from(self.evaluate_without_offspring(ioc_entry));
+        let mut total = f64::from(ioc_entry.checks_specified);
+        for child in ioc_entry.children.iter().flatten().filter_map(|id| self.ioc_graph.entry(*id)) {
+            let child_score = self.score_one(child);
+            let weight = f64::from(child.weight);
+            achieved += weight * if ioc_entry.child_eval == EvaluationPolicy::None { 1.0 - child_score } else { child_score };
+            total += weight;
+        }
+        if total == 0.0 {
+            return 0.0;
+        }
+        let share = achieved / total;
+        if ioc_entry.eval_policy == EvaluationPolicy::None { 1.0 - share } else { share }
+    }
+
+    /// The entry's own checks and, when it has children, their aggregate by `child_eval` are the operands
+    /// of its `eval_policy`, which has to be satisfied together with the temporal conditions. An entry
+    /// without checks and children is never confirmed.
     fn evaluate_one(
         &self,
         ioc_entry: &IocEntryItem,
     ) -> bool {
-        let evaluated_self = self.evaluate_without_offspring(ioc_entry);
-        match evaluated_self {
-            false => match ioc_entry.eval_policy {
-                EvaluationPolicy::All => if ioc_entry.checks_specified == 0 {
-                    self.evaluate_children(&ioc_entry, false)
-                } else {
-                    false
-                },
-                EvaluationPolicy::One => self.evaluate_children(&ioc_entry, false)
-            }
-            true => {
-                match ioc_entry.eval_policy {
-                    EvaluationPolicy::All => self.evaluate_children(&ioc_entry, true),
-                    EvaluationPolicy::One => true
-                }
+        let mut satisfied = self.evaluate_without_offspring(ioc_entry);
+        let mut total = ioc_entry.checks_specified;
+        if let Some(children) = ioc_entry.children.as_ref().filter(|children| !children.is_empty()) {
+            total += 1;
+            if self.evaluate_children(ioc_entry, children) {
+                satisfied += 1;
             }
         }
+        let confirmed = policy_satisfied(&ioc_entry.eval_policy, satisfied, total)
+            && self.evaluate_temporal_conditions(ioc_entry);
+        debug!("Ioc entry {} satisfied {} out of {}, policy {:?}: {}", ioc_entry.ioc_entry_id, satisfied, total, ioc_entry.eval_policy, confirmed);
+        confirmed
     }
 
+    /// Number of the entry's own checks that found something, however many results each of them reported.
     fn evaluate_without_offspring(
         &self,
         ioc_entry: &IocEntryItem,
-    ) -> bool {
-        let successful_founds_count = self.id_successful_founds_count.get(&ioc_entry.ioc_entry_id).copied();
-        match successful_founds_count {
-            None => false,
-            Some(successful_founds_count) => match ioc_entry.eval_policy {
-                EvaluationPolicy::All => {
-                    debug!("Ioc entry {} found {} times out of {}, policy ALL", ioc_entry.ioc_entry_id, successful_founds_count, ioc_entry.checks_specified);
-                    successful_founds_count == ioc_entry.checks_specified
-                }
-                EvaluationPolicy::One => {
-                    debug!("Ioc entry {} found {} times out of {}, policy ONE", ioc_entry.ioc_entry_id, successful_founds_count, ioc_entry.checks_specified);
-                    successful_founds_count > 0u32
-                }
-            },
+    ) -> u32 {
+        let found_checks = self.id_found_checks.get(&ioc_entry.ioc_entry_id).map(|it| it.len() as u32).unwrap_or(0);
+        debug!("Ioc entry {} found by {} checks out of {}", ioc_entry.ioc_entry_id, found_checks, ioc_entry.checks_specified);
+        found_checks.min(ioc_entry.checks_specified)
+    }
+
+    /// Every condition needs a timestamp of the entry and one of its sibling that relate as required,
+    /// entries without timestamps satisfy none.
+    fn evaluate_temporal_conditions(&self, ioc_entry: &IocEntryItem) -> bool {
+        if ioc_entry.temporal_conditions.is_empty() {
+            return true;
+        }
+        let own_timestamps = self.subtree_timestamps(ioc_entry.ioc_entry_id);
+        ioc_entry.temporal_conditions.iter().all(|condition| {
+            let sibling_timestamps = self.subtree_timestamps(condition.sibling);
+            let holds = own_timestamps.iter()
+                .any(|own| sibling_timestamps.iter().any(|sibling| condition.holds(*own, *sibling)));
+            debug!("Ioc entry {} {:?} entry {}: {}", ioc_entry.ioc_entry_id, condition.relation, condition.sibling, holds);
+            holds
+        })
+    }
+
+    /// Timestamps of what the entry and its offspring found.
+    fn subtree_timestamps(&self, ioc_entry_id: IocEntryId) -> Vec<DateTime<Utc>> {
+        let mut timestamps = self.id_timestamps.get(&ioc_entry_id).cloned().unwrap_or_default();
+        if let Some(ioc_entry) = self.ioc_graph.entry(ioc_entry_id) {
+            for child in ioc_entry.children.iter().flatten() {
+                timestamps.extend(self.subtree_timestamps(*child));
+            }
         }
+        timestamps
     }
 
     fn evaluate_children(
         &self,
         parent_ioc_entry: &IocEntryItem,
-        empty_children_evaluation_result: bool,
+        children: &[IocEntryId],
     ) -> bool {
-        let children = parent_ioc_entry.children.as_ref();
-        match children {
-            None => empty_children_evaluation_result,
-            Some(children) => {
-                if children.is_empty() {
-                    empty_children_evaluation_result
-                } else {
-                    let found_children = self.evaluate_non_empty_vector(children);
-                    match parent_ioc_entry.child_eval {
-                        EvaluationPolicy::All => {
-                            found_children == children.len()
-                        }
-                        EvaluationPolicy::One => {
-                            found_children > 0
-                        }
-                    }
-                }
-            }
-        }
+        let found_children = self.evaluate_non_empty_vector(children);
+        policy_satisfied(&parent_ioc_entry.child_eval, found_children as u32, children.len() as u32)
     }
 
-    fn evaluate_non_empty_vector(&self, ioc_entry_ids: &Vec<IocEntryId>) -> usize {
+    fn evaluate_non_empty_vector(&self, ioc_entry_ids: &[IocEntryId]) -> usize {
         let found_count = ioc_entry_ids.iter()
             .filter(|ioc_entry_id| {
-                let ioc_entry = self.id_ioc_entries.get(ioc_entry_id);
+                let ioc_entry = self.ioc_graph.entry(**ioc_entry_id);
                 match ioc_entry {
                     None => false,
                     Some(ioc_entry) => {
@@ -155,3 +304,441 @@ impl IocEvaluator {
         found_count
     }
 }
+
+/// Indented text form of the trace for the console, `[+]` marks confirmed entries.
+pub fn render_trace(trace: &EvaluationTrace) -> String {
+    let mut text = String::new();
+    render_entry(trace, 0, &mut text);
+    text
+}
+
+fn render_entry(trace: &EvaluationTrace, depth: usize, text: &mut String) {
+    let indent = "    ".repeat(depth);
+    text.push_str(&format!("{}[{}] Entry {}", indent, if trace.confirmed { '+' } else { '-' }, trace.ioc_entry_id));
+    if let Some(name) = &trace.name {
+        text.push_str(&format!(" {}", name));
+    }
+    text.push_str(&format!(": score {}, policy {}, {} of {} checks found",
+                           trace.score, trace.eval_policy, trace.checks_found, trace.checks_specified));
+    if let Some(offspring_satisfied) = trace.offspring_satisfied {
+        text.push_str(&format!(", offspring policy {} {}",
+                               trace.child_eval_policy,
+                               if offspring_satisfied { "satisfied" } else { "not satisfied" }));
+    }
+    if let Some(temporal_constraints_satisfied) = trace.temporal_constraints_satisfied {
+        text.push_str(&format!(", temporal constraints {}",
+                               if temporal_constraints_satisfied { "satisfied" } else { "not satisfied" }));
+    }
+    text.push('\n');
+    for search_report in &trace.search_reports {
+        text.push_str(&format!("{}      {}\n", indent, search_report));
+    }
+    for child in &trace.offspring {
+        render_entry(child, depth + 1, text);
+    }
+}
+
+/// Whether `satisfied` out of `total` operands fulfil the policy, no operands fulfil none.
+fn policy_satisfied(policy: &EvaluationPolicy, satisfied: u32, total: u32) -> bool {
+    if total == 0 {
+        return false;
+    }
+    match policy {
+        EvaluationPolicy::All => satisfied == total,
+        EvaluationPolicy::One => satisfied > 0,
+        EvaluationPolicy::None => satisfied == 0,
+        EvaluationPolicy::AtLeast(count) => *count > 0 && satisfied >= *count,
+        EvaluationPolicy::Percentage(percentage) => *percentage <= 100 && satisfied * 100 >= u32::from(*percentage) * total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{EvaluationPolicy, Evidence, IocEntryId, IocId, TemporalRelation};
+    use crate::ioc_graph::IocGraph;
+    use crate::ioc_evaluator::{policy_satisfied, render_trace, CheckKind, IocEntryItem, IocEntrySearchResult, IocEvaluator, IocItem, IocScore,
+                               TemporalCondition};
+    use chrono::{DateTime, TimeZone, Utc};
+    use std::collections::HashMap;
+
+    fn entry(ioc_entry_id: IocEntryId, eval_policy: EvaluationPolicy, checks_specified: u32,
+             child_eval: EvaluationPolicy, children: Option<Vec<IocEntryId>>) -> IocEntryItem {
+        IocEntryItem {
+            ioc_entry_id, ioc_id: 1, name: None, eval_policy, child_eval, children, checks_specified, weight: 1,
+            temporal_conditions: vec![],
+        }
+    }
+
+    fn search_result(ioc_entry_id: IocEntryId, check: CheckKind, description: &str, timestamp: Option<DateTime<Utc>>) -> IocEntrySearchResult {
+        IocEntrySearchResult {
+            ioc_id: 1,
+            ioc_entry_id,
+            check,
+            description: description.to_string(),
+            timestamp,
+            evidence: Evidence::Dns { name: "evil.com".to_string(), address: None, source: "cache".to_string() },
+        }
+    }
+
+    const CHECKS: [CheckKind; 5] = [CheckKind::File, CheckKind::Process, CheckKind::Dns, CheckKind::Registry, CheckKind::Connection];
+
+    /// Every further occurrence of an entry id is a result of its next check.
+    fn found(ioc_entry_ids: &[IocEntryId]) -> Vec<IocEntrySearchResult> {
+        let mut occurrences: HashMap<IocEntryId, usize> = HashMap::new();
+        ioc_entry_ids.iter()
+            .map(|ioc_entry_id| {
+                let occurrence = occurrences.entry(*ioc_entry_id).or_insert(0);
+                let check = CHECKS[*occurrence];
+                *occurrence += 1;
+                search_result(*ioc_entry_id, check, "", None)
+            })
+            .collect()
+    }
+
+    fn checked(founds: &[(IocEntryId, CheckKind)]) -> Vec<IocEntrySearchResult> {
+        founds.iter().map(|(ioc_entry_id, check)| search_result(*ioc_entry_id, *check, "", None)).collect()
+    }
+
+    /// Results timestamped at the given seconds since the epoch.
+    fn found_at(founds: &[(IocEntryId, i64)]) -> Vec<IocEntrySearchResult> {
+        founds.iter()
+            .map(|(ioc_entry_id, seconds)| search_result(*ioc_entry_id, CheckKind::File, "", Some(Utc.timestamp_opt(*seconds, 0).unwrap())))
+            .collect()
+    }
+
+    /// Evaluator of IOC 1 whose root entry is the first one.
+    fn evaluator(entries: Vec<IocEntryItem>, founds: &[IocEntryId], score_threshold: u8) -> IocEvaluator {
+        evaluator_of(entries, &found(founds), score_threshold)
+    }
+
+    fn evaluator_of(entries: Vec<IocEntryItem>, founds: &[IocEntrySearchResult], score_threshold: u8) -> IocEvaluator {
+        let mut root_ioc_ids: HashMap<IocId, IocItem> = HashMap::new();
+        root_ioc_ids.insert(1, IocItem { root_entry_id: entries[0].ioc_entry_id, score_threshold });
+        let id_ioc_entries = entries.into_iter().map(|it| (it.ioc_entry_id, it)).collect();
+        IocEvaluator::new(IocGraph::new(root_ioc_ids, id_ioc_entries).unwrap(), founds)
+    }
+
+    fn score(entries: Vec<IocEntryItem>, founds: &[IocEntryId], score_threshold: u8, report_floor: u8) -> Option<IocScore> {
+        evaluator(entries, founds, score_threshold).evaluate(report_floor).pop()
+    }
+
+    fn confirmed(entries: Vec<IocEntryItem>, founds: &[IocEntryId]) -> bool {
+        confirmed_by(entries, &found(founds))
+    }
+
+    fn confirmed_by(entries: Vec<IocEntryItem>, founds: &[IocEntrySearchResult]) -> bool {
+        evaluator_of(entries, founds, 100).evaluate(100).pop().map(|it| it.hit).unwrap_or(false)
+    }
+
+    fn weighted(mut entry: IocEntryItem, weight: u32) -> IocEntryItem {
+        entry.weight = weight;
+        entry
+    }
+
+    #[test]
+    fn test_policy_satisfied() {
+        let cases = [
+            (EvaluationPolicy::All, [false, false, false, false, true]),
+            (EvaluationPolicy::One, [false, false, true, true, true]),
+            (EvaluationPolicy::None, [false, true, false, false, false]),
+            (EvaluationPolicy::AtLeast(2), [false, false, false, true, true]),
+            (EvaluationPolicy::AtLeast(0), [false, false, false, false, false]),
+            (EvaluationPolicy::Percentage(50), [false, false, false, true, true]),
+            (EvaluationPolicy::Percentage(33), [false, false, true, true, true]),
+            (EvaluationPolicy::Percentage(34), [false, false, false, true, true]),
+            (EvaluationPolicy::Percentage(0), [false, true, true, true, true]),
+            (EvaluationPolicy::Percentage(100), [false, false, false, false, true]),
+            (EvaluationPolicy::Percentage(101), [false, false, false, false, false]),
+        ];
+        // (satisfied, total) operands
+        let operands = [(0, 0), (0, 3), (1, 3), (2, 3), (3, 3)];
+        for (policy, expected) in cases.iter() {
+            for ((satisfied, total), expected) in operands.iter().zip(expected.iter()) {
+                assert_eq!(policy_satisfied(policy, *satisfied, *total), *expected,
+                           "{:?} with {} out of {}", policy, satisfied, total);
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_policy() {
+        let policies: Vec<EvaluationPolicy> = serde_json::from_str(r#"["ALL", "NONE", {"AT_LEAST": 3}, {"PERCENTAGE": 60}]"#).unwrap();
+        assert_eq!(policies, vec![
+            EvaluationPolicy::All,
+            EvaluationPolicy::None,
+            EvaluationPolicy::AtLeast(3),
+            EvaluationPolicy::Percentage(60),
+        ]);
+    }
+
+    #[test]
+    fn test_evaluate_own_checks() {
+        let policies = [
+            (EvaluationPolicy::All, [false, false, true]),
+            (EvaluationPolicy::One, [false, true, true]),
+            (EvaluationPolicy::None, [true, false, false]),
+            (EvaluationPolicy::AtLeast(2), [false, false, true]),
+            (EvaluationPolicy::Percentage(50), [false, true, true]),
+        ];
+        // none, one and both of the two checks found
+        let founds: [&[(IocEntryId, CheckKind)]; 3] = [&[], &[(1, CheckKind::File)], &[(1, CheckKind::File), (1, CheckKind::Process)]];
+        for (policy, expected) in policies.iter() {
+            for (founds, expected) in founds.iter().zip(expected.iter()) {
+                let entries = vec![entry(1, policy.clone(), 2, EvaluationPolicy::One, None)];
+                assert_eq!(confirmed_by(entries, &checked(founds)), *expected, "{:?} with {} found", policy, founds.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluate_results_of_one_check() {
+        // one check reporting three files
+        let three_files = checked(&[(1, CheckKind::File), (1, CheckKind::File), (1, CheckKind::File)]);
+        assert!(confirmed_by(vec![entry(1, EvaluationPolicy::All, 1, EvaluationPolicy::One, None)], &three_files));
+        for policy in [EvaluationPolicy::All, EvaluationPolicy::AtLeast(2), EvaluationPolicy::Percentage(60)].iter() {
+            let entries = vec![entry(1, policy.clone(), 2, EvaluationPolicy::One, None)];
+            assert!(!confirmed_by(entries, &three_files), "{:?}", policy);
+        }
+        // at least 3 of 5 indicators
+        let entries = || vec![entry(1, EvaluationPolicy::AtLeast(3), 5, EvaluationPolicy::One, None)];
+        assert!(!confirmed_by(entries(), &three_files));
+        assert!(confirmed_by(entries(), &checked(&[(1, CheckKind::File), (1, CheckKind::Dns), (1, CheckKind::Process)])));
+    }
+
+    #[test]
+    fn test_evaluate_empty_entry() {
+        for policy in [EvaluationPolicy::All, EvaluationPolicy::One, EvaluationPolicy::None, EvaluationPolicy::AtLeast(0)].iter() {
+            assert!(!confirmed(vec![entry(1, policy.clone(), 0, EvaluationPolicy::All, None)], &[]));
+            assert!(!confirmed(vec![entry(1, policy.clone(), 0, EvaluationPolicy::All, Some(vec![]))], &[]));
+        }
+    }
+
+    #[test]
+    fn test_evaluate_children() {
+        let children = || Some(vec![2, 3, 4, 5, 6]);
+        let leaves = || (2..=6).map(|id| entry(id, EvaluationPolicy::One, 1, EvaluationPolicy::One, None));
+        let policies = [
+            (EvaluationPolicy::All, [false, false, false, true]),
+            (EvaluationPolicy::One, [false, true, true, true]),
+            (EvaluationPolicy::None, [true, false, false, false]),
+            (EvaluationPolicy::AtLeast(3), [false, false, true, true]),
+            (EvaluationPolicy::Percentage(40), [false, false, true, true]),
+        ];
+        // none, one, three and all five children found
+        let founds: [&[IocEntryId]; 4] = [&[], &[4], &[2, 4, 6], &[2, 3, 4, 5, 6]];
+        for (policy, expected) in policies.iter() {
+            for (founds, expected) in founds.iter().zip(expected.iter()) {
+                let mut entries = vec![entry(1, EvaluationPolicy::All, 0, policy.clone(), children())];
+                entries.extend(leaves());
+                assert_eq!(confirmed(entries, founds), *expected, "{:?} with {} children found", policy, founds.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluate_checks_and_children() {
+        // own check and the children aggregate are two operands
+        let tree = |eval_policy| vec![
+            entry(1, eval_policy, 1, EvaluationPolicy::One, Some(vec![2])),
+            entry(2, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+        ];
+        assert!(confirmed(tree(EvaluationPolicy::All), &[1, 2]));
+        assert!(!confirmed(tree(EvaluationPolicy::All), &[1]));
+        assert!(!confirmed(tree(EvaluationPolicy::All), &[2]));
+        assert!(confirmed(tree(EvaluationPolicy::One), &[2]));
+        assert!(!confirmed(tree(EvaluationPolicy::One), &[]));
+        assert!(confirmed(tree(EvaluationPolicy::None), &[]));
+        assert!(!confirmed(tree(EvaluationPolicy::None), &[2]));
+        assert!(confirmed(tree(EvaluationPolicy::AtLeast(2)), &[1, 2]));
+        assert!(!confirmed(tree(EvaluationPolicy::AtLeast(2)), &[1, 1]));
+        assert!(confirmed(tree(EvaluationPolicy::Percentage(50)), &[2]));
+    }
+
+    #[test]
+    fn test_evaluate_negated_child() {
+        // file exists but is not signed
+        let tree = || vec![
+            entry(1, EvaluationPolicy::All, 1, EvaluationPolicy::None, Some(vec![2])),
+            entry(2, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+        ];
+        assert!(confirmed(tree(), &[1]));
+        assert!(!confirmed(tree(), &[1, 2]));
+        assert!(!confirmed(tree(), &[]));
+        assert!(!confirmed(tree(), &[2]));
+        assert_eq!(evaluator(tree(), &[], 100).negated_entries(1), [2].iter().copied().collect());
+        let double_negation = vec![
+            entry(1, EvaluationPolicy::All, 0, EvaluationPolicy::None, Some(vec![2])),
+            entry(2, EvaluationPolicy::None, 1, EvaluationPolicy::One, None),
+        ];
+        assert!(evaluator(double_negation, &[], 100).negated_entries(1).is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_nested_negation() {
+        // process running and none of the expected parents
+        let tree = || vec![
+            entry(1, EvaluationPolicy::All, 0, EvaluationPolicy::All, Some(vec![2, 3])),
+            entry(2, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+            entry(3, EvaluationPolicy::None, 0, EvaluationPolicy::One, Some(vec![4, 5])),
+            entry(4, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+            entry(5, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+        ];
+        assert!(confirmed(tree(), &[2]));
+        assert!(!confirmed(tree(), &[2, 5]));
+        assert!(!confirmed(tree(), &[]));
+        assert!(!confirmed(tree(), &[4]));
+        assert_eq!(evaluator(tree(), &[], 100).negated_entries(1), [3, 4, 5].iter().copied().collect());
+    }
+
+    #[test]
+    fn test_score_near_miss() {
+        let tree = || vec![
+            entry(1, EvaluationPolicy::All, 2, EvaluationPolicy::All, Some(vec![2, 3])),
+            entry(2, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+            entry(3, EvaluationPolicy::All, 2, EvaluationPolicy::One, None),
+        ];
+        // 2 checks of the root, child 2 and half of child 3
+        assert_eq!(score(tree(), &[1, 1, 2, 3], 100, 50), Some(IocScore { ioc_id: 1, score: 87, hit: false }));
+        assert_eq!(score(tree(), &[1, 1, 2, 3, 3], 100, 50), Some(IocScore { ioc_id: 1, score: 100, hit: true }));
+        assert_eq!(score(tree(), &[1, 1, 2, 3], 80, 50), Some(IocScore { ioc_id: 1, score: 87, hit: true }));
+        assert_eq!(score(tree(), &[1], 100, 50), None);
+        assert_eq!(score(tree(), &[1], 100, 20), Some(IocScore { ioc_id: 1, score: 25, hit: false }));
+        assert_eq!(score(tree(), &[], 100, 0), None);
+    }
+
+    #[test]
+    fn test_score_weights() {
+        let tree = |weight| vec![
+            entry(1, EvaluationPolicy::All, 0, EvaluationPolicy::All, Some(vec![2, 3])),
+            weighted(entry(2, EvaluationPolicy::One, 1, EvaluationPolicy::One, None), weight),
+            entry(3, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+        ];
+        assert_eq!(score(tree(1), &[2], 100, 0).unwrap().score, 50);
+        assert_eq!(score(tree(3), &[2], 100, 0).unwrap().score, 75);
+        assert_eq!(score(tree(3), &[3], 100, 0).unwrap().score, 25);
+        assert_eq!(score(tree(0), &[3], 100, 0).unwrap().score, 99);
+    }
+
+    #[test]
+    fn test_score_negation() {
+        let tree = || vec![
+            entry(1, EvaluationPolicy::All, 1, EvaluationPolicy::None, Some(vec![2, 3])),
+            entry(2, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+            entry(3, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+        ];
+        assert_eq!(score(tree(), &[1], 100, 0).unwrap().score, 100);
+        assert_eq!(score(tree(), &[1, 2], 100, 0).unwrap().score, 66);
+        assert_eq!(score(tree(), &[1, 2, 3], 100, 0).unwrap().score, 33);
+        let negated = vec![entry(1, EvaluationPolicy::None, 4, EvaluationPolicy::One, None)];
+        assert_eq!(score(negated, &[1], 100, 0).unwrap().score, 75);
+    }
+
+    #[test]
+    fn test_score_unsatisfiable_policy() {
+        // all found, but fewer than required
+        let entries = vec![entry(1, EvaluationPolicy::AtLeast(3), 2, EvaluationPolicy::One, None)];
+        assert_eq!(score(entries, &[1, 1], 100, 0), Some(IocScore { ioc_id: 1, score: 99, hit: false }));
+    }
+
+    fn timed(mut entry: IocEntryItem, relation: TemporalRelation, sibling: IocEntryId, seconds: Option<u64>) -> IocEntryItem {
+        entry.temporal_conditions.push(TemporalCondition { relation, sibling, seconds });
+        entry
+    }
+
+    #[test]
+    fn test_temporal_condition_holds() {
+        let cases = [
+            // (relation, seconds, gap of the entry after its sibling, holds)
+            (TemporalRelation::After, None, 3600, true),
+            (TemporalRelation::After, None, 0, true),
+            (TemporalRelation::After, None, -1, false),
+            (TemporalRelation::After, Some(60), 61, false),
+            (TemporalRelation::Before, None, -3600, true),
+            (TemporalRelation::Before, Some(60), -60, true),
+            (TemporalRelation::Before, None, 1, false),
+            (TemporalRelation::Within, Some(300), -300, true),
+            (TemporalRelation::Within, Some(300), 301, false),
+            (TemporalRelation::Within, None, 0, false),
+        ];
+        let sibling = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        for (relation, seconds, gap, holds) in cases.iter() {
+            let condition = TemporalCondition { relation: *relation, sibling: 2, seconds: *seconds };
+            assert_eq!(condition.holds(Utc.timestamp_opt(1_600_000_000 + gap, 0).unwrap(), sibling), *holds,
+                       "{:?} {:?} with gap {}", relation, seconds, gap);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_file_written_around_process_start() {
+        // process 2 started, file 3 written within 5 minutes of it
+        let tree = || vec![
+            entry(1, EvaluationPolicy::All, 0, EvaluationPolicy::All, Some(vec![2, 3])),
+            entry(2, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+            timed(entry(3, EvaluationPolicy::One, 1, EvaluationPolicy::One, None), TemporalRelation::Within, 2, Some(300)),
+        ];
+        let hit = |founds: &[(IocEntryId, i64)]| evaluator_of(tree(), &found_at(founds), 100).evaluate(100).pop().is_some();
+        assert!(hit(&[(2, 1000), (3, 1200)]));
+        assert!(hit(&[(2, 1000), (3, 800)]));
+        assert!(!hit(&[(2, 1000), (3, 1400)]));
+        // any pair of results may satisfy it
+        assert!(hit(&[(2, 1000), (3, 5000), (3, 1100)]));
+        assert!(!hit(&[(2, 1000)]));
+
+        let score = evaluator_of(tree(), &found(&[2, 3]), 100).evaluate(0).pop().unwrap();
+        assert_eq!(score, IocScore { ioc_id: 1, score: 99, hit: false });
+    }
+
+    #[test]
+    fn test_evaluate_connection_after_offspring() {
+        // dropper entry 2 with two file checks in children 4 and 5, connection 3 after any of them
+        let tree = || vec![
+            entry(1, EvaluationPolicy::All, 0, EvaluationPolicy::All, Some(vec![2, 3])),
+            entry(2, EvaluationPolicy::All, 0, EvaluationPolicy::One, Some(vec![4, 5])),
+            timed(entry(3, EvaluationPolicy::One, 1, EvaluationPolicy::One, None), TemporalRelation::After, 2, None),
+            entry(4, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+            entry(5, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+        ];
+        let evaluator = evaluator_of(tree(), &found_at(&[(5, 2000), (3, 3000)]), 100);
+        assert!(evaluator.score_ioc(1).unwrap().hit);
+        let evaluator = evaluator_of(tree(), &found_at(&[(5, 2000), (3, 1000)]), 100);
+        assert!(!evaluator.score_ioc(1).unwrap().hit);
+        let trace = evaluator.explain(1).unwrap();
+        assert_eq!(trace.offspring[1].temporal_constraints_satisfied, Some(false));
+        assert_eq!(trace.offspring[0].temporal_constraints_satisfied, None);
+        assert!(render_trace(&trace).contains("[-] Entry 3: score 99, policy ONE, 1 of 1 checks found, temporal constraints not satisfied\n"));
+    }
+
+    #[test]
+    fn test_explain() {
+        let mut root = entry(1, EvaluationPolicy::All, 1, EvaluationPolicy::AtLeast(2), Some(vec![2, 3, 4]));
+        root.name = Some("Dropper".to_string());
+        let entries = vec![
+            root,
+            entry(2, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+            entry(3, EvaluationPolicy::One, 1, EvaluationPolicy::One, None),
+            entry(4, EvaluationPolicy::None, 1, EvaluationPolicy::One, None),
+        ];
+        let founds = vec![
+            search_result(1, CheckKind::File, "File search: Found dropper.exe", None),
+            search_result(3, CheckKind::Dns, "DNS search: Found evil.com", None),
+        ];
+        let mut root_ioc_ids: HashMap<IocId, IocItem> = HashMap::new();
+        root_ioc_ids.insert(1, IocItem { root_entry_id: 1, score_threshold: 100 });
+        let id_ioc_entries = entries.into_iter().map(|it| (it.ioc_entry_id, it)).collect();
+        let evaluator = IocEvaluator::new(IocGraph::new(root_ioc_ids, id_ioc_entries).unwrap(), &founds);
+        assert!(evaluator.explain(2).is_none());
+        let trace = evaluator.explain(1).unwrap();
+        assert!(trace.confirmed);
+        assert_eq!(trace.checks_found, 1);
+        assert_eq!(trace.offspring_satisfied, Some(true));
+        assert_eq!(trace.offspring.iter().map(|it| it.confirmed).collect::<Vec<bool>>(), vec![false, true, true]);
+        assert_eq!(trace.offspring[1].search_reports, vec!["DNS search: Found evil.com"]);
+        assert_eq!(render_trace(&trace), "\
+            [+] Entry 1 Dropper: score 100, policy ALL, 1 of 1 checks found, offspring policy AT_LEAST 2 satisfied\n      \
+            File search: Found dropper.exe\n    \
+            [-] Entry 2: score 0, policy ONE, 0 of 1 checks found\n    \
+            [+] Entry 3: score 100, policy ONE, 1 of 1 checks found\n          \
+            DNS search: Found evil.com\n    \
+            [+] Entry 4: score 100, policy NONE, 0 of 1 checks found\n");
+    }
+}
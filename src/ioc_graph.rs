@@ -0,0 +1,396 @@
+use crate::data::{EvaluationPolicy, Ioc, IocEntry, IocEntryId, IocId, TemporalConstraint, TemporalRelation};
+use crate::ioc_evaluator::{IocEntryItem, IocItem, TemporalCondition};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fmt;
+
+#[derive(Debug)]
+pub struct IocGraphError {
+    pub kind: String,
+    pub message: String,
+}
+
+impl Display for IocGraphError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "IocGraphError(kind: {}, message: {})", self.kind, self.message)
+    }
+}
+
+/// IOC definitions compiled for evaluation. Every root entry and child exists and belongs to exactly
+/// one parent within the same IOC, so the entries of each IOC form a tree.
+pub struct IocGraph {
+    roots: HashMap<IocId, IocItem>,
+    entries: HashMap<IocEntryId, IocEntryItem>,
+}
+
+impl IocGraph {
+    pub fn new(roots: HashMap<IocId, IocItem>, entries: HashMap<IocEntryId, IocEntryItem>) -> Result<Self, IocGraphError> {
+        let mut visited: HashSet<IocEntryId> = HashSet::new();
+        for (ioc_id, ioc_item) in &roots {
+            let mut pending = vec![ioc_item.root_entry_id];
+            while let Some(ioc_entry_id) = pending.pop() {
+                let ioc_entry = entries.get(&ioc_entry_id).ok_or_else(|| IocGraphError {
+                    kind: "Missing entry".to_string(),
+                    message: format!("IOC {} refers to entry {} which does not exist", ioc_id, ioc_entry_id),
+                })?;
+                if ioc_entry.ioc_id != *ioc_id {
+                    return Err(IocGraphError {
+                        kind: "Foreign entry".to_string(),
+                        message: format!("Entry {} of IOC {} is reachable from IOC {}", ioc_entry_id, ioc_entry.ioc_id, ioc_id),
+                    });
+                }
+                if !visited.insert(ioc_entry_id) {
+                    return Err(IocGraphError {
+                        kind: "Shared entry".to_string(),
+                        message: format!("Entry {} of IOC {} is reachable more than once", ioc_entry_id, ioc_id),
+                    });
+                }
+                pending.extend(ioc_entry.children.iter().flatten());
+            }
+        }
+        if let Some(orphan) = entries.keys().find(|id| !visited.contains(id)) {
+            return Err(IocGraphError {
+                kind: "Unreachable entry".to_string(),
+                message: format!("Entry {} is not reachable from any IOC", orphan),
+            });
+        }
+        Ok(IocGraph { roots, entries })
+    }
+
+    pub fn ioc_ids(&self) -> impl Iterator<Item=&IocId> {
+        self.roots.keys()
+    }
+
+    pub fn root(&self, ioc_id: IocId) -> Option<&IocItem> {
+        self.roots.get(&ioc_id)
+    }
+
+    pub fn entry(&self, ioc_entry_id: IocEntryId) -> Option<&IocEntryItem> {
+        self.entries.get(&ioc_entry_id)
+    }
+
+    pub fn root_entry(&self, ioc_id: IocId) -> Option<&IocEntryItem> {
+        self.root(ioc_id).and_then(|ioc_item| self.entry(ioc_item.root_entry_id))
+    }
+}
+
+/// Compiles IOC definitions into an [`IocGraph`], entry ids come from a single counter shared by all IOCs.
+pub struct IocGraphBuilder {
+    roots: HashMap<IocId, IocItem>,
+    entries: HashMap<IocEntryId, IocEntryItem>,
+    next_entry_id: IocEntryId,
+}
+
+impl IocGraphBuilder {
+    pub fn new() -> Self {
+        IocGraphBuilder { roots: HashMap::new(), entries: HashMap::new(), next_entry_id: 1 }
+    }
+
+    /// Adds the IOC after validating it, nothing is added when it is invalid. `register_checks` hands the checks
+    /// of each entry to the checkers under the entry id and returns how many of them will run.
+    pub fn add_ioc<F>(&mut self, ioc: &Ioc, mut register_checks: F) -> Result<(), IocGraphError>
+        where F: FnMut(&IocEntry, IocId, IocEntryId) -> u32 {
+        if self.roots.contains_key(&ioc.id) {
+            return Err(IocGraphError {
+                kind: "Duplicate IOC".to_string(),
+                message: format!("IOC {} is defined more than once", ioc.id),
+            });
+        }
+        if let Some(score_threshold) = ioc.score_threshold.filter(|it| *it > 100) {
+            return Err(IocGraphError {
+                kind: "Invalid IOC".to_string(),
+                message: format!("Score threshold {} of IOC {} is over 100", score_threshold, ioc.id),
+            });
+        }
+        if !ioc.definition.temporal_constraints.is_empty() {
+            return Err(IocGraphError {
+                kind: "Invalid temporal constraint".to_string(),
+                message: format!("Root entry of IOC {} has no siblings to be timed against", ioc.id),
+            });
+        }
+        let entry_count = validate_entry(&ioc.definition, ioc.id)?;
+        if self.next_entry_id.checked_add(entry_count).is_none() {
+            return Err(IocGraphError {
+                kind: "Too many entries".to_string(),
+                message: format!("No entry ids left for IOC {}", ioc.id),
+            });
+        }
+        let root_entry_id = self.add_entry(&ioc.definition, ioc.id, &mut register_checks);
+        self.roots.insert(ioc.id, IocItem {
+            root_entry_id,
+            score_threshold: ioc.score_threshold.unwrap_or(100),
+        });
+        Ok(())
+    }
+
+    fn add_entry<F>(&mut self, ioc_entry: &IocEntry, ioc_id: IocId, register_checks: &mut F) -> IocEntryId
+        where F: FnMut(&IocEntry, IocId, IocEntryId) -> u32 {
+        let ioc_entry_id = self.next_entry_id;
+        self.next_entry_id += 1;
+        let checks_specified = register_checks(ioc_entry, ioc_id, ioc_entry_id);
+        let children: Option<Vec<IocEntryId>> = ioc_entry.offspring.as_ref().map(|offspring| offspring.iter()
+            .map(|child| self.add_entry(child, ioc_id, register_checks))
+            .collect());
+        if let (Some(offspring), Some(children)) = (&ioc_entry.offspring, &children) {
+            self.resolve_siblings(offspring, children);
+        }
+        self.entries.insert(ioc_entry_id, IocEntryItem {
+            ioc_entry_id,
+            ioc_id,
+            name: ioc_entry.name.clone(),
+            eval_policy: ioc_entry.eval_policy.clone(),
+            child_eval: ioc_entry.child_eval_policy.clone(),
+            children,
+            checks_specified,
+            weight: ioc_entry.weight.unwrap_or(1),
+            temporal_conditions: vec![],
+        });
+        ioc_entry_id
+    }
+
+    /// Turns the sibling names of the temporal constraints of the children into their entry ids.
+    fn resolve_siblings(&mut self, offspring: &[IocEntry], children: &[IocEntryId]) {
+        for (child, child_id) in offspring.iter().zip(children) {
+            let temporal_conditions = child.temporal_constraints.iter()
+                .filter_map(|constraint| offspring.iter()
+                    .position(|sibling| sibling.name.as_ref() == Some(&constraint.sibling))
+                    .map(|position| TemporalCondition {
+                        relation: constraint.relation,
+                        sibling: children[position],
+                        seconds: constraint.seconds,
+                    }))
+                .collect();
+            if let Some(item) = self.entries.get_mut(child_id) {
+                item.temporal_conditions = temporal_conditions;
+            }
+        }
+    }
+
+    pub fn build(self) -> Result<IocGraph, IocGraphError> {
+        IocGraph::new(self.roots, self.entries)
+    }
+}
+
+/// Number of entries in the subtree if all of them are valid.
+fn validate_entry(ioc_entry: &IocEntry, ioc_id: IocId) -> Result<u64, IocGraphError> {
+    let offspring = ioc_entry.offspring.as_deref().unwrap_or_default();
+    let operands = defined_checks(ioc_entry) + if offspring.is_empty() { 0 } else { 1 };
+    // The policy for children only applies when there are any
+    let policies = [(&ioc_entry.eval_policy, Some(operands)),
+        (&ioc_entry.child_eval_policy, Some(offspring.len() as u32).filter(|it| *it > 0))];
+    for (policy, operands) in policies.iter() {
+        let invalid = |message: String| Err(IocGraphError {
+            kind: "Invalid evaluation policy".to_string(),
+            message,
+        });
+        match policy {
+            EvaluationPolicy::Percentage(percentage) if *percentage > 100 =>
+                return invalid(format!("Percentage {} in IOC {} is over 100", percentage, ioc_id)),
+            EvaluationPolicy::AtLeast(0) =>
+                return invalid(format!("AT_LEAST 0 in IOC {} is always satisfied", ioc_id)),
+            EvaluationPolicy::AtLeast(count) if operands.map(|it| *count > it).unwrap_or(false) =>
+                return invalid(format!("AT_LEAST {} in IOC {} exceeds the {} operands", count, ioc_id, operands.unwrap_or_default())),
+            _ => {}
+        }
+    }
+    let mut entry_count = 1u64;
+    for (position, child) in offspring.iter().enumerate() {
+        for constraint in &child.temporal_constraints {
+            validate_temporal_constraint(constraint, position, offspring, ioc_id)?;
+        }
+        entry_count += validate_entry(child, ioc_id)?;
+    }
+    Ok(entry_count)
+}
+
+/// Number of checks the entry defines, whether or not they are enabled.
+fn defined_checks(ioc_entry: &IocEntry) -> u32 {
+    [
+        ioc_entry.registry_check.is_some(),
+        ioc_entry.file_check.is_some(),
+        ioc_entry.mutex_check.is_some(),
+        ioc_entry.object_check.is_some(),
+        ioc_entry.process_check.is_some(),
+        ioc_entry.dns_check.is_some(),
+        ioc_entry.conns_check.is_some(),
+        ioc_entry.certs_check.is_some(),
+        ioc_entry.scheduled_task_check.is_some(),
+        ioc_entry.shortcut_check.is_some(),
+        ioc_entry.service_check.is_some(),
+        ioc_entry.ssh_key_check.is_some(),
+        ioc_entry.account_check.is_some(),
+        ioc_entry.kernel_module_check.is_some(),
+        ioc_entry.preload_check.is_some(),
+        ioc_entry.environment_check.is_some(),
+    ].iter().filter(|it| **it).count() as u32
+}
+
+/// The sibling of the child at `position` has to be another child with a unique name.
+fn validate_temporal_constraint(constraint: &TemporalConstraint, position: usize, offspring: &[IocEntry],
+                                ioc_id: IocId) -> Result<(), IocGraphError> {
+    let invalid = |message: String| Err(IocGraphError {
+        kind: "Invalid temporal constraint".to_string(),
+        message,
+    });
+    if constraint.relation == TemporalRelation::Within && constraint.seconds.is_none() {
+        return invalid(format!("Constraint WITHIN {} in IOC {} has no seconds", constraint.sibling, ioc_id));
+    }
+    let named: Vec<usize> = offspring.iter().enumerate()
+        .filter(|(_, sibling)| sibling.name.as_ref() == Some(&constraint.sibling))
+        .map(|(sibling_position, _)| sibling_position)
+        .collect();
+    match named.as_slice() {
+        [] => invalid(format!("No sibling entry named {} in IOC {}", constraint.sibling, ioc_id)),
+        [sibling_position] if *sibling_position == position =>
+            invalid(format!("Entry {} in IOC {} is timed against itself", constraint.sibling, ioc_id)),
+        [_] => Ok(()),
+        _ => invalid(format!("Several sibling entries are named {} in IOC {}", constraint.sibling, ioc_id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{EvaluationPolicy, Ioc, IocEntry, IocEntryId, IocId, TemporalRelation};
+    use crate::ioc_evaluator::{IocEntryItem, IocItem, TemporalCondition};
+    use crate::ioc_graph::{IocGraph, IocGraphBuilder};
+    use std::collections::HashMap;
+
+    fn ioc(json: &str) -> Ioc {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_build() {
+        let mut builder = IocGraphBuilder::new();
+        let mut registered: Vec<(IocId, IocEntryId)> = Vec::new();
+        let mut register = |_: &IocEntry, ioc_id: IocId, ioc_entry_id: IocEntryId| {
+            registered.push((ioc_id, ioc_entry_id));
+            1
+        };
+        let first = ioc(r#"{"id": 7, "definition": {"offspring": [{"offspring": [{}]}, {}]}}"#);
+        let second = ioc(r#"{"id": 3, "scoreThreshold": 80, "definition": {"offspring": [{}]}}"#);
+        builder.add_ioc(&first, &mut register).unwrap();
+        builder.add_ioc(&second, &mut register).unwrap();
+        let error = builder.add_ioc(&ioc(r#"{"id": 7, "definition": {}}"#), &mut register).unwrap_err();
+        assert_eq!(error.kind, "Duplicate IOC");
+        assert_eq!(registered, vec![(7, 1), (7, 2), (7, 3), (7, 4), (3, 5), (3, 6)]);
+
+        let graph = builder.build().unwrap();
+        assert_eq!(graph.root(7).unwrap().root_entry_id, 1);
+        assert_eq!(graph.root_entry(7).unwrap().children, Some(vec![2, 4]));
+        assert_eq!(graph.entry(2).unwrap().children, Some(vec![3]));
+        assert_eq!(graph.root(3).unwrap().score_threshold, 80);
+        assert_eq!(graph.root_entry(3).unwrap().children, Some(vec![6]));
+        assert!(graph.root(1).is_none());
+    }
+
+    #[test]
+    fn test_invalid_ioc_adds_nothing() {
+        let mut builder = IocGraphBuilder::new();
+        let mut registrations = 0;
+        let invalid = [
+            r#"{"id": 1, "definition": {"offspring": [{"childEvalPolicy": {"PERCENTAGE": 120}}]}}"#,
+            r#"{"id": 2, "scoreThreshold": 101, "definition": {}}"#,
+        ];
+        for json in invalid.iter() {
+            let error = builder.add_ioc(&ioc(json), |_, _, _| {
+                registrations += 1;
+                1
+            });
+            assert!(error.is_err());
+        }
+        assert_eq!(registrations, 0);
+        let graph = builder.build().unwrap();
+        assert_eq!(graph.ioc_ids().count(), 0);
+    }
+
+    #[test]
+    fn test_validate_at_least() {
+        let mut builder = IocGraphBuilder::new();
+        let valid = ioc(r#"{"id": 1, "definition": {"evalPolicy": {"AT_LEAST": 2},
+            "fileCheck": {"name": "evil.exe"}, "offspring": [{}]}}"#);
+        builder.add_ioc(&valid, |_, _, _| 1).unwrap();
+        let invalid = [
+            r#"{"id": 2, "definition": {"evalPolicy": {"AT_LEAST": 0}, "offspring": [{}]}}"#,
+            r#"{"id": 3, "definition": {"childEvalPolicy": {"AT_LEAST": 0}, "offspring": [{}]}}"#,
+            r#"{"id": 4, "definition": {"evalPolicy": {"AT_LEAST": 2}, "offspring": [{}]}}"#,
+            r#"{"id": 5, "definition": {"childEvalPolicy": {"AT_LEAST": 3}, "offspring": [{}, {}]}}"#,
+        ];
+        for json in invalid.iter() {
+            let error = builder.add_ioc(&ioc(json), |_, _, _| 1).unwrap_err();
+            assert_eq!(error.kind, "Invalid evaluation policy", "{}", json);
+        }
+        let graph = builder.build().unwrap();
+        assert_eq!(graph.ioc_ids().collect::<Vec<&IocId>>(), vec![&1]);
+    }
+
+    #[test]
+    fn test_resolve_temporal_constraints() {
+        let mut builder = IocGraphBuilder::new();
+        let timed = ioc(r#"{"id": 1, "definition": {"offspring": [
+            {"name": "Dropper"},
+            {"name": "Beacon", "temporalConstraints": [{"relation": "AFTER", "sibling": "Dropper", "seconds": 600}]}
+        ]}}"#);
+        builder.add_ioc(&timed, |_, _, _| 1).unwrap();
+        let graph = builder.build().unwrap();
+        assert!(graph.entry(2).unwrap().temporal_conditions.is_empty());
+        assert_eq!(graph.entry(3).unwrap().temporal_conditions, vec![
+            TemporalCondition { relation: TemporalRelation::After, sibling: 2, seconds: Some(600) },
+        ]);
+
+        let invalid = [
+            r#"{"id": 2, "definition": {"temporalConstraints": [{"relation": "AFTER", "sibling": "Dropper"}]}}"#,
+            r#"{"id": 3, "definition": {"offspring": [
+                {"name": "Dropper", "temporalConstraints": [{"relation": "AFTER", "sibling": "Beacon"}]}
+            ]}}"#,
+            r#"{"id": 4, "definition": {"offspring": [
+                {"name": "Dropper", "temporalConstraints": [{"relation": "AFTER", "sibling": "Dropper"}]}
+            ]}}"#,
+            r#"{"id": 5, "definition": {"offspring": [
+                {"name": "Dropper"}, {"name": "Dropper"},
+                {"temporalConstraints": [{"relation": "BEFORE", "sibling": "Dropper"}]}
+            ]}}"#,
+            r#"{"id": 6, "definition": {"offspring": [
+                {"name": "Dropper"}, {"temporalConstraints": [{"relation": "WITHIN", "sibling": "Dropper"}]}
+            ]}}"#,
+        ];
+        let mut builder = IocGraphBuilder::new();
+        for json in invalid.iter() {
+            let error = builder.add_ioc(&ioc(json), |_, _, _| 1).unwrap_err();
+            assert_eq!(error.kind, "Invalid temporal constraint", "{}", json);
+        }
+    }
+
+    fn entry(ioc_entry_id: IocEntryId, ioc_id: IocId, children: Option<Vec<IocEntryId>>) -> IocEntryItem {
+        IocEntryItem {
+            ioc_entry_id,
+            ioc_id,
+            name: None,
+            eval_policy: EvaluationPolicy::All,
+            child_eval: EvaluationPolicy::All,
+            children,
+            checks_specified: 1,
+            weight: 1,
+            temporal_conditions: vec![],
+        }
+    }
+
+    fn graph_error(roots: &[(IocId, IocEntryId)], entries: Vec<IocEntryItem>) -> Option<String> {
+        let roots = roots.iter()
+            .map(|(ioc_id, root_entry_id)| (*ioc_id, IocItem { root_entry_id: *root_entry_id, score_threshold: 100 }))
+            .collect::<HashMap<IocId, IocItem>>();
+        let entries = entries.into_iter().map(|it| (it.ioc_entry_id, it)).collect();
+        IocGraph::new(roots, entries).err().map(|it| it.kind)
+    }
+
+    #[test]
+    fn test_validate_graph() {
+        assert_eq!(graph_error(&[(1, 1)], vec![entry(1, 1, Some(vec![2])), entry(2, 1, None)]), None);
+        assert_eq!(graph_error(&[(1, 3)], vec![entry(1, 1, None)]).unwrap(), "Missing entry");
+        assert_eq!(graph_error(&[(1, 1)], vec![entry(1, 1, Some(vec![2]))]).unwrap(), "Missing entry");
+        assert_eq!(graph_error(&[(1, 1), (2, 2)], vec![entry(1, 1, Some(vec![2])), entry(2, 2, None)]).unwrap(), "Foreign entry");
+        assert_eq!(graph_error(&[(1, 1)], vec![entry(1, 1, Some(vec![2, 2])), entry(2, 1, None)]).unwrap(), "Shared entry");
+        assert_eq!(graph_error(&[(1, 1)], vec![entry(1, 1, Some(vec![1]))]).unwrap(), "Shared entry");
+        assert_eq!(graph_error(&[(1, 1)], vec![entry(1, 1, None), entry(2, 1, None)]).unwrap(), "Unreachable entry");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::data::{GetIocResponse, ReportUploadRequest, Ioc};
+use crate::data::{GetIocResponse, ReportUploadRequest, DetailedReportUploadRequest, Ioc};
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt;
@@ -41,6 +41,7 @@ impl Display for IocServiceError {
 pub trait IocService {
     fn receive_ioc(&self) -> Result<GetIocResponse, IocServiceError>;
     fn report_results(&self, request: ReportUploadRequest) -> Result<(), IocServiceError>;
+    fn report_detailed_results(&self, request: DetailedReportUploadRequest) -> Result<(), IocServiceError>;
 }
 
 pub struct FileIocService<T: AsRef<str> + AsRef<Path> + std::fmt::Display> {
@@ -88,6 +89,13 @@ IocService for FileIocService<T> {
         report_file.write_all(json.as_ref())?;
         Ok(())
     }
+
+    fn report_detailed_results(&self, request: DetailedReportUploadRequest) -> Result<(), IocServiceError> {
+        let json = serde_json::to_string_pretty(&request)?;
+        let mut report_file = std::fs::File::create(format!("DetailedReport-{}.json", Local::now().format("_%Y-%m-%d_%H-%M-%S")))?;
+        report_file.write_all(json.as_ref())?;
+        Ok(())
+    }
 }
 
 /////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -179,4 +187,20 @@ impl IocService for HttpIocService {
             })?;
         Ok(())
     }
+
+    fn report_detailed_results(&self, request: DetailedReportUploadRequest) -> Result<(), IocServiceError> {
+        let client = http_client()?;
+        let url = format!("{}/api/probe/auth/post/ioc/result/detailed", self.url.as_str()).to_string();
+
+        client.post(&url)
+            .header(header::AUTHORIZATION, format!("Basic {}", self.authorization_header_value.as_str()))
+            .header(header::WWW_AUTHENTICATE, format!("Basic REALM=\"{}\", charset=UTF-8", REALM))
+            .json(&request)
+            .send()
+            .map_err(|err| IocServiceError {
+                kind: "HTTP Error".to_string(),
+                message: format!("{}", err),
+            })?;
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -10,22 +10,32 @@ extern crate winreg;
 
 use simplelog::*;
 use std::fs::File;
-use crate::data::{IocEntry, ReportUploadRequest, IocEntryId, GetIocResponse, Ioc, IocId, PrettyReport, PrettyReportList};
+use crate::data::{IocEntry, ReportUploadRequest, DetailedReportUploadRequest, IocEntryId, GetIocResponse, Ioc, IocId, PrettyReport, PrettyReportList, ObjectType, Severity};
 use crate::file_checker::FileParameters;
 use crate::arg_parser::{parsed_args, ParsedArgs};
 use crate::properties::Properties;
 use crate::ioc_service::{FileIocService, IocService, HttpIocService};
-use crate::ioc_evaluator::{IocEvaluator, IocEntryItem, IocEntrySearchResult};
-use std::collections::HashMap;
+use crate::ioc_evaluator::{CheckKind, IocEvaluator, IocEntrySearchResult, IocScore, render_trace};
+use crate::ioc_graph::IocGraphBuilder;
+use crate::conversion::findings_of;
 use crate::dns_checker::DnsParameters;
-use crate::mutant_checker::MutexParameters;
+use crate::mutant_checker::ObjectParameters;
 use crate::registry_checker::RegistryParameters;
 use crate::conns_checker::ConnectionParameters;
 use crate::process_checker::ProcessParameters;
 use crate::cert_checker::CertificateParameters;
+use crate::persistence_checker::{ScheduledTaskParameters, ServiceParameters, ShortcutParameters};
+use crate::ssh_checker::SshKeyParameters;
+use crate::account_checker::AccountParameters;
+use crate::rootkit_checker::{KernelModuleParameters, PreloadParameters};
+use crate::environment_checker::EnvironmentParameters;
+use crate::reverse_dns::{ReverseDns, SystemResolver};
 use crate::logo::print_logo;
 use chrono::Local;
 use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 #[cfg(windows)]
@@ -36,6 +46,7 @@ mod priv_esca;
 mod data;
 mod hasher;
 mod mutant_checker;
+mod named_objects;
 mod file_checker;
 mod properties;
 mod utils;
@@ -43,13 +54,30 @@ mod conversion;
 mod arg_parser;
 mod ioc_service;
 mod ioc_evaluator;
+mod ioc_graph;
 mod dns_checker;
+mod dns_sources;
 mod registry_checker;
 mod process_checker;
 mod conns_checker;
 mod cert_checker;
+mod certificate;
+mod cert_sources;
+mod der;
+mod authenticode;
 mod dir_resolver;
 mod logo;
+mod search_pattern;
+mod artifact_parser;
+mod persistence_checker;
+mod unix_accounts;
+mod ssh_checker;
+mod account_checker;
+mod proc_fs;
+mod rootkit_checker;
+mod environment_checker;
+mod reverse_dns;
+mod pcap_parser;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = parsed_args();
@@ -74,166 +102,254 @@ fn setup_logger(args: &ParsedArgs) {
     CombinedLogger::init(loggers).unwrap();
 }
 
-fn walk_iocs(
-    args: &ParsedArgs,
-    root_ioc_entries: &mut HashMap<IocId, IocEntryId>,
-    iocs: &Vec<Ioc>,
-    ioc_entries: &mut HashMap<IocEntryId, IocEntryItem>,
-    file_parameters: &mut Vec<FileParameters>,
-    dns_parameters: &mut Vec<DnsParameters>,
-    mutex_parameters: &mut Vec<MutexParameters>,
-    registry_parameters: &mut Vec<RegistryParameters>,
-    conns_parameters: &mut Vec<ConnectionParameters>,
-    process_parameters: &mut Vec<ProcessParameters>,
-    cert_parameters: &mut Vec<CertificateParameters>,
-) {
-    let mut id_gen: u64 = 1;
-    for ioc in iocs {
-        id_gen = id_gen + 1;
-        root_ioc_entries.insert(ioc.id.clone(), id_gen.clone());
-        walk_ioc_entries(
-            args,
-            &ioc.definition,
-            ioc.id,
-            root_ioc_entries,
-            ioc_entries,
-            file_parameters,
-            dns_parameters,
-            mutex_parameters,
-            registry_parameters,
-            conns_parameters,
-            process_parameters,
-            cert_parameters,
-            &mut id_gen,
-        )
-    }
+/// Search parameters of all checkers, collected from the IOC entries.
+#[derive(Default)]
+struct CheckerParameters {
+    file_parameters: Vec<FileParameters>,
+    dns_parameters: Vec<DnsParameters>,
+    object_parameters: Vec<ObjectParameters>,
+    registry_parameters: Vec<RegistryParameters>,
+    conns_parameters: Vec<ConnectionParameters>,
+    process_parameters: Vec<ProcessParameters>,
+    cert_parameters: Vec<CertificateParameters>,
+    task_parameters: Vec<ScheduledTaskParameters>,
+    shortcut_parameters: Vec<ShortcutParameters>,
+    service_parameters: Vec<ServiceParameters>,
+    ssh_key_parameters: Vec<SshKeyParameters>,
+    account_parameters: Vec<AccountParameters>,
+    kernel_module_parameters: Vec<KernelModuleParameters>,
+    preload_parameters: Vec<PreloadParameters>,
+    environment_parameters: Vec<EnvironmentParameters>,
 }
 
-// Basicaly BFS
-fn walk_ioc_entries(
-    args: &ParsedArgs,
-    ioc_entry: &IocEntry,
-    ioc_root_id: IocId,
-    root_ioc_entries: &mut HashMap<IocEntryId, IocId>,
-    ioc_entries: &mut HashMap<IocEntryId, IocEntryItem>,
-    file_parameters: &mut Vec<FileParameters>,
-    dns_parameters: &mut Vec<DnsParameters>,
-    mutex_parameters: &mut Vec<MutexParameters>,
-    registry_parameters: &mut Vec<RegistryParameters>,
-    conns_parameters: &mut Vec<ConnectionParameters>,
-    process_parameters: &mut Vec<ProcessParameters>,
-    cert_parameters: &mut Vec<CertificateParameters>,
-    id_gen: &mut IocEntryId,
-) {
-    let offspring = ioc_entry.offspring.as_ref();
-    let mut checks_specified = 0u32;
-
-    if ioc_entry.certs_check.is_some() && args.cert_check {
-        checks_specified += 1;
-        let cert_info = ioc_entry.certs_check.clone().unwrap();
-        cert_parameters.push(CertificateParameters {
-            ioc_id: ioc_root_id,
-            ioc_entry_id: *id_gen,
-            name: cert_info.name,
-        })
-    }
-    if ioc_entry.file_check.is_some() && args.file_check {
-        checks_specified += 1;
-        let file_info = ioc_entry.file_check.clone().unwrap();
-        file_parameters.push(FileParameters {
-            ioc_id: ioc_root_id,
-            ioc_entry_id: *id_gen,
-            search_type: file_info.search,
-            file_path_or_name: file_info.name,
-            hash: file_info.hash,
-        });
-    }
-    if ioc_entry.registry_check.is_some() && args.registry_check {
-        checks_specified += 1;
-        let registry_info = ioc_entry.registry_check.clone().unwrap();
-        registry_parameters.push(RegistryParameters {
-            ioc_id: ioc_root_id,
-            ioc_entry_id: *id_gen,
-            search_type: registry_info.search,
-            key: registry_info.key,
-            value_name: registry_info.value_name,
-            value: registry_info.value,
-        })
-    }
-    if ioc_entry.dns_check.is_some() && args.dns_check {
-        checks_specified += 1;
-        let dns_info = ioc_entry.dns_check.clone().unwrap();
-        dns_parameters.push(DnsParameters {
-            ioc_id: ioc_root_id,
-            ioc_entry_id: *id_gen,
-            name: dns_info.name,
-        })
-    }
-    if ioc_entry.process_check.is_some() && args.process_check {
-        checks_specified += 1;
-        let proc_info = ioc_entry.process_check.clone().unwrap();
-        process_parameters.push(ProcessParameters {
-            ioc_id: ioc_root_id,
-            ioc_entry_id: *id_gen,
-            search: proc_info.search,
-            name: proc_info.name,
-            hash: proc_info.hash,
-        })
-    }
-    if ioc_entry.mutex_check.is_some() && args.mutex_check {
-        checks_specified += 1;
-        let mutex_info = ioc_entry.mutex_check.clone().unwrap();
-        mutex_parameters.push(MutexParameters {
-            ioc_entry_id: *id_gen,
-            ioc_id: ioc_root_id,
-            data: mutex_info.name,
-        })
-    }
+impl CheckerParameters {
+    /// Hands the enabled checks of the entry to the checkers, returns how many of them will run.
+    fn register_checks(
+        &mut self,
+        args: &ParsedArgs,
+        ioc_entry: &IocEntry,
+        ioc_root_id: IocId,
+        ioc_entry_id: IocEntryId,
+    ) -> u32 {
+        let mut checks_specified = 0u32;
 
-    if ioc_entry.conns_check.is_some() && args.conn_check {
-        checks_specified += 1;
-        let conns_info = ioc_entry.conns_check.clone().unwrap();
-        conns_parameters.push(ConnectionParameters {
-            ioc_id: ioc_root_id,
-            ioc_entry_id: *id_gen,
-            search: conns_info.search,
-            name: conns_info.name,
-        })
-    }
+        if ioc_entry.certs_check.is_some() && args.cert_check {
+            checks_specified += 1;
+            let cert_info = ioc_entry.certs_check.clone().unwrap();
+            self.cert_parameters.push(CertificateParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: cert_info.search,
+                name: cert_info.name,
+                subject: cert_info.subject,
+                issuer: cert_info.issuer,
+                serial: cert_info.serial,
+                thumbprint: cert_info.thumbprint,
+                not_before: cert_info.not_before,
+                not_after: cert_info.not_after,
+                valid_at: cert_info.valid_at,
+                issued_after: cert_info.issued_after,
+                expires_before: cert_info.expires_before,
+                key_usage: cert_info.key_usage,
+            })
+        }
+        if ioc_entry.file_check.is_some() && args.file_check {
+            checks_specified += 1;
+            let file_info = ioc_entry.file_check.clone().unwrap();
+            self.file_parameters.push(FileParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search_type: file_info.search,
+                file_path_or_name: file_info.name,
+                hash: file_info.hash,
+                signature: file_info.signature,
+            });
+        }
+        if ioc_entry.registry_check.is_some() && args.registry_check {
+            checks_specified += 1;
+            let registry_info = ioc_entry.registry_check.clone().unwrap();
+            self.registry_parameters.push(RegistryParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search_type: registry_info.search,
+                key: registry_info.key,
+                value_name: registry_info.value_name,
+                value: registry_info.value,
+            })
+        }
+        if ioc_entry.dns_check.is_some() && args.dns_check {
+            checks_specified += 1;
+            let dns_info = ioc_entry.dns_check.clone().unwrap();
+            self.dns_parameters.push(DnsParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: dns_info.search,
+                name: dns_info.name,
+                include_subdomains: dns_info.include_subdomains,
+                address: dns_info.address,
+            })
+        }
+        if ioc_entry.process_check.is_some() && args.process_check {
+            checks_specified += 1;
+            let proc_info = ioc_entry.process_check.clone().unwrap();
+            self.process_parameters.push(ProcessParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: proc_info.search,
+                name: proc_info.name,
+                hash: proc_info.hash,
+            })
+        }
+        if ioc_entry.mutex_check.is_some() && args.mutex_check {
+            checks_specified += 1;
+            let mutex_info = ioc_entry.mutex_check.clone().unwrap();
+            self.object_parameters.push(ObjectParameters {
+                ioc_entry_id,
+                ioc_id: ioc_root_id,
+                search: mutex_info.search,
+                object_type: ObjectType::Mutant,
+                name: mutex_info.name,
+                check: CheckKind::Mutex,
+            })
+        }
+        if ioc_entry.object_check.is_some() && args.mutex_check {
+            checks_specified += 1;
+            let object_info = ioc_entry.object_check.clone().unwrap();
+            self.object_parameters.push(ObjectParameters {
+                ioc_entry_id,
+                ioc_id: ioc_root_id,
+                search: object_info.search,
+                object_type: object_info.object_type,
+                name: object_info.name,
+                check: CheckKind::Object,
+            })
+        }
 
-    let entry_item = IocEntryItem {
-        ioc_entry_id: *id_gen,
-        ioc_id: ioc_root_id,
-        eval_policy: ioc_entry.eval_policy.clone(),
-        child_eval: ioc_entry.child_eval_policy.clone(),
-        children: match offspring {
-            None => None,
-            Some(children) => Some(children.iter().map(|child| {
-                *id_gen = *id_gen + 1;
-                let this_child_id = id_gen.clone();
-                walk_ioc_entries( // Rekurzia fuj.
-                                  args,
-                                  child,
-                                  ioc_root_id,
-                                  root_ioc_entries,
-                                  ioc_entries,
-                                  file_parameters,
-                                  dns_parameters,
-                                  mutex_parameters,
-                                  registry_parameters,
-                                  conns_parameters,
-                                  process_parameters,
-                                  cert_parameters,
-                                  id_gen,
-                );
-                this_child_id
-            }).collect()),
-        },
-        checks_specified,
-    };
-    ioc_entries.insert(entry_item.ioc_entry_id, entry_item);
-}
+        if ioc_entry.conns_check.is_some() && args.conn_check {
+            checks_specified += 1;
+            let conns_info = ioc_entry.conns_check.clone().unwrap();
+            self.conns_parameters.push(ConnectionParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: conns_info.search,
+                name: conns_info.name,
+                address: conns_info.address,
+                protocol: conns_info.protocol,
+                local_port: conns_info.local_port,
+                remote_port: conns_info.remote_port,
+                state: conns_info.state,
+                direction: conns_info.direction,
+                process: conns_info.process,
+            })
+        }
+
+        if ioc_entry.scheduled_task_check.is_some() && args.task_check {
+            checks_specified += 1;
+            let task_info = ioc_entry.scheduled_task_check.clone().unwrap();
+            self.task_parameters.push(ScheduledTaskParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: task_info.search,
+                name: task_info.name,
+                command: task_info.command,
+                arguments: task_info.arguments,
+            })
+        }
+
+        if ioc_entry.shortcut_check.is_some() && args.shortcut_check {
+            checks_specified += 1;
+            let shortcut_info = ioc_entry.shortcut_check.clone().unwrap();
+            self.shortcut_parameters.push(ShortcutParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: shortcut_info.search,
+                name: shortcut_info.name,
+                target: shortcut_info.target,
+                arguments: shortcut_info.arguments,
+            })
+        }
+
+        if ioc_entry.service_check.is_some() && args.service_check {
+            checks_specified += 1;
+            let service_info = ioc_entry.service_check.clone().unwrap();
+            self.service_parameters.push(ServiceParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: service_info.search,
+                name: service_info.name,
+                image_path: service_info.image_path,
+                service_dll: service_info.service_dll,
+            })
+        }
+
+        if ioc_entry.ssh_key_check.is_some() && args.ssh_key_check {
+            checks_specified += 1;
+            let ssh_key_info = ioc_entry.ssh_key_check.clone().unwrap();
+            self.ssh_key_parameters.push(SshKeyParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: ssh_key_info.search,
+                fingerprint: ssh_key_info.fingerprint,
+                comment: ssh_key_info.comment,
+                command: ssh_key_info.command,
+            })
+        }
+
+        if ioc_entry.account_check.is_some() && args.account_check {
+            checks_specified += 1;
+            let account_info = ioc_entry.account_check.clone().unwrap();
+            self.account_parameters.push(AccountParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: account_info.search,
+                name: account_info.name,
+                uid: account_info.uid,
+                gid: account_info.gid,
+                shell: account_info.shell,
+                group: account_info.group,
+                sudo_rule: account_info.sudo_rule,
+                predicate: account_info.predicate,
+            })
+        }
+
+        if ioc_entry.kernel_module_check.is_some() && args.kernel_module_check {
+            checks_specified += 1;
+            let kernel_module_info = ioc_entry.kernel_module_check.clone().unwrap();
+            self.kernel_module_parameters.push(KernelModuleParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: kernel_module_info.search,
+                name: kernel_module_info.name,
+                hidden: kernel_module_info.hidden,
+            })
+        }
+
+        if ioc_entry.preload_check.is_some() && args.preload_check {
+            checks_specified += 1;
+            let preload_info = ioc_entry.preload_check.clone().unwrap();
+            self.preload_parameters.push(PreloadParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: preload_info.search,
+                library: preload_info.library,
+            })
+        }
 
+        if ioc_entry.environment_check.is_some() && args.environment_check {
+            checks_specified += 1;
+            let environment_info = ioc_entry.environment_check.clone().unwrap();
+            self.environment_parameters.push(EnvironmentParameters {
+                ioc_id: ioc_root_id,
+                ioc_entry_id,
+                search: environment_info.search,
+                name: environment_info.name,
+                value: environment_info.value,
+            })
+        }
+
+        checks_specified
+    }
+}
 
 fn run_checker(program_properties: &Properties, args: &ParsedArgs) {
     let file_ioc_service = FileIocService::new(args.ioc_definitions.to_vec());
@@ -277,125 +393,190 @@ fn run_checker(program_properties: &Properties, args: &ParsedArgs) {
 
     // Join them
     ////////////////////////////////////////////////////////////////////////////
-    let iocs: Vec<Ioc> = ioc_from_file.into_iter().chain(ioc_from_server.into_iter()).collect();
+    let mut iocs: Vec<Ioc> = ioc_from_file.into_iter().chain(ioc_from_server).collect();
     info!("Total loaded IOC definitions: {}", iocs.len());
+    if let Some(explained_ioc) = args.explain {
+        iocs.retain(|it| it.id == explained_ioc);
+        if iocs.is_empty() {
+            error!("IOC {} to explain is not among the loaded IOC definitions", explained_ioc);
+            return;
+        }
+    }
 
     // Create checker's params
     ////////////////////////////////////////////////////////////////////////////
-    let mut root_ioc_entries: HashMap<IocEntryId, IocId> = HashMap::new();
-    let mut ioc_entries: HashMap<IocEntryId, IocEntryItem> = HashMap::new();
-    let mut file_parameters: Vec<FileParameters> = Vec::new();
-    let mut dns_parameters: Vec<DnsParameters> = Vec::new();
-    let mut mutex_parameters: Vec<MutexParameters> = Vec::new();
-    let mut registry_parameters: Vec<RegistryParameters> = Vec::new();
-    let mut conns_parameters: Vec<ConnectionParameters> = Vec::new();
-    let mut proc_parameters: Vec<ProcessParameters> = Vec::new();
-    let mut cert_parameters: Vec<CertificateParameters> = Vec::new();
-    walk_iocs(
-        &args,
-        &mut root_ioc_entries,
-        &iocs,
-        &mut ioc_entries,
-        &mut file_parameters,
-        &mut dns_parameters,
-        &mut mutex_parameters,
-        &mut registry_parameters,
-        &mut conns_parameters,
-        &mut proc_parameters,
-        &mut cert_parameters,
-    );
+    let mut checker_parameters = CheckerParameters::default();
+    let mut ioc_graph_builder = IocGraphBuilder::new();
+    for ioc in &iocs {
+        let added = ioc_graph_builder.add_ioc(ioc, |ioc_entry, ioc_id, ioc_entry_id| {
+            checker_parameters.register_checks(args, ioc_entry, ioc_id, ioc_entry_id)
+        });
+        if let Err(err) = added {
+            error!("Skipping IOC {}: {}", ioc.id, err);
+        }
+    }
+    let ioc_graph = match ioc_graph_builder.build() {
+        Ok(ioc_graph) => ioc_graph,
+        Err(err) => {
+            error!("Cannot compile IOC definitions: {}", err);
+            return;
+        }
+    };
+    let CheckerParameters {
+        file_parameters,
+        dns_parameters,
+        object_parameters,
+        registry_parameters,
+        conns_parameters,
+        process_parameters: proc_parameters,
+        cert_parameters,
+        task_parameters,
+        shortcut_parameters,
+        service_parameters,
+        ssh_key_parameters,
+        account_parameters,
+        kernel_module_parameters,
+        preload_parameters,
+        environment_parameters,
+    } = checker_parameters;
 
     let deep_search_enabled = program_properties.deep_search;
     // Run checkers
     ////////////////////////////////////////////////////////////////////////////
 
-    let dns_check_results = if args.dns_check { dns_checker::check_dns(dns_parameters) } else { vec![] };
-    let cert_check_results = if args.cert_check { cert_checker::check_certs(cert_parameters) } else { vec![] };
+    let capture = args.pcap_file.as_ref().and_then(|pcap_file| match pcap_parser::read_capture(Path::new(pcap_file)) {
+        Ok(capture) => {
+            info!("Loaded {} DNS records and {} flows from {}", capture.dns.len(), capture.flows.len(), pcap_file);
+            Some(capture)
+        }
+        Err(err) => {
+            error!("Cannot read capture {}: {}", pcap_file, err);
+            None
+        }
+    });
+    let dns_check_results = if args.dns_check { dns_checker::check_dns(dns_parameters, capture.as_ref()) } else { vec![] };
+    let cert_check_results = if args.cert_check { cert_checker::check_certs(cert_parameters, &program_properties.certificate_paths) } else { vec![] };
     let proc_check_results = if args.process_check { process_checker::check_processes(proc_parameters) } else { vec![] };
-    let mutex_check_results = if args.mutex_check { mutant_checker::check_mutexes(mutex_parameters) } else { vec![] };
+    let object_check_results = if args.mutex_check { mutant_checker::check_objects(object_parameters) } else { vec![] };
     let registry_check_results = if args.registry_check { registry_checker::check_registry(registry_parameters, deep_search_enabled) } else { vec![] };
-    let conns_check_results = if args.conn_check { conns_checker::check_conns(conns_parameters) } else { vec![] };
+    let mut reverse_dns = if program_properties.reverse_dns {
+        ReverseDns::new(Arc::new(SystemResolver), Duration::from_millis(program_properties.reverse_dns_timeout_ms))
+    } else {
+        ReverseDns::disabled()
+    };
+    let conns_check_results = if args.conn_check { conns_checker::check_conns(conns_parameters, &mut reverse_dns, capture.as_ref()) } else { vec![] };
     let file_check_results = if args.file_check { file_checker::check_files(file_parameters, deep_search_enabled) } else { vec![] };
+    let artifacts_dir = args.artifacts_dir.as_ref().map(Path::new);
+    let task_check_results = if args.task_check { persistence_checker::check_scheduled_tasks(task_parameters, artifacts_dir) } else { vec![] };
+    let shortcut_check_results = if args.shortcut_check { persistence_checker::check_shortcuts(shortcut_parameters, artifacts_dir) } else { vec![] };
+    let service_check_results = if args.service_check { persistence_checker::check_services(service_parameters, artifacts_dir) } else { vec![] };
+    let ssh_key_check_results = if args.ssh_key_check { ssh_checker::check_ssh_keys(ssh_key_parameters) } else { vec![] };
+    let account_check_results = if args.account_check { account_checker::check_accounts(account_parameters) } else { vec![] };
+    let kernel_module_check_results = if args.kernel_module_check { rootkit_checker::check_kernel_modules(kernel_module_parameters) } else { vec![] };
+    let preload_check_results = if args.preload_check { rootkit_checker::check_preload(preload_parameters) } else { vec![] };
+    let environment_check_results = if args.environment_check { environment_checker::check_environment(environment_parameters) } else { vec![] };
 
     // Combine results
     ////////////////////////////////////////////////////////////////////////////
     let all_results: Vec<IocEntrySearchResult> =
         file_check_results.into_iter()
             .chain(dns_check_results.into_iter())
-            .chain(mutex_check_results.into_iter())
+            .chain(object_check_results)
             .chain(registry_check_results.into_iter())
             .chain(conns_check_results)
             .chain(proc_check_results)
             .chain(cert_check_results)
+            .chain(task_check_results)
+            .chain(shortcut_check_results)
+            .chain(service_check_results)
+            .chain(ssh_key_check_results)
+            .chain(account_check_results)
+            .chain(kernel_module_check_results)
+            .chain(preload_check_results)
+            .chain(environment_check_results)
             .collect();
 
     // Create cached ioc defs and search results
     ////////////////////////////////////////////////////////////////////////////
 
-    let evaluator = IocEvaluator::new(
-        root_ioc_entries,
-        ioc_entries,
-        &all_results,
-    );
-    let evaluated_iocs: Vec<IocId> = evaluator.evaluate();
+    let evaluator = IocEvaluator::new(ioc_graph, &all_results);
+    if let Some(explained_ioc) = args.explain {
+        match (evaluator.score_ioc(explained_ioc), evaluator.explain(explained_ioc)) {
+            (Some(ioc_score), Some(trace)) => {
+                println!("IOC {}: score {}, {}", explained_ioc, ioc_score.score, if ioc_score.hit { "found" } else { "not found" });
+                print!("{}", render_trace(&trace));
+            }
+            _ => error!("Cannot evaluate IOC {}", explained_ioc),
+        }
+        return;
+    }
+    let ioc_scores: Vec<IocScore> = evaluator.evaluate(program_properties.report_floor);
 
-    let upload_request = ReportUploadRequest::new(evaluated_iocs.clone());
-    info!("Found {} IOCs out of {}",
+    let upload_request = ReportUploadRequest::new(&ioc_scores, &iocs);
+    info!("Found {} IOCs out of {}, {} near misses",
           upload_request.found_iocs.len(),
-          iocs.len()
+          iocs.len(),
+          upload_request.near_misses.len()
     );
+    let detailed_request = if program_properties.detailed_report {
+        Some(DetailedReportUploadRequest::new(&ioc_scores, &iocs, &evaluator, &all_results))
+    } else {
+        None
+    };
     if args.local_mode && !args.raw_console_mode {
-        pretty_report(&evaluated_iocs, &iocs, &all_results);
-        let report_response = file_ioc_service.report_results(upload_request.clone());
-        match report_response {
-            Ok(_) => { info!("Report saved") }
-            Err(error) => { error!("Cannot save report: {}", error) }
-        }
+        pretty_report(&ioc_scores, &evaluator, &iocs, &all_results);
+        report_results(&file_ioc_service, upload_request, detailed_request);
     } else if !args.raw_console_mode {
-        let report_response = http_ioc_service.report_results(upload_request);
-        match report_response {
-            Ok(_) => { info!("Report saved") }
-            Err(error) => { error!("Cannot save report: {}", error) }
-        }
+        report_results(&http_ioc_service, upload_request, detailed_request);
     }else {
         println!("{:?}", upload_request.found_iocs);
     }
 }
 
+/// Sends the detailed report when there is one, otherwise the scores.
+fn report_results<S: IocService>(
+    ioc_service: &S,
+    upload_request: ReportUploadRequest,
+    detailed_request: Option<DetailedReportUploadRequest>,
+) {
+    let report_response = match detailed_request {
+        Some(detailed_request) => ioc_service.report_detailed_results(detailed_request),
+        None => ioc_service.report_results(upload_request),
+    };
+    match report_response {
+        Ok(_) => { info!("Report saved") }
+        Err(error) => { error!("Cannot save report: {}", error) }
+    }
+}
+
 fn pretty_report(
-    evaluated_iocs: &[IocId],
+    ioc_scores: &[IocScore],
+    evaluator: &IocEvaluator,
     all_iocs: &[Ioc],
     results: &[IocEntrySearchResult],
 ) {
-    let pretty_reports: Vec<PrettyReport> = evaluated_iocs
-        .iter()
-        .filter_map(|ioc_id| {
-            let report: Vec<String> = results
-                .iter()
-                .filter(|iesr| iesr.ioc_id == ioc_id.clone())
-                .map(|it| {
-                    it.description.clone()
-                }).collect();
-            if report.is_empty() {
-                None
-            } else {
-                let ioc_mame = all_iocs
+    let (hits, near_misses): (Vec<&IocScore>, Vec<&IocScore>) = ioc_scores.iter().partition(|it| it.hit);
+    let pretty_reports = |ioc_scores: Vec<&IocScore>| -> Vec<PrettyReport> {
+        ioc_scores.into_iter()
+            .filter_map(|ioc_score| {
+                let findings = findings_of(results, evaluator, ioc_score.ioc_id);
+                let ioc = all_iocs
                     .iter()
-                    .find(|it| it.id == ioc_id.clone())
-                    .map(|it| it.name.clone())
-                    .unwrap_or(None)
-                    .unwrap_or("UNKNOWN".to_string());
-
-                Some(
-                    PrettyReport {
-                        ioc_id: ioc_id.clone(),
-                        name: ioc_mame,
-                        search_reports: report,
-                    }
-                )
-            }
-        }).collect();
-    let pretty_report_wrapper = PrettyReportList { found_iocs: pretty_reports };
+                    .find(|it| it.id == ioc_score.ioc_id);
+                Some(PrettyReport {
+                    ioc_id: ioc_score.ioc_id,
+                    name: ioc.and_then(|it| it.name.clone()).unwrap_or("UNKNOWN".to_string()),
+                    severity: ioc.map(|it| it.severity).unwrap_or(Severity::default()),
+                    score: ioc_score.score,
+                    findings,
+                    evaluation: evaluator.explain(ioc_score.ioc_id)?,
+                })
+            }).collect()
+    };
+    let pretty_report_wrapper = PrettyReportList {
+        found_iocs: pretty_reports(hits),
+        near_misses: pretty_reports(near_misses),
+    };
     let json = serde_json::to_string_pretty(&pretty_report_wrapper);
     match json {
         Ok(json) => {
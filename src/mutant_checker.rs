@@ -2,16 +2,28 @@
 extern crate winapi;
 
 //use crate::query_result;
-use crate::data::{IocEntryId, IocId};
+use crate::data::{IocEntryId, IocId, ObjectType, SearchType, Evidence};
+#[cfg(windows)]
 use std::ffi::CString;
+#[cfg(windows)]
 use std::ptr;
 #[cfg(windows)]
 use winapi::ctypes::c_void;
-use crate::ioc_evaluator::IocEntrySearchResult;
+use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult};
+#[cfg(not(windows))]
+use crate::named_objects::{handle_owners, linux_named_objects, NamedObjectKind};
+#[cfg(not(windows))]
+use crate::proc_fs;
+use regex::Regex;
+#[cfg(not(windows))]
+use std::collections::{BTreeSet, HashSet};
+#[cfg(windows)]
+use sysinfo::{ProcessExt, SystemExt};
 #[cfg(windows)]
 use crate::windows_bindings::PoolType;
 #[cfg(windows)]
 use crate::priv_esca::{drop_privileges, get_privileges};
+#[cfg(windows)]
 use winapi::ctypes::c_int;
 
 #[cfg(windows)]
@@ -106,16 +118,119 @@ type NtDuplicateObject = Option<extern "system" fn(
     options: winapi::shared::minwindef::ULONG,
 ) -> winapi::shared::ntdef::NTSTATUS>; // or extern "sdtcall"
 
-pub struct MutexParameters {
+pub struct ObjectParameters {
     pub ioc_entry_id: IocEntryId,
     pub ioc_id: IocId,
-    pub data: String,
+    pub search: SearchType,
+    pub object_type: ObjectType,
+    pub name: String,
+    /// `Mutex` for the legacy mutex check, an entry may have it besides an object check
+    pub check: CheckKind,
+}
+
+struct ObjectParametersRegexed {
+    object_param: ObjectParameters,
+    /// None for exact search, which matches name prefixes
+    regex: Option<Regex>,
+}
+
+impl ObjectParametersRegexed {
+    fn is_match(&self, name: &str) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(name),
+            None => name.starts_with(&self.object_param.name),
+        }
+    }
+}
+
+fn compile_parameters(search_parameters: Vec<ObjectParameters>) -> Vec<ObjectParametersRegexed> {
+    search_parameters.into_iter()
+        .filter_map(|sp| match sp.search {
+            SearchType::Exact => Some(ObjectParametersRegexed { object_param: sp, regex: None }),
+            SearchType::Regex => match Regex::new(&sp.name) {
+                Ok(regex) => Some(ObjectParametersRegexed { object_param: sp, regex: Some(regex) }),
+                Err(err) => {
+                    error!("Object search: Cannot parse object name {} as regex: {}", sp.name, err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Type name reported by NtQueryObject. Named pipes are File objects, whose names cannot be queried
+/// without risking a hang on synchronous pipes, they are listed from the pipe file system instead.
+#[cfg(windows)]
+fn kernel_type_name(object_type: ObjectType) -> Option<&'static str> {
+    match object_type {
+        ObjectType::Mutant => Some("Mutant"),
+        ObjectType::Event => Some("Event"),
+        ObjectType::Section => Some("Section"),
+        ObjectType::Semaphore => Some("Semaphore"),
+        ObjectType::NamedPipe => None,
+    }
+}
+
+#[cfg(windows)]
+pub fn check_objects(search_parameters: Vec<ObjectParameters>) -> Vec<IocEntrySearchResult> {
+    if search_parameters.is_empty() { return vec![]; }
+    info!("Object search: Searching IOCs using named object search.");
+    let (pipe_parameters, handle_parameters): (Vec<ObjectParametersRegexed>, Vec<ObjectParametersRegexed>) =
+        compile_parameters(search_parameters).into_iter()
+            .partition(|sp| sp.object_param.object_type == ObjectType::NamedPipe);
+    let mut ioc_results = check_named_pipes(&pipe_parameters);
+    if !handle_parameters.is_empty() {
+        ioc_results.extend(check_handles(handle_parameters));
+    }
+    ioc_results
 }
 
 #[cfg(windows)]
-pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySearchResult> {
+const PIPE_PATH: &str = r"\\.\pipe\";
+
+#[cfg(windows)]
+fn check_named_pipes(search_parameters: &[ObjectParametersRegexed]) -> Vec<IocEntrySearchResult> {
     if search_parameters.is_empty() { return vec![]; }
-    info!("Mutex search: Searching IOCs using open mutex search.");
+    let pipes = match std::fs::read_dir(PIPE_PATH) {
+        Ok(entries) => entries.filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<String>>(),
+        Err(err) => {
+            error!("Object search: Cannot list named pipes: {}", err);
+            return vec![];
+        }
+    };
+    let mut ioc_results = Vec::<IocEntrySearchResult>::new();
+    for pipe in &pipes {
+        let path = format!("{}{}", PIPE_PATH, pipe);
+        search_parameters.iter()
+            .filter(|search_parameter| search_parameter.is_match(pipe) || search_parameter.is_match(&path))
+            .for_each(|search_parameter| {
+                let message = format!("Object search: Found named pipe {} for IOC {}", path, search_parameter.object_param.ioc_id);
+                info!("{}", message);
+                ioc_results.push(IocEntrySearchResult {
+                    ioc_id: search_parameter.object_param.ioc_id,
+                    ioc_entry_id: search_parameter.object_param.ioc_entry_id,
+                    check: search_parameter.object_param.check,
+                    description: message,
+                    timestamp: None,
+                    evidence: Evidence::Object {
+                        kind: "named pipe".to_string(),
+                        name: pipe.clone(),
+                        location: Some(path.clone()),
+                        pids: vec![],
+                    },
+                })
+            });
+    }
+    ioc_results
+}
+
+/// Goes through the handles of all processes for named objects of the searched types.
+#[cfg(windows)]
+fn check_handles(search_parameters: Vec<ObjectParametersRegexed>) -> Vec<IocEntrySearchResult> {
+    let mut system = sysinfo::System::new_all();
+    system.refresh_all();
     let mut ioc_results = Vec::<IocEntrySearchResult>::new();
     unsafe {
         let ntdll_name = CString::new("ntdll.dll").unwrap();
@@ -125,7 +240,7 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
         let farproc = winapi::um::libloaderapi::GetProcAddress(ntdll, function_name.as_ptr());
         let query_system_information_fn = std::mem::transmute::<winapi::shared::minwindef::FARPROC, NtQuerySystemInformation>(farproc);
         if query_system_information_fn.is_none() {
-            let err = format!("Mutex search: Cannot load function NtQuerySystemInformation");
+            let err = format!("Object search: Cannot load function NtQuerySystemInformation");
             error!("{}", err);
             return ioc_results;
         }
@@ -135,7 +250,7 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
         let farproc = winapi::um::libloaderapi::GetProcAddress(ntdll, function_name.as_ptr());
         let query_object_fn = std::mem::transmute::<winapi::shared::minwindef::FARPROC, NtQueryObject>(farproc);
         if query_object_fn.is_none() {
-            let err = format!("Mutex search: Cannot load function NtQueryObject");
+            let err = format!("Object search: Cannot load function NtQueryObject");
             error!("{}", err);
             return ioc_results;
         }
@@ -145,7 +260,7 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
         let farproc = winapi::um::libloaderapi::GetProcAddress(ntdll, function_name.as_ptr());
         let duplicate_object_fn = std::mem::transmute::<winapi::shared::minwindef::FARPROC, NtDuplicateObject>(farproc);
         if duplicate_object_fn.is_none() {
-            let err = format!("Mutex search: Cannot load function NtDuplicateObject");
+            let err = format!("Object search: Cannot load function NtDuplicateObject");
             error!("{}", err);
             return ioc_results;
         }
@@ -153,7 +268,7 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
 
         let gp = get_privileges(winapi::um::winnt::SE_DEBUG_NAME);
         if gp.is_err() {
-            error!("Mutex check: {}, rerun as admin.", gp.unwrap_err());
+            error!("Object search: {}, rerun as admin.", gp.unwrap_err());
             return vec![];
         }
 
@@ -187,7 +302,6 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
                 winapi::um::winnt::PAGE_READWRITE,
             ) as *mut SystemHandleInformation;
         }
-        let mutant_string = widestring::WideString::from("Mutant".to_string());
         for i in 0..((*shi).number_of_handles as usize) {
             let handle: *mut SystemHandle = (*shi).handles.as_mut_ptr().add(i);
             let handle_val = (*handle).handle;
@@ -225,7 +339,7 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
             );
             let err_code = winapi::um::errhandlingapi::GetLastError();
             if err_code != 0 {
-                let err = format!("Mutex search: NtQueryObject failed, error code {}", err_code);
+                let err = format!("Object search: NtQueryObject failed, error code {}", err_code);
                 debug!("{}", err);
                 winapi::um::handleapi::CloseHandle(dup_handle);
                 winapi::um::handleapi::CloseHandle(process_handle);
@@ -246,7 +360,7 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
                                                               return_length,
                                                               &mut ret);
             if query_object_fn_return_code != 0 {
-                let err = format!("Mutex search: NtQueryObject failed with error 0x{:X}, system error code {}",
+                let err = format!("Object search: NtQueryObject failed with error 0x{:X}, system error code {}",
                                   query_object_fn_return_code,
                                   winapi::um::errhandlingapi::GetLastError());
                 error!("{}", err);
@@ -255,11 +369,15 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
                 continue;
             }
 
-            let ws = widestring::WideString::from_ptr(
+            // Length is in bytes
+            let type_name = widestring::WideString::from_ptr(
                 (*object_type_info).name.Buffer,
-                (*object_type_info).name.Length as usize,
-            );
-            if &ws.as_slice()[..6] != mutant_string.as_slice() {
+                (*object_type_info).name.Length as usize / 2,
+            ).to_string_lossy();
+            let type_parameters: Vec<&ObjectParametersRegexed> = search_parameters.iter()
+                .filter(|search_parameter| kernel_type_name(search_parameter.object_param.object_type) == Some(type_name.as_str()))
+                .collect();
+            if type_parameters.is_empty() {
                 winapi::um::memoryapi::VirtualFree(
                     object_type_info as *mut c_void,
                     0,
@@ -294,7 +412,7 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
                     ptr::null_mut(),
                 );
             if query_object_fn_return_code != 0 {
-                let err = format!("Mutex search: Fetch name failed with code 0x{:X}", query_object_fn_return_code);
+                let err = format!("Object search: Fetch name failed with code 0x{:X}", query_object_fn_return_code);
                 error!("{}", err);
                 winapi::um::memoryapi::VirtualFree(
                     object_name_info,
@@ -308,18 +426,34 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
 
             let name = object_name_info as winapi::shared::ntdef::PUNICODE_STRING;
             if (*name).Length != 0 {
-                let wss = widestring::WideString::from_ptr((*name).Buffer, (*name).Length as usize);
+                let wss = widestring::WideString::from_ptr((*name).Buffer, (*name).Length as usize / 2);
                 let ss = wss.to_string_lossy(); // Beware possible data loss.
-                search_parameters.iter()
-                    .filter(|search_parameter| {
-                        ss.starts_with(&search_parameter.data)
-                    }).for_each(|search_parameter| {
-                    let message = format!("Mutex search: Found mutex {} for IOC {}", ss, search_parameter.ioc_id);
+                type_parameters.iter()
+                    .filter(|search_parameter| search_parameter.is_match(&ss))
+                    .for_each(|search_parameter| {
+                    let process_name = system.get_process(pid as sysinfo::Pid)
+                        .map(|process| process.name().to_string())
+                        .unwrap_or_else(|| "UNKNOWN".to_string());
+                    let message = format!("Object search: Found {} {} held by {} ({}) for IOC {}",
+                                          type_name,
+                                          ss,
+                                          process_name,
+                                          pid,
+                                          search_parameter.object_param.ioc_id
+                    );
                     info!("{}", message);
                     ioc_results.push(IocEntrySearchResult {
-                        ioc_id: search_parameter.ioc_id,
-                        ioc_entry_id: search_parameter.ioc_entry_id,
-                        description: message
+                        ioc_id: search_parameter.object_param.ioc_id,
+                        ioc_entry_id: search_parameter.object_param.ioc_entry_id,
+                        check: search_parameter.object_param.check,
+                        description: message,
+                        timestamp: None,
+                        evidence: Evidence::Object {
+                            kind: type_name.clone(),
+                            name: ss.clone(),
+                            location: None,
+                            pids: vec![pid as u32],
+                        },
                     })
                 });
             }
@@ -347,8 +481,109 @@ pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySea
     ioc_results
 }
 
+/// Linux objects standing for the Windows ones, malware uses them in the same way.
 #[cfg(not(windows))]
-pub fn check_mutexes(search_parameters: Vec<MutexParameters>) -> Vec<IocEntrySearchResult> {
-    return vec![];
+fn linux_kinds(object_type: ObjectType) -> &'static [NamedObjectKind] {
+    match object_type {
+        ObjectType::Mutant => &[
+            NamedObjectKind::Semaphore,
+            NamedObjectKind::SharedMemory,
+            NamedObjectKind::AbstractSocket,
+            NamedObjectKind::LockFile,
+        ],
+        ObjectType::Event => &[],
+        ObjectType::Section => &[NamedObjectKind::SharedMemory],
+        ObjectType::Semaphore => &[NamedObjectKind::Semaphore],
+        ObjectType::NamedPipe => &[NamedObjectKind::Fifo, NamedObjectKind::UnixSocket, NamedObjectKind::AbstractSocket],
+    }
 }
 
+#[cfg(not(windows))]
+pub fn check_objects(search_parameters: Vec<ObjectParameters>) -> Vec<IocEntrySearchResult> {
+    if search_parameters.is_empty() { return vec![]; }
+    info!("Object search: Searching IOCs using named object search.");
+    let search_parameters = compile_parameters(search_parameters);
+    search_parameters.iter()
+        .filter(|sp| linux_kinds(sp.object_param.object_type).is_empty())
+        .for_each(|sp| warn!("Object search: IOC {} searches for {:?}, which has no Linux counterpart",
+                             sp.object_param.ioc_id,
+                             sp.object_param.object_type
+        ));
+    let objects = linux_named_objects();
+    let matches: Vec<_> = objects.iter()
+        .flat_map(|object| search_parameters.iter()
+            .filter(move |search_parameter| {
+                linux_kinds(search_parameter.object_param.object_type).contains(&object.kind)
+                    && (search_parameter.is_match(&object.name) || search_parameter.is_match(&object.location))
+            })
+            .map(move |search_parameter| (object, search_parameter)))
+        .collect();
+    let handles: HashSet<&str> = matches.iter()
+        .flat_map(|(object, _)| object.handles.iter().map(|it| it.as_str()))
+        .collect();
+    let owners = handle_owners(&handles);
+
+    matches.iter().map(|(object, search_parameter)| {
+        let pids: BTreeSet<u32> = object.handles.iter()
+            .flat_map(|handle| owners.get(handle).into_iter().flatten().copied())
+            .collect();
+        let held_by = if pids.is_empty() {
+            String::new()
+        } else {
+            let processes: Vec<String> = pids.iter()
+                .map(|pid| format!("{} ({})", proc_fs::process_name(*pid), pid))
+                .collect();
+            format!(" held by {}", processes.join(", "))
+        };
+        let message = format!("Object search: Found {} {} ({}){} for IOC {}",
+                              object.kind,
+                              object.name,
+                              object.location,
+                              held_by,
+                              search_parameter.object_param.ioc_id
+        );
+        info!("{}", message);
+        IocEntrySearchResult {
+            ioc_id: search_parameter.object_param.ioc_id,
+            ioc_entry_id: search_parameter.object_param.ioc_entry_id,
+            check: search_parameter.object_param.check,
+            description: message,
+            timestamp: None,
+            evidence: Evidence::Object {
+                kind: object.kind.to_string(),
+                name: object.name.clone(),
+                location: Some(object.location.clone()),
+                pids: pids.into_iter().collect(),
+            },
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{ObjectType, SearchType};
+    use crate::ioc_evaluator::CheckKind;
+    use crate::mutant_checker::{compile_parameters, ObjectParameters};
+
+    #[test]
+    fn test_compile_parameters() {
+        let parameters = |search, name: &str| ObjectParameters {
+            ioc_entry_id: 1,
+            ioc_id: 1,
+            search,
+            object_type: ObjectType::Mutant,
+            name: name.to_string(),
+            check: CheckKind::Mutex,
+        };
+        let compiled = compile_parameters(vec![
+            parameters(SearchType::Exact, "\\BaseNamedObjects\\Evil"),
+            parameters(SearchType::Regex, r"\\M_[0-9a-f]{8}-([0-9a-f]{4}-){3}[0-9a-f]{12}$"),
+            parameters(SearchType::Regex, "M_("),
+        ]);
+        assert_eq!(compiled.len(), 2);
+        assert!(compiled[0].is_match("\\BaseNamedObjects\\EvilMutex"));
+        assert!(!compiled[0].is_match("\\Sessions\\1\\BaseNamedObjects\\EvilMutex"));
+        assert!(compiled[1].is_match("\\BaseNamedObjects\\M_0f1e2d3c-aaaa-bbbb-cccc-0123456789ab"));
+        assert!(!compiled[1].is_match("\\BaseNamedObjects\\M_not-a-guid"));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::proc_fs;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fmt;
+use std::path::Path;
+use walkdir::WalkDir;
+
+pub const SHM_PATH: &str = "/dev/shm";
+pub const PROC_NET_UNIX_PATH: &str = "/proc/net/unix";
+/// Every file in these directories is a lock
+pub const LOCK_DIRS: [&str; 2] = ["/run/lock", "/var/lock"];
+/// Only files with a lock extension are locks in these directories
+pub const TEMP_DIRS: [&str; 2] = ["/tmp", "/var/tmp"];
+/// Directories searched for FIFOs standing for named pipes
+pub const FIFO_DIRS: [&str; 4] = ["/tmp", "/var/tmp", "/dev/shm", "/run"];
+const LOCK_EXTENSIONS: [&str; 3] = ["lock", "lck", "pid"];
+const SEMAPHORE_PREFIX: &str = "sem.";
+
+/// Linux counterparts of the Windows named objects malware uses as single instance markers or for IPC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NamedObjectKind {
+    Semaphore,
+    SharedMemory,
+    AbstractSocket,
+    LockFile,
+    Fifo,
+    UnixSocket,
+}
+
+impl Display for NamedObjectKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            NamedObjectKind::Semaphore => "POSIX semaphore",
+            NamedObjectKind::SharedMemory => "shared memory",
+            NamedObjectKind::AbstractSocket => "abstract UNIX socket",
+            NamedObjectKind::LockFile => "lock file",
+            NamedObjectKind::Fifo => "FIFO",
+            NamedObjectKind::UnixSocket => "UNIX socket",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// `name` is what the program passed to sem_open, shm_open or bind, `location` where the object was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedObject {
+    pub kind: NamedObjectKind,
+    pub name: String,
+    pub location: String,
+    /// How processes holding the object see it among their open or mapped files, e.g. `socket:[1234]`
+    pub handles: Vec<String>,
+}
+
+/// All Linux named objects, unavailable sources are skipped.
+pub fn linux_named_objects() -> Vec<NamedObject> {
+    let mut objects = shm_objects(Path::new(SHM_PATH));
+    match std::fs::read_to_string(PROC_NET_UNIX_PATH) {
+        Ok(content) => objects.extend(parse_proc_net_unix(&content)),
+        Err(err) => debug!("Object search: Cannot read {}: {}", PROC_NET_UNIX_PATH, err),
+    }
+    for dir in LOCK_DIRS.iter() {
+        objects.extend(lock_files(Path::new(dir), 2, false));
+    }
+    for dir in TEMP_DIRS.iter() {
+        objects.extend(lock_files(Path::new(dir), 1, true));
+    }
+    for dir in FIFO_DIRS.iter() {
+        objects.extend(fifos(Path::new(dir), 3));
+    }
+    objects
+}
+
+/// POSIX semaphores are `sem.<name>` files of the shm directory, other files are shared memory objects.
+fn shm_objects(shm_dir: &Path) -> Vec<NamedObject> {
+    let entries = match std::fs::read_dir(shm_dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            debug!("Object search: Cannot list {}: {}", shm_dir.display(), err);
+            return vec![];
+        }
+    };
+    entries.filter_map(Result::ok)
+        .filter(|entry| entry.file_type().map(|it| it.is_file()).unwrap_or(false))
+        .map(|entry| shm_object(&entry.file_name().to_string_lossy(), &entry.path().to_string_lossy()))
+        .collect()
+}
+
+pub fn shm_object(file_name: &str, location: &str) -> NamedObject {
+    let (kind, name) = if file_name.starts_with(SEMAPHORE_PREFIX) && file_name.len() > SEMAPHORE_PREFIX.len() {
+        (NamedObjectKind::Semaphore, &file_name[SEMAPHORE_PREFIX.len()..])
+    } else {
+        (NamedObjectKind::SharedMemory, file_name)
+    };
+    NamedObject { kind, name: name.to_string(), location: location.to_string(), handles: vec![location.to_string()] }
+}
+
+/// Abstract and path bound sockets from `/proc/net/unix`, each once. The kernel shows abstract names
+/// with a leading `@` and NUL bytes in them also as `@`.
+pub fn parse_proc_net_unix(content: &str) -> Vec<NamedObject> {
+    let mut objects: Vec<NamedObject> = Vec::new();
+    // Num RefCount Protocol Flags Type St Inode Path
+    for line in content.lines().skip(1) {
+        let path = proc_fs::skip_fields(line, 7);
+        let (kind, name, location) = if path.starts_with('@') && path.len() > 1 {
+            (NamedObjectKind::AbstractSocket, &path[1..], PROC_NET_UNIX_PATH)
+        } else if path.starts_with('/') {
+            (NamedObjectKind::UnixSocket, path, path)
+        } else {
+            continue;
+        };
+        let handle = match line.split_whitespace().nth(6) {
+            Some(inode) => format!("socket:[{}]", inode),
+            None => continue,
+        };
+        match objects.iter_mut().find(|it| it.kind == kind && it.name == name) {
+            Some(object) => object.handles.push(handle),
+            None => objects.push(NamedObject {
+                kind,
+                name: name.to_string(),
+                location: location.to_string(),
+                handles: vec![handle],
+            }),
+        }
+    }
+    objects
+}
+
+/// Pids of the processes having any of the handles open or mapped.
+pub fn handle_owners(handles: &HashSet<&str>) -> HashMap<String, BTreeSet<u32>> {
+    let mut owners: HashMap<String, BTreeSet<u32>> = HashMap::new();
+    if handles.is_empty() {
+        return owners;
+    }
+    for pid in proc_fs::list_pids() {
+        for file in proc_fs::open_files(pid).into_iter().chain(proc_fs::mapped_files(pid)) {
+            if handles.contains(file.as_str()) {
+                owners.entry(file).or_default().insert(pid);
+            }
+        }
+    }
+    owners
+}
+
+fn lock_files(dir: &Path, max_depth: usize, lock_extension_only: bool) -> Vec<NamedObject> {
+    WalkDir::new(dir).max_depth(max_depth).into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| !lock_extension_only || entry.path().extension()
+            .map(|extension| LOCK_EXTENSIONS.iter().any(|it| extension == *it))
+            .unwrap_or(false))
+        .map(|entry| {
+            let location = entry.path().to_string_lossy().to_string();
+            NamedObject {
+                kind: NamedObjectKind::LockFile,
+                name: entry.file_name().to_string_lossy().to_string(),
+                handles: vec![location.clone()],
+                location,
+            }
+        })
+        .collect()
+}
+
+/// Processes open FIFOs by path, so the path is also the handle.
+#[cfg(unix)]
+fn fifos(dir: &Path, max_depth: usize) -> Vec<NamedObject> {
+    use std::os::unix::fs::FileTypeExt;
+    WalkDir::new(dir).max_depth(max_depth).into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_fifo())
+        .map(|entry| {
+            let location = entry.path().to_string_lossy().to_string();
+            NamedObject {
+                kind: NamedObjectKind::Fifo,
+                name: entry.file_name().to_string_lossy().to_string(),
+                handles: vec![location.clone()],
+                location,
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn fifos(_dir: &Path, _max_depth: usize) -> Vec<NamedObject> {
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::named_objects::{parse_proc_net_unix, shm_object, NamedObjectKind};
+
+    #[test]
+    fn test_parse_proc_net_unix() {
+        let content = "Num       RefCount Protocol Flags    Type St Inode Path\n\
+            0000000000000000: 00000002 00000000 00010000 0001 01 23117 @/tmp/.X11-unix/X0\n\
+            0000000000000000: 00000002 00000000 00010000 0001 01 31337 @evil instance@lock\n\
+            0000000000000000: 00000003 00000000 00000000 0001 03 31338 @evil instance@lock\n\
+            0000000000000000: 00000002 00000000 00010000 0001 01 18080 /run/systemd/private\n\
+            0000000000000000: 00000003 00000000 00000000 0001 03 18081\n";
+        let objects = parse_proc_net_unix(content);
+        assert_eq!(objects.len(), 3);
+        assert_eq!(objects[0].name, "/tmp/.X11-unix/X0");
+        assert_eq!(objects[1].name, "evil instance@lock");
+        assert_eq!(objects[1].kind, NamedObjectKind::AbstractSocket);
+        assert_eq!(objects[1].handles, vec!["socket:[31337]", "socket:[31338]"]);
+        assert_eq!(objects[2].kind, NamedObjectKind::UnixSocket);
+        assert_eq!(objects[2].name, "/run/systemd/private");
+        assert_eq!(objects[2].handles, vec!["socket:[18080]"]);
+    }
+
+    #[test]
+    fn test_shm_object() {
+        let semaphore = shm_object("sem.evil_mutex", "/dev/shm/sem.evil_mutex");
+        assert_eq!(semaphore.kind, NamedObjectKind::Semaphore);
+        assert_eq!(semaphore.name, "evil_mutex");
+        let shared_memory = shm_object("pulse-shm-1", "/dev/shm/pulse-shm-1");
+        assert_eq!(shared_memory.kind, NamedObjectKind::SharedMemory);
+        assert_eq!(shared_memory.name, "pulse-shm-1");
+    }
+}
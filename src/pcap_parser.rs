@@ -0,0 +1,614 @@
+use crate::data::ConnectionProtocol;
+use crate::dns_sources::DnsEntry;
+use chrono::{DateTime, TimeZone, Utc};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+const PCAP_MAGIC_MICROS: u32 = 0xA1B2_C3D4;
+const PCAP_MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x01;
+const PCAPNG_OBSOLETE_PACKET: u32 = 0x02;
+const PCAPNG_SIMPLE_PACKET: u32 = 0x03;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x06;
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_TIMESTAMP_RESOLUTION: u16 = 9;
+const MICROSECONDS: u64 = 1_000_000;
+const NANOSECONDS: u64 = 1_000_000_000;
+
+const LINKTYPE_NULL: u16 = 0;
+const LINKTYPE_ETHERNET: u16 = 1;
+const LINKTYPE_RAW: u16 = 101;
+const LINKTYPE_LINUX_SLL: u16 = 113;
+const LINKTYPE_IPV4: u16 = 228;
+const LINKTYPE_IPV6: u16 = 229;
+const LINKTYPE_LINUX_SLL2: u16 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88A8;
+
+const IP_PROTOCOL_TCP: u8 = 6;
+const IP_PROTOCOL_UDP: u8 = 17;
+const TCP_FLAG_SYN: u8 = 0x02;
+const TCP_FLAG_ACK: u8 = 0x10;
+
+const DNS_PORT: u16 = 53;
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_CNAME: u16 = 5;
+const DNS_TYPE_AAAA: u16 = 28;
+const DNS_MAX_POINTER_JUMPS: usize = 64;
+
+#[derive(Debug)]
+pub struct PcapError {
+    pub kind: String,
+    pub message: String,
+}
+
+impl Display for PcapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "PcapError(kind: {}, message: {})", self.kind, self.message)
+    }
+}
+
+impl From<std::io::Error> for PcapError {
+    fn from(error: std::io::Error) -> Self {
+        PcapError {
+            kind: "IO".to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl PcapError {
+    fn format(message: &str) -> Self {
+        PcapError {
+            kind: "Format".to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// TCP or UDP conversation, the client is the side which sent the first packet or the TCP SYN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flow {
+    pub protocol: ConnectionProtocol,
+    pub client: (IpAddr, u16),
+    pub server: (IpAddr, u16),
+    /// The TCP handshake was captured, so the client is known to have initiated the connection
+    pub handshake_seen: bool,
+    /// Time of the first captured packet
+    pub first_seen: Option<DateTime<Utc>>,
+}
+
+/// Evidence extracted from a capture file.
+#[derive(Debug, Default)]
+pub struct Capture {
+    /// Capture file the evidence comes from
+    pub source: String,
+    pub dns: Vec<DnsEntry>,
+    pub flows: Vec<Flow>,
+}
+
+pub fn read_capture(path: &Path) -> Result<Capture, PcapError> {
+    let content = std::fs::read(path)?;
+    parse_capture(&content, &path.display().to_string())
+}
+
+/// Parses classic pcap and pcapng, `source` names the capture in the found DNS entries.
+pub fn parse_capture(content: &[u8], source: &str) -> Result<Capture, PcapError> {
+    let packets = if content.len() >= 4 && read_u32(content, 0, false) == Some(PCAPNG_SECTION_HEADER) {
+        pcapng_packets(content)?
+    } else {
+        pcap_packets(content)?
+    };
+    let mut collector = Collector::new(source);
+    for (link_type, timestamp, data) in packets {
+        if let Some(packet) = parse_link_layer(link_type, data) {
+            collector.add(&packet, timestamp);
+        }
+    }
+    Ok(collector.capture())
+}
+
+fn read_u16(data: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes = [*data.get(offset)?, *data.get(offset + 1)?];
+    Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+}
+
+fn read_u32(data: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes = [*data.get(offset)?, *data.get(offset + 1)?, *data.get(offset + 2)?, *data.get(offset + 3)?];
+    Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+}
+
+/// Time of `units` counted since the UNIX epoch, `units_per_second` of them make a second.
+fn timestamp(units: u64, units_per_second: u64) -> Option<DateTime<Utc>> {
+    let nanoseconds = u128::from(units % units_per_second) * u128::from(NANOSECONDS) / u128::from(units_per_second);
+    Utc.timestamp_opt((units / units_per_second) as i64, nanoseconds as u32).single()
+}
+
+/// Packets with their link type and capture time.
+type Packets<'a> = Vec<(u16, Option<DateTime<Utc>>, &'a [u8])>;
+
+fn pcap_packets(content: &[u8]) -> Result<Packets<'_>, PcapError> {
+    let (big_endian, units_per_second) = match read_u32(content, 0, false) {
+        Some(PCAP_MAGIC_MICROS) => (false, MICROSECONDS),
+        Some(PCAP_MAGIC_NANOS) => (false, NANOSECONDS),
+        _ => match read_u32(content, 0, true) {
+            Some(PCAP_MAGIC_MICROS) => (true, MICROSECONDS),
+            Some(PCAP_MAGIC_NANOS) => (true, NANOSECONDS),
+            _ => return Err(PcapError::format("Neither pcap nor pcapng file")),
+        },
+    };
+    let link_type = read_u32(content, 20, big_endian)
+        .ok_or_else(|| PcapError::format("Truncated pcap header"))? as u16;
+    let mut packets = Vec::new();
+    let mut offset = 24;
+    while offset + 16 <= content.len() {
+        let captured_length = read_u32(content, offset + 8, big_endian).unwrap_or_default() as usize;
+        let data = content.get(offset + 16..offset + 16 + captured_length)
+            .ok_or_else(|| PcapError::format("Truncated pcap record"))?;
+        let seconds = u64::from(read_u32(content, offset, big_endian).unwrap_or_default());
+        let fraction = u64::from(read_u32(content, offset + 4, big_endian).unwrap_or_default());
+        packets.push((link_type, timestamp(seconds * units_per_second + fraction, units_per_second), data));
+        offset += 16 + captured_length;
+    }
+    Ok(packets)
+}
+
+/// Units per second of the interface timestamps from the `if_tsresol` option, microseconds by default.
+fn pcapng_timestamp_resolution(options: &[u8], big_endian: bool) -> u64 {
+    let mut offset = 0;
+    while let (Some(code), Some(length)) = (read_u16(options, offset, big_endian), read_u16(options, offset + 2, big_endian)) {
+        match code {
+            PCAPNG_OPTION_END => break,
+            PCAPNG_OPTION_TIMESTAMP_RESOLUTION => {
+                let resolution = match options.get(offset + 4) {
+                    Some(resolution) => *resolution,
+                    None => break,
+                };
+                // The most significant bit selects a power of two instead of ten
+                let units_per_second = if resolution & 0x80 == 0 {
+                    10u64.checked_pow(u32::from(resolution))
+                } else {
+                    2u64.checked_pow(u32::from(resolution & 0x7F))
+                };
+                return units_per_second.unwrap_or(MICROSECONDS);
+            }
+            _ => {}
+        }
+        offset += 4 + (length as usize).div_ceil(4) * 4;
+    }
+    MICROSECONDS
+}
+
+fn pcapng_packets(content: &[u8]) -> Result<Packets<'_>, PcapError> {
+    let mut packets = Vec::new();
+    let mut big_endian = false;
+    // Link type and timestamp resolution
+    let mut interfaces: Vec<(u16, u64)> = Vec::new();
+    let mut offset = 0;
+    while offset + 12 <= content.len() {
+        if read_u32(content, offset, false) == Some(PCAPNG_SECTION_HEADER) {
+            big_endian = match read_u32(content, offset + 8, false) {
+                Some(PCAPNG_BYTE_ORDER_MAGIC) => false,
+                _ if read_u32(content, offset + 8, true) == Some(PCAPNG_BYTE_ORDER_MAGIC) => true,
+                _ => return Err(PcapError::format("Invalid pcapng byte order magic")),
+            };
+            // Interface ids are numbered per section
+            interfaces.clear();
+        }
+        let block_type = read_u32(content, offset, big_endian).unwrap_or_default();
+        let block_length = read_u32(content, offset + 4, big_endian).unwrap_or_default() as usize;
+        if block_length < 12 || !block_length.is_multiple_of(4) {
+            return Err(PcapError::format("Invalid pcapng block length"));
+        }
+        let body = content.get(offset + 8..offset + block_length - 4)
+            .ok_or_else(|| PcapError::format("Truncated pcapng block"))?;
+        let interface_of = |interface: usize| interfaces.get(interface).cloned();
+        // High and low 32 bits of the timestamp follow the interface id
+        let timestamp_of = |units_per_second: u64| {
+            let high = u64::from(read_u32(body, 4, big_endian)?);
+            let low = u64::from(read_u32(body, 8, big_endian)?);
+            timestamp(high << 32 | low, units_per_second)
+        };
+        match block_type {
+            PCAPNG_INTERFACE_DESCRIPTION => {
+                let link_type = read_u16(body, 0, big_endian).unwrap_or_default();
+                let options = body.get(8..).unwrap_or_default();
+                interfaces.push((link_type, pcapng_timestamp_resolution(options, big_endian)));
+            }
+            PCAPNG_ENHANCED_PACKET => {
+                let interface = read_u32(body, 0, big_endian).unwrap_or_default() as usize;
+                let captured_length = read_u32(body, 12, big_endian).unwrap_or_default() as usize;
+                match (interface_of(interface), body.get(20..20 + captured_length)) {
+                    (Some((link_type, units_per_second)), Some(data)) => packets.push((link_type, timestamp_of(units_per_second), data)),
+                    _ => debug!("Pcap: Skipping malformed enhanced packet block at offset {}", offset),
+                }
+            }
+            PCAPNG_OBSOLETE_PACKET => {
+                let interface = read_u16(body, 0, big_endian).unwrap_or_default() as usize;
+                let captured_length = read_u32(body, 12, big_endian).unwrap_or_default() as usize;
+                match (interface_of(interface), body.get(20..20 + captured_length)) {
+                    (Some((link_type, units_per_second)), Some(data)) => packets.push((link_type, timestamp_of(units_per_second), data)),
+                    _ => debug!("Pcap: Skipping malformed packet block at offset {}", offset),
+                }
+            }
+            PCAPNG_SIMPLE_PACKET => {
+                let original_length = read_u32(body, 0, big_endian).unwrap_or_default() as usize;
+                let data = &body[4.min(body.len())..];
+                if let Some((link_type, _)) = interface_of(0) {
+                    packets.push((link_type, None, &data[..original_length.min(data.len())]));
+                }
+            }
+            _ => {}
+        }
+        offset += block_length;
+    }
+    Ok(packets)
+}
+
+/// Transport layer view of a captured packet.
+#[derive(Debug, PartialEq)]
+struct Packet<'a> {
+    protocol: ConnectionProtocol,
+    source: (IpAddr, u16),
+    destination: (IpAddr, u16),
+    tcp_flags: u8,
+    payload: &'a [u8],
+}
+
+fn parse_link_layer(link_type: u16, data: &[u8]) -> Option<Packet<'_>> {
+    match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut ethertype = read_u16(data, 12, true)?;
+            let mut offset = 14;
+            while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+                ethertype = read_u16(data, offset + 2, true)?;
+                offset += 4;
+            }
+            parse_ip(ethertype, data.get(offset..)?)
+        }
+        LINKTYPE_NULL => match read_u32(data, 0, false)? {
+            2 => parse_ip(ETHERTYPE_IPV4, data.get(4..)?),
+            24 | 28 | 30 => parse_ip(ETHERTYPE_IPV6, data.get(4..)?),
+            _ => None,
+        },
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => match data.first()? >> 4 {
+            4 => parse_ip(ETHERTYPE_IPV4, data),
+            6 => parse_ip(ETHERTYPE_IPV6, data),
+            _ => None,
+        },
+        LINKTYPE_LINUX_SLL => parse_ip(read_u16(data, 14, true)?, data.get(16..)?),
+        LINKTYPE_LINUX_SLL2 => parse_ip(read_u16(data, 0, true)?, data.get(20..)?),
+        _ => None,
+    }
+}
+
+fn parse_ip(ethertype: u16, data: &[u8]) -> Option<Packet<'_>> {
+    match ethertype {
+        ETHERTYPE_IPV4 => {
+            if data.len() < 20 {
+                return None;
+            }
+            let header_length = ((data.first()? & 0x0F) as usize) * 4;
+            let fragment_offset = read_u16(data, 6, true)? & 0x1FFF;
+            if fragment_offset != 0 {
+                return None;
+            }
+            let total_length = (read_u16(data, 2, true)? as usize).min(data.len());
+            let source = IpAddr::V4(Ipv4Addr::new(data[12], data[13], data[14], data[15]));
+            let destination = IpAddr::V4(Ipv4Addr::new(data[16], data[17], data[18], data[19]));
+            parse_transport(data[9], source, destination, data.get(header_length..total_length)?)
+        }
+        ETHERTYPE_IPV6 => {
+            let mut addresses = [[0u8; 16]; 2];
+            addresses[0].copy_from_slice(data.get(8..24)?);
+            addresses[1].copy_from_slice(data.get(24..40)?);
+            let mut next_header = *data.get(6)?;
+            let mut offset = 40;
+            // Hop-by-hop, routing, fragment and destination options extension headers
+            while let 0 | 43 | 44 | 60 = next_header {
+                let length = if next_header == 44 { 8 } else { (*data.get(offset + 1)? as usize + 1) * 8 };
+                next_header = *data.get(offset)?;
+                offset += length;
+            }
+            parse_transport(
+                next_header,
+                IpAddr::V6(Ipv6Addr::from(addresses[0])),
+                IpAddr::V6(Ipv6Addr::from(addresses[1])),
+                data.get(offset..)?,
+            )
+        }
+        _ => None,
+    }
+}
+
+fn parse_transport(protocol: u8, source: IpAddr, destination: IpAddr, data: &[u8]) -> Option<Packet<'_>> {
+    let source_port = read_u16(data, 0, true)?;
+    let destination_port = read_u16(data, 2, true)?;
+    match protocol {
+        IP_PROTOCOL_TCP => {
+            let header_length = ((*data.get(12)? >> 4) as usize) * 4;
+            Some(Packet {
+                protocol: ConnectionProtocol::Tcp,
+                source: (source, source_port),
+                destination: (destination, destination_port),
+                tcp_flags: *data.get(13)?,
+                payload: data.get(header_length..).unwrap_or_default(),
+            })
+        }
+        IP_PROTOCOL_UDP => Some(Packet {
+            protocol: ConnectionProtocol::Udp,
+            source: (source, source_port),
+            destination: (destination, destination_port),
+            tcp_flags: 0,
+            payload: data.get(8..)?,
+        }),
+        _ => None,
+    }
+}
+
+/// Protocol, local and remote end of a flow.
+type FlowKey = (ConnectionProtocol, (IpAddr, u16), (IpAddr, u16));
+
+struct Collector<'a> {
+    source: &'a str,
+    dns: Vec<DnsEntry>,
+    flows: Vec<Flow>,
+    flow_index: HashMap<FlowKey, usize>,
+}
+
+impl<'a> Collector<'a> {
+    fn new(source: &'a str) -> Self {
+        Collector { source, dns: Vec::new(), flows: Vec::new(), flow_index: HashMap::new() }
+    }
+
+    fn add(&mut self, packet: &Packet, timestamp: Option<DateTime<Utc>>) {
+        let protocol = packet.protocol;
+        let key = if packet.source <= packet.destination {
+            (protocol, packet.source, packet.destination)
+        } else {
+            (protocol, packet.destination, packet.source)
+        };
+        let syn = packet.tcp_flags & TCP_FLAG_SYN != 0;
+        let ack = packet.tcp_flags & TCP_FLAG_ACK != 0;
+        let (client, server) = if syn && ack {
+            (packet.destination, packet.source)
+        } else {
+            (packet.source, packet.destination)
+        };
+        match self.flow_index.get(&key) {
+            Some(index) => {
+                let flow = &mut self.flows[*index];
+                if syn && !flow.handshake_seen {
+                    flow.client = client;
+                    flow.server = server;
+                    flow.handshake_seen = true;
+                }
+            }
+            None => {
+                self.flow_index.insert(key, self.flows.len());
+                self.flows.push(Flow { protocol, client, server, handshake_seen: syn, first_seen: timestamp });
+            }
+        }
+        if protocol == ConnectionProtocol::Udp && (packet.source.1 == DNS_PORT || packet.destination.1 == DNS_PORT) {
+            let source = format!("DNS traffic in {}", self.source);
+            self.dns.extend(parse_dns_message(packet.payload, &source));
+        }
+    }
+
+    fn capture(mut self) -> Capture {
+        self.dns.dedup();
+        Capture { source: self.source.to_string(), dns: self.dns, flows: self.flows }
+    }
+}
+
+/// Queried names and A, AAAA and CNAME answers of a DNS message.
+pub fn parse_dns_message(message: &[u8], source: &str) -> Vec<DnsEntry> {
+    let mut entries = Vec::new();
+    let question_count = read_u16(message, 4, true).unwrap_or_default();
+    let answer_count = read_u16(message, 6, true).unwrap_or_default();
+    let mut offset = 12;
+    for _ in 0..question_count {
+        match read_dns_name(message, offset) {
+            Some((name, next)) => {
+                if answer_count == 0 {
+                    entries.push(dns_entry(&name, None, source));
+                }
+                offset = next + 4;
+            }
+            None => return entries,
+        }
+    }
+    for _ in 0..answer_count {
+        let (name, next) = match read_dns_name(message, offset) {
+            Some(name) => name,
+            None => return entries,
+        };
+        let record_type = read_u16(message, next, true).unwrap_or_default();
+        let data_length = read_u16(message, next + 8, true).unwrap_or_default() as usize;
+        let data_offset = next + 10;
+        let data = match message.get(data_offset..data_offset + data_length) {
+            Some(data) => data,
+            None => return entries,
+        };
+        match record_type {
+            DNS_TYPE_A if data.len() == 4 => {
+                let address = Ipv4Addr::new(data[0], data[1], data[2], data[3]).to_string();
+                entries.push(dns_entry(&name, Some(address), source));
+            }
+            DNS_TYPE_AAAA if data.len() == 16 => {
+                let mut address = [0u8; 16];
+                address.copy_from_slice(data);
+                entries.push(dns_entry(&name, Some(Ipv6Addr::from(address).to_string()), source));
+            }
+            DNS_TYPE_CNAME => {
+                entries.push(dns_entry(&name, None, source));
+                if let Some((target, _)) = read_dns_name(message, data_offset) {
+                    entries.push(dns_entry(&target, None, source));
+                }
+            }
+            _ => {}
+        }
+        offset = data_offset + data_length;
+    }
+    entries
+}
+
+fn dns_entry(name: &str, address: Option<String>, source: &str) -> DnsEntry {
+    DnsEntry { name: name.to_ascii_lowercase(), address, source: source.to_string() }
+}
+
+/// Name at `offset` and the offset following it, compression pointers are followed.
+fn read_dns_name(message: &[u8], offset: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut position = offset;
+    let mut next = None;
+    for _ in 0..DNS_MAX_POINTER_JUMPS {
+        let length = *message.get(position)? as usize;
+        if length == 0 {
+            return Some((labels.join("."), next.unwrap_or(position + 1)));
+        }
+        if length & 0xC0 == 0xC0 {
+            if next.is_none() {
+                next = Some(position + 2);
+            }
+            position = (read_u16(message, position, true)? & 0x3FFF) as usize;
+            continue;
+        }
+        let label = message.get(position + 1..position + 1 + length)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        position += 1 + length;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::ConnectionProtocol;
+    use crate::pcap_parser::{parse_capture, parse_dns_message, Flow};
+    use chrono::{DateTime, Utc};
+    use std::net::IpAddr;
+    use std::str::FromStr;
+
+    fn dns_query() -> Vec<u8> {
+        let mut message = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        message.extend_from_slice(b"\x02c2\x07example\x03com\x00\x00\x01\x00\x01");
+        message
+    }
+
+    fn ipv4_udp(source: [u8; 4], destination: [u8; 4], source_port: u16, destination_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0u8; 12];
+        packet.extend_from_slice(&[0x08, 0x00]);
+        let total_length = (20 + 8 + payload.len()) as u16;
+        packet.extend_from_slice(&[0x45, 0, (total_length >> 8) as u8, total_length as u8, 0, 0, 0, 0, 64, 17, 0, 0]);
+        packet.extend_from_slice(&source);
+        packet.extend_from_slice(&destination);
+        packet.extend_from_slice(&source_port.to_be_bytes());
+        packet.extend_from_slice(&destination_port.to_be_bytes());
+        packet.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn ipv4_tcp(source: [u8; 4], destination: [u8; 4], source_port: u16, destination_port: u16, flags: u8) -> Vec<u8> {
+        let mut packet = vec![0u8; 12];
+        packet.extend_from_slice(&[0x08, 0x00, 0x45, 0, 0, 40, 0, 0, 0x40, 0, 64, 6, 0, 0]);
+        packet.extend_from_slice(&source);
+        packet.extend_from_slice(&destination);
+        packet.extend_from_slice(&source_port.to_be_bytes());
+        packet.extend_from_slice(&destination_port.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0x50, flags, 0xFF, 0xFF, 0, 0, 0, 0]);
+        packet
+    }
+
+    /// Response for c2.example.com with a CNAME and an A record, names compressed.
+    fn dns_response() -> Vec<u8> {
+        let mut message = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0];
+        message.extend_from_slice(b"\x02c2\x07example\x03com\x00\x00\x01\x00\x01");
+        message.extend_from_slice(&[0xC0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 7]);
+        message.extend_from_slice(b"\x04edge\xC0\x0F");
+        message.extend_from_slice(&[0xC0, 44, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 198, 51, 100, 7]);
+        message
+    }
+
+    const FIRST_PACKET_SECONDS: u32 = 1_600_000_000;
+
+    fn pcap(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = vec![0xD4, 0xC3, 0xB2, 0xA1, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0, 0, 1, 0, 0, 0];
+        for (index, packet) in packets.iter().enumerate() {
+            file.extend_from_slice(&(FIRST_PACKET_SECONDS + index as u32).to_le_bytes());
+            file.extend_from_slice(&500_000u32.to_le_bytes());
+            file.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            file.extend_from_slice(packet);
+        }
+        file
+    }
+
+    fn pcapng(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = vec![0x0A, 0x0D, 0x0D, 0x0A, 28, 0, 0, 0, 0x4D, 0x3C, 0x2B, 0x1A, 1, 0, 0, 0];
+        file.extend_from_slice(&[0xFF; 8]);
+        file.extend_from_slice(&[28, 0, 0, 0]);
+        file.extend_from_slice(&[1, 0, 0, 0, 20, 0, 0, 0, 1, 0, 0, 0, 0xFF, 0xFF, 0, 0, 20, 0, 0, 0]);
+        for (index, packet) in packets.iter().enumerate() {
+            let padded = packet.len().div_ceil(4) * 4;
+            let block_length = (32 + padded) as u32;
+            file.extend_from_slice(&[6, 0, 0, 0]);
+            file.extend_from_slice(&block_length.to_le_bytes());
+            file.extend_from_slice(&[0u8; 4]);
+            let microseconds = (u64::from(FIRST_PACKET_SECONDS) + index as u64) * 1_000_000 + 500_000;
+            file.extend_from_slice(&((microseconds >> 32) as u32).to_le_bytes());
+            file.extend_from_slice(&(microseconds as u32).to_le_bytes());
+            file.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            file.extend_from_slice(packet);
+            file.extend_from_slice(&vec![0u8; padded - packet.len()]);
+            file.extend_from_slice(&block_length.to_le_bytes());
+        }
+        file
+    }
+
+    #[test]
+    fn test_parse_dns_message() {
+        let entries = parse_dns_message(&dns_response(), "capture.pcap");
+        let names: Vec<(&str, Option<&str>)> = entries.iter().map(|it| (it.name.as_str(), it.address.as_deref())).collect();
+        assert_eq!(names, vec![
+            ("c2.example.com", None),
+            ("edge.example.com", None),
+            ("edge.example.com", Some("198.51.100.7")),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_capture() {
+        let packets = vec![
+            ipv4_udp([10, 0, 0, 5], [10, 0, 0, 1], 53124, 53, &dns_query()),
+            ipv4_udp([10, 0, 0, 1], [10, 0, 0, 5], 53, 53124, &dns_response()),
+            ipv4_tcp([198, 51, 100, 7], [10, 0, 0, 5], 4444, 49822, 0x12),
+            ipv4_tcp([10, 0, 0, 5], [198, 51, 100, 7], 49822, 4444, 0x10),
+        ];
+        for file in [pcap(&packets), pcapng(&packets)].iter() {
+            let capture = parse_capture(file, "capture.pcap").unwrap();
+            assert_eq!(capture.dns[0].name, "c2.example.com");
+            assert!(capture.dns.iter().any(|it| it.address.as_deref() == Some("198.51.100.7")));
+            assert_eq!(capture.flows.len(), 2);
+            let address = |it: &str| IpAddr::from_str(it).unwrap();
+            assert_eq!(capture.flows[1], Flow {
+                protocol: ConnectionProtocol::Tcp,
+                client: (address("10.0.0.5"), 49822),
+                server: (address("198.51.100.7"), 4444),
+                handshake_seen: true,
+                first_seen: Some("2020-09-13T12:26:42.500Z".parse::<DateTime<Utc>>().unwrap()),
+            });
+        }
+        assert!(parse_capture(b"not a capture", "x").is_err());
+    }
+}
@@ -0,0 +1,404 @@
+use crate::data::{IocEntryId, IocId, SearchType, Evidence};
+use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult};
+use crate::artifact_parser::{ScheduledTask, Service, Shortcut, decode_text, parse_task_xml, parse_lnk, parse_reg_services};
+use crate::search_pattern::{SearchPattern, optional_match};
+use crate::utils::modified_time;
+use std::path::{Path, PathBuf, Component};
+use walkdir::WalkDir;
+
+pub struct ScheduledTaskParameters {
+    pub ioc_id: IocId,
+    pub ioc_entry_id: IocEntryId,
+    pub search: SearchType,
+    pub name: Option<String>,
+    pub command: Option<String>,
+    pub arguments: Option<String>,
+}
+
+pub struct ShortcutParameters {
+    pub ioc_id: IocId,
+    pub ioc_entry_id: IocEntryId,
+    pub search: SearchType,
+    pub name: Option<String>,
+    pub target: Option<String>,
+    pub arguments: Option<String>,
+}
+
+pub struct ServiceParameters {
+    pub ioc_id: IocId,
+    pub ioc_entry_id: IocEntryId,
+    pub search: SearchType,
+    pub name: Option<String>,
+    pub image_path: Option<String>,
+    pub service_dll: Option<String>,
+}
+
+struct CompiledPatterns {
+    ioc_id: IocId,
+    ioc_entry_id: IocEntryId,
+    name: Option<SearchPattern>,
+    // Command of a task action, target of a shortcut or image path of a service
+    target: Option<SearchPattern>,
+    // Arguments of a task action or shortcut
+    arguments: Option<SearchPattern>,
+    service_dll: Option<SearchPattern>,
+}
+
+fn compile_patterns(
+    checker: &str,
+    ioc_id: IocId,
+    ioc_entry_id: IocEntryId,
+    search: SearchType,
+    patterns: [&Option<String>; 4],
+) -> Option<CompiledPatterns> {
+    if patterns.iter().all(|it| it.is_none()) {
+        warn!("{}: IOC {} specifies nothing to search for, skipping", checker, ioc_id);
+        return None;
+    }
+    let compile = |pattern: &Option<String>| match pattern {
+        None => Ok(None),
+        Some(pattern) => SearchPattern::new_ignore_case(search, pattern).map(Some),
+    };
+    match (compile(patterns[0]), compile(patterns[1]), compile(patterns[2]), compile(patterns[3])) {
+        (Ok(name), Ok(target), Ok(arguments), Ok(service_dll)) =>
+            Some(CompiledPatterns { ioc_id, ioc_entry_id, name, target, arguments, service_dll }),
+        (Err(err), _, _, _) | (_, Err(err), _, _) | (_, _, Err(err), _) | (_, _, _, Err(err)) => {
+            error!("{}: {}", checker, err);
+            None
+        }
+    }
+}
+
+pub fn check_scheduled_tasks(search_parameters: Vec<ScheduledTaskParameters>, artifacts_dir: Option<&Path>) -> Vec<IocEntrySearchResult> {
+    if search_parameters.is_empty() {
+        return vec![];
+    }
+    info!("Scheduled task search: Searching IOCs using scheduled task search.");
+    let search_parameters: Vec<CompiledPatterns> = search_parameters.iter()
+        .filter_map(|sp| compile_patterns(
+            "Scheduled task search",
+            sp.ioc_id,
+            sp.ioc_entry_id,
+            sp.search,
+            [&sp.name, &sp.command, &sp.arguments, &None],
+        ))
+        .collect();
+
+    let mut result: Vec<IocEntrySearchResult> = Vec::new();
+    for (path, task) in load_scheduled_tasks(&task_roots(artifacts_dir)) {
+        debug!("Scheduled task search: Checking task {} from {}", task.name, path.display());
+        search_parameters.iter()
+            .filter(|sp| task_matches(sp, &task))
+            .for_each(|sp| {
+                let message = format!("Scheduled task search: Found task {} ({}) for IOC {}",
+                                      task.name,
+                                      path.display(),
+                                      sp.ioc_id
+                );
+                info!("{}", message);
+                result.push(IocEntrySearchResult {
+                    ioc_id: sp.ioc_id,
+                    ioc_entry_id: sp.ioc_entry_id,
+                    check: CheckKind::ScheduledTask,
+                    description: message,
+                    timestamp: modified_time(&path),
+                    evidence: Evidence::ScheduledTask {
+                        path: path.display().to_string(),
+                        name: task.name.clone(),
+                        author: task.author.clone(),
+                        actions: task.actions.iter()
+                            .map(|action| match &action.arguments {
+                                Some(arguments) => format!("{} {}", action.command, arguments),
+                                None => action.command.clone(),
+                            })
+                            .collect(),
+                    },
+                })
+            });
+    }
+    result
+}
+
+fn task_matches(sp: &CompiledPatterns, task: &ScheduledTask) -> bool {
+    if !optional_match(&sp.name, Some(&task.name)) {
+        return false;
+    }
+    if sp.target.is_none() && sp.arguments.is_none() {
+        return true;
+    }
+    task.actions.iter().any(|action|
+        optional_match(&sp.target, Some(&action.command))
+            && optional_match(&sp.arguments, action.arguments.as_deref())
+    )
+}
+
+pub fn check_shortcuts(search_parameters: Vec<ShortcutParameters>, artifacts_dir: Option<&Path>) -> Vec<IocEntrySearchResult> {
+    if search_parameters.is_empty() {
+        return vec![];
+    }
+    info!("Shortcut search: Searching IOCs using startup shortcut search.");
+    let search_parameters: Vec<CompiledPatterns> = search_parameters.iter()
+        .filter_map(|sp| compile_patterns(
+            "Shortcut search",
+            sp.ioc_id,
+            sp.ioc_entry_id,
+            sp.search,
+            [&sp.name, &sp.target, &sp.arguments, &None],
+        ))
+        .collect();
+
+    let mut result: Vec<IocEntrySearchResult> = Vec::new();
+    for (path, shortcut) in load_shortcuts(&shortcut_roots(artifacts_dir)) {
+        debug!("Shortcut search: Checking shortcut {}", path.display());
+        let file_name = path.file_name().map(|it| it.to_string_lossy().to_string());
+        search_parameters.iter()
+            .filter(|sp| shortcut_matches(sp, file_name.as_deref(), &shortcut))
+            .for_each(|sp| {
+                let message = format!("Shortcut search: Found shortcut {} -> {} for IOC {}",
+                                      path.display(),
+                                      shortcut.target.as_deref().unwrap_or("UNKNOWN"),
+                                      sp.ioc_id
+                );
+                info!("{}", message);
+                result.push(IocEntrySearchResult {
+                    ioc_id: sp.ioc_id,
+                    ioc_entry_id: sp.ioc_entry_id,
+                    check: CheckKind::Shortcut,
+                    description: message,
+                    timestamp: modified_time(&path),
+                    evidence: Evidence::Shortcut {
+                        path: path.display().to_string(),
+                        target: shortcut.target.clone(),
+                        arguments: shortcut.arguments.clone(),
+                    },
+                })
+            });
+    }
+    result
+}
+
+fn shortcut_matches(sp: &CompiledPatterns, file_name: Option<&str>, shortcut: &Shortcut) -> bool {
+    optional_match(&sp.name, file_name)
+        && optional_match(&sp.target, shortcut.target.as_deref())
+        && optional_match(&sp.arguments, shortcut.arguments.as_deref())
+}
+
+pub fn check_services(search_parameters: Vec<ServiceParameters>, artifacts_dir: Option<&Path>) -> Vec<IocEntrySearchResult> {
+    if search_parameters.is_empty() {
+        return vec![];
+    }
+    info!("Service search: Searching IOCs using service search.");
+    let search_parameters: Vec<CompiledPatterns> = search_parameters.iter()
+        .filter_map(|sp| compile_patterns(
+            "Service search",
+            sp.ioc_id,
+            sp.ioc_entry_id,
+            sp.search,
+            [&sp.name, &sp.image_path, &None, &sp.service_dll],
+        ))
+        .collect();
+
+    let services = match artifacts_dir {
+        Some(artifacts_dir) => load_services(artifacts_dir),
+        None => live_services(),
+    };
+    let mut result: Vec<IocEntrySearchResult> = Vec::new();
+    for (source, service) in services {
+        debug!("Service search: Checking service {} from {}", service.name, source);
+        search_parameters.iter()
+            .filter(|sp| service_matches(sp, &service))
+            .for_each(|sp| {
+                let message = format!("Service search: Found service {} ({}) in {} for IOC {}",
+                                      service.name,
+                                      service.service_dll.as_ref().or(service.image_path.as_ref()).map(|it| it.as_str()).unwrap_or("UNKNOWN"),
+                                      source,
+                                      sp.ioc_id
+                );
+                info!("{}", message);
+                result.push(IocEntrySearchResult {
+                    ioc_id: sp.ioc_id,
+                    ioc_entry_id: sp.ioc_entry_id,
+                    check: CheckKind::Service,
+                    description: message,
+                    timestamp: None,
+                    evidence: Evidence::Service {
+                        source: source.clone(),
+                        name: service.name.clone(),
+                        display_name: service.display_name.clone(),
+                        image_path: service.image_path.clone(),
+                        service_dll: service.service_dll.clone(),
+                        start: service.start,
+                    },
+                })
+            });
+    }
+    result
+}
+
+/// The name matches the service name or its display name.
+fn service_matches(sp: &CompiledPatterns, service: &Service) -> bool {
+    let name_matches = match &sp.name {
+        None => true,
+        Some(name) => name.is_match(&service.name)
+            || service.display_name.as_ref().map(|it| name.is_match(it)).unwrap_or(false),
+    };
+    name_matches
+        && optional_match(&sp.target, service.image_path.as_deref())
+        && optional_match(&sp.service_dll, service.service_dll.as_deref())
+}
+
+/// Services of the `regedit` exports (`.reg`) among the artifacts.
+fn load_services(artifacts_dir: &Path) -> Vec<(String, Service)> {
+    WalkDir::new(artifacts_dir).into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.path().extension()
+            .map(|it| it.to_string_lossy().eq_ignore_ascii_case("reg"))
+            .unwrap_or(false))
+        .flat_map(|entry| {
+            let source = entry.path().display().to_string();
+            match std::fs::read(entry.path()) {
+                Ok(bytes) => parse_reg_services(&decode_text(&bytes)).into_iter()
+                    .map(|service| (source.clone(), service))
+                    .collect(),
+                Err(err) => {
+                    debug!("Service search: Cannot read {}: {}", source, err);
+                    vec![]
+                }
+            }
+        })
+        .collect()
+}
+
+/// Task definitions are recognised by living under a `Tasks` directory,
+/// the part of the path below it is the task name.
+fn load_scheduled_tasks(roots: &[PathBuf]) -> Vec<(PathBuf, ScheduledTask)> {
+    roots.iter()
+        .flat_map(|root| WalkDir::new(root).into_iter().filter_map(Result::ok))
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let fallback_name = task_name_from_path(entry.path())?;
+            let bytes = std::fs::read(entry.path())
+                .map_err(|err| debug!("Scheduled task search: Cannot read {}: {}", entry.path().display(), err))
+                .ok()?;
+            match parse_task_xml(&decode_text(&bytes), &fallback_name) {
+                Ok(task) => Some((entry.path().to_path_buf(), task)),
+                Err(err) => {
+                    debug!("Scheduled task search: Skipping {}: {}", entry.path().display(), err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn task_name_from_path(path: &Path) -> Option<String> {
+    let components: Vec<Component> = path.components().collect();
+    let tasks_position = components.iter()
+        .rposition(|it| it.as_os_str().to_string_lossy().eq_ignore_ascii_case("Tasks"))?;
+    let name_parts: Vec<String> = components[tasks_position + 1..].iter()
+        .map(|it| it.as_os_str().to_string_lossy().to_string())
+        .collect();
+    if name_parts.is_empty() {
+        return None;
+    }
+    Some(format!("\\{}", name_parts.join("\\")))
+}
+
+fn load_shortcuts(roots: &[PathBuf]) -> Vec<(PathBuf, Shortcut)> {
+    roots.iter()
+        .flat_map(|root| WalkDir::new(root).into_iter().filter_map(Result::ok))
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.path().extension()
+            .map(|it| it.to_string_lossy().eq_ignore_ascii_case("lnk"))
+            .unwrap_or(false))
+        .filter_map(|entry| {
+            let bytes = std::fs::read(entry.path())
+                .map_err(|err| debug!("Shortcut search: Cannot read {}: {}", entry.path().display(), err))
+                .ok()?;
+            match parse_lnk(&bytes) {
+                Ok(shortcut) => Some((entry.path().to_path_buf(), shortcut)),
+                Err(err) => {
+                    debug!("Shortcut search: Skipping {}: {}", entry.path().display(), err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn task_roots(artifacts_dir: Option<&Path>) -> Vec<PathBuf> {
+    match artifacts_dir {
+        Some(artifacts_dir) => vec![artifacts_dir.to_path_buf()],
+        None => live_task_roots(),
+    }
+}
+
+fn shortcut_roots(artifacts_dir: Option<&Path>) -> Vec<PathBuf> {
+    match artifacts_dir {
+        Some(artifacts_dir) => vec![artifacts_dir.to_path_buf()],
+        None => live_shortcut_roots(),
+    }
+}
+
+#[cfg(windows)]
+fn live_task_roots() -> Vec<PathBuf> {
+    vec![crate::dir_resolver::resolve(PathBuf::from("%SystemRoot%/System32/Tasks"))]
+}
+
+#[cfg(windows)]
+fn live_shortcut_roots() -> Vec<PathBuf> {
+    vec![
+        crate::dir_resolver::resolve(PathBuf::from("%ProgramData%/Microsoft/Windows/Start Menu/Programs/StartUp")),
+        crate::dir_resolver::resolve(PathBuf::from("%AppData%/Microsoft/Windows/Start Menu/Programs/Startup")),
+    ]
+}
+
+#[cfg(not(windows))]
+fn live_task_roots() -> Vec<PathBuf> {
+    info!("Scheduled task search: No artifacts directory specified, nothing to search on this platform.");
+    vec![]
+}
+
+#[cfg(not(windows))]
+fn live_shortcut_roots() -> Vec<PathBuf> {
+    info!("Shortcut search: No artifacts directory specified, nothing to search on this platform.");
+    vec![]
+}
+
+#[cfg(windows)]
+fn live_services() -> Vec<(String, Service)> {
+    use winreg::enums::{HKEY_LOCAL_MACHINE, KEY_READ};
+    use winreg::RegKey;
+
+    const SERVICES_KEY: &str = "SYSTEM\\CurrentControlSet\\Services";
+    let services = match RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey_with_flags(SERVICES_KEY, KEY_READ) {
+        Ok(services) => services,
+        Err(err) => {
+            error!("Service search: Cannot open HKLM\\{}: {}", SERVICES_KEY, err);
+            return vec![];
+        }
+    };
+    services.enum_keys()
+        .filter_map(Result::ok)
+        .filter_map(|name| {
+            let key = services.open_subkey_with_flags(&name, KEY_READ).ok()?;
+            let service_dll = key.open_subkey_with_flags("Parameters", KEY_READ).ok()
+                .and_then(|parameters| parameters.get_value("ServiceDll").ok());
+            let service = Service {
+                display_name: key.get_value("DisplayName").ok(),
+                image_path: key.get_value("ImagePath").ok(),
+                service_dll,
+                start: key.get_value("Start").ok(),
+                name,
+            };
+            Some((format!("HKLM\\{}\\{}", SERVICES_KEY, service.name), service))
+        })
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn live_services() -> Vec<(String, Service)> {
+    info!("Service search: No artifacts directory specified, nothing to search on this platform.");
+    vec![]
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+
+pub const PROC_PATH: &str = "/proc";
+
+/// Pids of all processes visible in procfs, empty where procfs is not available.
+pub fn list_pids() -> Vec<u32> {
+    match std::fs::read_dir(PROC_PATH) {
+        Ok(entries) => entries.filter_map(Result::ok)
+            .filter_map(|entry| entry.file_name().to_string_lossy().parse::<u32>().ok())
+            .collect(),
+        Err(err) => {
+            debug!("Cannot list {}: {}", PROC_PATH, err);
+            vec![]
+        }
+    }
+}
+
+pub fn process_name(pid: u32) -> String {
+    std::fs::read_to_string(Path::new(PROC_PATH).join(pid.to_string()).join("comm"))
+        .map(|it| it.trim_end().to_string())
+        .unwrap_or_else(|_| "UNKNOWN".to_string())
+}
+
+/// Environment of a process, None when the process is gone or not readable.
+pub fn process_environ(pid: u32) -> Option<Vec<(String, String)>> {
+    let environ = std::fs::read(Path::new(PROC_PATH).join(pid.to_string()).join("environ")).ok()?;
+    Some(parse_environ(&environ))
+}
+
+/// Targets of the open file descriptors of a process, like `/tmp/x.lock` or `socket:[1234]`.
+pub fn open_files(pid: u32) -> Vec<String> {
+    match std::fs::read_dir(Path::new(PROC_PATH).join(pid.to_string()).join("fd")) {
+        Ok(entries) => entries.filter_map(Result::ok)
+            .filter_map(|entry| std::fs::read_link(entry.path()).ok())
+            .map(|target| target.to_string_lossy().to_string())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// Paths of the files mapped into the memory of a process.
+pub fn mapped_files(pid: u32) -> Vec<String> {
+    std::fs::read_to_string(Path::new(PROC_PATH).join(pid.to_string()).join("maps"))
+        .map(|content| parse_maps(&content))
+        .unwrap_or_default()
+}
+
+/// Parses `/proc/<pid>/maps`, lines are `address perms offset dev inode path` with an optional path.
+pub fn parse_maps(content: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for line in content.lines() {
+        let path = skip_fields(line, 5);
+        if path.starts_with('/') && !paths.iter().any(|it| it == path) {
+            paths.push(path.to_string());
+        }
+    }
+    paths
+}
+
+/// Rest of a whitespace separated line after `count` fields, for a last field which may contain spaces.
+pub fn skip_fields(line: &str, count: usize) -> &str {
+    let mut rest = line;
+    for _ in 0..count {
+        rest = rest.trim_start();
+        rest = &rest[rest.find(char::is_whitespace).unwrap_or(rest.len())..];
+    }
+    rest.trim_start()
+}
+
+/// Parses NUL separated `NAME=value` pairs.
+pub fn parse_environ(environ: &[u8]) -> Vec<(String, String)> {
+    environ.split(|it| *it == 0)
+        .filter(|it| !it.is_empty())
+        .map(|variable| {
+            let variable = String::from_utf8_lossy(variable);
+            let mut parts = variable.splitn(2, '=');
+            let name = parts.next().unwrap_or_default().to_string();
+            let value = parts.next().unwrap_or_default().to_string();
+            (name, value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::proc_fs::{parse_environ, parse_maps};
+
+    #[test]
+    fn test_parse_environ() {
+        let environ = parse_environ(b"PATH=/usr/bin\0LD_PRELOAD=/tmp/x.so\0EMPTY=\0A=b=c\0");
+        assert_eq!(environ, vec![
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("LD_PRELOAD".to_string(), "/tmp/x.so".to_string()),
+            ("EMPTY".to_string(), "".to_string()),
+            ("A".to_string(), "b=c".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_maps() {
+        let maps = "55d0c8a00000-55d0c8a02000 r--p 00000000 08:01 1310742                    /usr/bin/evil daemon\n\
+            7f1c2c000000-7f1c2c001000 rw-s 00000000 00:1a 4242                       /dev/shm/sem.evil_mutex\n\
+            7f1c2c001000-7f1c2c002000 rw-s 00000000 00:1a 4242                       /dev/shm/sem.evil_mutex\n\
+            7ffc4e3b1000-7ffc4e3d2000 rw-p 00000000 00:00 0                          [stack]\n\
+            7ffc4e3d2000-7ffc4e3d3000 rw-p 00000000 00:00 0\n";
+        assert_eq!(parse_maps(maps), vec!["/usr/bin/evil daemon", "/dev/shm/sem.evil_mutex"]);
+    }
+}
@@ -1,9 +1,15 @@
-use crate::data::{IocEntryId, SearchType, Hashed, IocId, HashType};
+use crate::data::{IocEntryId, SearchType, Hashed, IocId};
+#[cfg(windows)]
+use crate::data::{HashType, Evidence, ProcessEvidence};
 use crate::ioc_evaluator::IocEntrySearchResult;
+#[cfg(windows)]
+use crate::ioc_evaluator::CheckKind;
 use sysinfo::{ProcessExt, SystemExt};
 use regex::Regex;
 use std::path::Path;
 use crate::hasher::Hasher;
+#[cfg(windows)]
+use crate::utils::epoch_time;
 
 pub struct ProcessParameters {
     pub ioc_id: IocId,
@@ -61,6 +67,17 @@ pub fn check_processes(search_parameters: Vec<ProcessParameters>) -> Vec<IocEntr
         let hasher_sha256 = Hasher::new(HashType::Sha256);
         let executable_hash_sha256 = hasher_sha256.hash_file_by_path(exe_path);
 
+        let evidence = ProcessEvidence {
+            pid: proc.pid() as u32,
+            name: proc.name().to_string(),
+            exe: Some(exe_path.display().to_string()),
+            command_line: proc.cmd().to_vec(),
+            hashes: [&executable_hash_md5, &executable_hash_sha1, &executable_hash_sha256].iter()
+                .filter_map(|it| it.as_ref().ok().cloned())
+                .collect(),
+            started: epoch_time(proc.start_time()),
+        };
+
         debug!("Process search: Checking process {}", proc.name());
         search_parameters.iter().for_each(|sp| {
             match &sp.proc_param.hash {
@@ -81,7 +98,10 @@ pub fn check_processes(search_parameters: Vec<ProcessParameters>) -> Vec<IocEntr
                             result.push(IocEntrySearchResult {
                                 ioc_id: sp.proc_param.ioc_id,
                                 ioc_entry_id: sp.proc_param.ioc_entry_id,
+                                check: CheckKind::Process,
                                 description: message,
+                                timestamp: epoch_time(proc.start_time()),
+                                evidence: Evidence::Process(evidence.clone()),
                             });
                         }
                     }
@@ -108,7 +128,10 @@ pub fn check_processes(search_parameters: Vec<ProcessParameters>) -> Vec<IocEntr
                                         result.push(IocEntrySearchResult {
                                             ioc_id: sp.proc_param.ioc_id,
                                             ioc_entry_id: sp.proc_param.ioc_entry_id,
+                                            check: CheckKind::Process,
                                             description: message,
+                                            timestamp: epoch_time(proc.start_time()),
+                                            evidence: Evidence::Process(evidence.clone()),
                                         })
                                     }
                             };
@@ -123,6 +146,6 @@ pub fn check_processes(search_parameters: Vec<ProcessParameters>) -> Vec<IocEntr
 }
 
 #[cfg(not(windows))]
-pub fn check_dns(search_parameters: Vec<DnsParameters>) -> Vec<IocEntrySearchResult> {
+pub fn check_processes(_search_parameters: Vec<ProcessParameters>) -> Vec<IocEntrySearchResult> {
     vec![]
 }
@@ -13,7 +13,19 @@ pub struct Properties {
     pub auth_probe_name: String,
     pub auth_key: String,
     pub deep_search: bool,
-    pub max_iocs: isize
+    pub max_iocs: isize,
+    #[serde(default = "default_reverse_dns")]
+    pub reverse_dns: bool,
+    #[serde(default = "default_reverse_dns_timeout_ms")]
+    pub reverse_dns_timeout_ms: u64,
+    #[serde(default = "default_certificate_paths")]
+    pub certificate_paths: Vec<String>,
+    /// Lowest score of IOCs under their threshold still reported as near misses
+    #[serde(default = "default_report_floor")]
+    pub report_floor: u8,
+    /// Upload what was found for each IOC instead of the scores only
+    #[serde(default)]
+    pub detailed_report: bool,
 }
 
 impl Properties {
@@ -54,10 +66,37 @@ fn default_properties() -> Properties {
         auth_probe_name: "TESTING".to_string(),
         auth_key: "TESTING".to_string(),
         deep_search: false,
-        max_iocs: 5000
+        max_iocs: 5000,
+        reverse_dns: default_reverse_dns(),
+        reverse_dns_timeout_ms: default_reverse_dns_timeout_ms(),
+        certificate_paths: default_certificate_paths(),
+        report_floor: default_report_floor(),
+        detailed_report: false,
     }
 }
 
+fn default_reverse_dns() -> bool { true }
+
+fn default_reverse_dns_timeout_ms() -> u64 { 2000 }
+
+/// Files and directories searched for certificates besides the native store
+#[cfg(not(windows))]
+fn default_certificate_paths() -> Vec<String> {
+    [
+        "/etc/ssl",
+        "/etc/pki",
+        "/etc/ca-certificates",
+        "/usr/share/ca-certificates",
+        "/usr/local/share/ca-certificates",
+        "/usr/lib/jvm",
+    ].iter().map(|it| it.to_string()).collect()
+}
+
+#[cfg(windows)]
+fn default_certificate_paths() -> Vec<String> { vec![] }
+
+fn default_report_floor() -> u8 { 50 }
+
 fn write_default_if_not_exists() -> Result<(), std::io::Error> {
     let maybe_properties_file = File::open(PROPERTIES_FILENAME);
     match maybe_properties_file {
@@ -71,7 +110,15 @@ fn write_default_if_not_exists() -> Result<(), std::io::Error> {
                     writer.write_all(b"auth_probe_name = \"TESTING\"\n")?;
                     writer.write_all(b"auth_key = \"TESTING\"\n")?;
                     writer.write_all(b"deep_search = false\n")?;
-                    writer.write_all(b"max_iocs = 5000")?;
+                    writer.write_all(b"max_iocs = 5000\n")?;
+                    writer.write_all(b"reverse_dns = true\n")?;
+                    writer.write_all(b"reverse_dns_timeout_ms = 2000\n")?;
+                    let certificate_paths: Vec<String> = default_certificate_paths().iter()
+                        .map(|it| format!("\"{}\"", it))
+                        .collect();
+                    writer.write_all(format!("certificate_paths = [{}]\n", certificate_paths.join(", ")).as_bytes())?;
+                    writer.write_all(b"report_floor = 50\n")?;
+                    writer.write_all(b"detailed_report = false\n")?;
                     let write_result = writer.flush();
                     if write_result.is_err() {
                         error!("Cannot write default properties into file: {}", write_result.unwrap_err());
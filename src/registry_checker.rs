@@ -1,6 +1,10 @@
 use crate::data::{IocEntryId, IocId, SearchType};
+#[cfg(windows)]
+use crate::data::Evidence;
 use crate::ioc_evaluator::IocEntrySearchResult;
 #[cfg(windows)]
+use crate::ioc_evaluator::CheckKind;
+#[cfg(windows)]
 use crate::priv_esca::{get_privileges, drop_privileges};
 #[cfg(windows)]
 use winapi::um::winreg::{HKEY_CLASSES_ROOT, HKEY_CURRENT_CONFIG, HKEY_CURRENT_USER, HKEY_CURRENT_USER_LOCAL_SETTINGS, HKEY_DYN_DATA, HKEY_LOCAL_MACHINE, HKEY_PERFORMANCE_DATA, HKEY_PERFORMANCE_NLSTEXT, HKEY_PERFORMANCE_TEXT, HKEY_USERS};
@@ -19,7 +23,7 @@ pub struct RegistryParameters {
 }
 
 #[cfg(not(windows))]
-pub fn check_registry(search_parameters: Vec<RegistryParameters>) -> Vec<IocEntrySearchResult> {
+pub fn check_registry(_search_parameters: Vec<RegistryParameters>, _deep_search_enabled: bool) -> Vec<IocEntrySearchResult> {
     return vec![];
 }
 
@@ -225,6 +229,11 @@ fn check_by_value(search_parameter: &RegistryParameters, reg_entry: &winreg::Reg
         }
     };
 
+    let evidence = Evidence::Registry {
+        key: search_parameter.key.clone(),
+        value_name: search_parameter.value_name.clone(),
+        value: reg_value.clone(),
+    };
     return match &search_parameter.value {
         None => {
             let message = format!("Registry search: Found reg key {}\\{} for IOC {}",
@@ -236,7 +245,10 @@ fn check_by_value(search_parameter: &RegistryParameters, reg_entry: &winreg::Reg
             Some(IocEntrySearchResult {
                 ioc_id: search_parameter.ioc_id,
                 ioc_entry_id: search_parameter.ioc_entry_id,
-                description: message
+                check: CheckKind::Registry,
+                description: message,
+                timestamp: None,
+                evidence,
             })
         }
         Some(search_value) => {
@@ -251,7 +263,10 @@ fn check_by_value(search_parameter: &RegistryParameters, reg_entry: &winreg::Reg
                 Some(IocEntrySearchResult {
                     ioc_id: search_parameter.ioc_id,
                     ioc_entry_id: search_parameter.ioc_entry_id,
-                    description: message
+                    check: CheckKind::Registry,
+                    description: message,
+                    timestamp: None,
+                    evidence,
                 })
             } else {
                 None
@@ -0,0 +1,213 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MAX_PARALLEL_LOOKUPS: usize = 16;
+
+/// Source of PTR names, implemented by the system resolver and by stand-ins in tests.
+pub trait ReverseResolver: Send + Sync {
+    fn lookup(&self, address: &IpAddr) -> Option<String>;
+}
+
+pub struct SystemResolver;
+
+impl ReverseResolver for SystemResolver {
+    fn lookup(&self, address: &IpAddr) -> Option<String> {
+        dns_lookup::lookup_addr(address).ok()
+    }
+}
+
+enum Lookup {
+    Started(IpAddr, Instant),
+    Answered(IpAddr, Option<String>),
+}
+
+/// Deduplicates lookups for a scan, unanswered and timed out lookups are cached as None.
+pub struct ReverseDns {
+    resolver: Option<Arc<dyn ReverseResolver>>,
+    timeout: Duration,
+    cache: HashMap<IpAddr, Option<String>>,
+}
+
+impl ReverseDns {
+    pub fn new(resolver: Arc<dyn ReverseResolver>, timeout: Duration) -> Self {
+        ReverseDns { resolver: Some(resolver), timeout, cache: HashMap::new() }
+    }
+
+    /// No lookup leaves the machine, names are never known.
+    pub fn disabled() -> Self {
+        ReverseDns { resolver: None, timeout: Duration::default(), cache: HashMap::new() }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.resolver.is_some()
+    }
+
+    /// Looks up all addresses not cached yet on at most `MAX_PARALLEL_LOOKUPS` worker threads, each lookup
+    /// given `timeout`. A blocked lookup cannot be cancelled, its worker is left behind and not replaced, once
+    /// all workers are blocked the remaining addresses are not looked up.
+    pub fn resolve_all<'a>(&mut self, addresses: impl IntoIterator<Item=&'a IpAddr>) {
+        let resolver = match &self.resolver {
+            Some(resolver) => resolver.clone(),
+            None => return,
+        };
+        let mut pending: Vec<IpAddr> = addresses.into_iter()
+            .filter(|it| !self.cache.contains_key(it))
+            .cloned()
+            .collect();
+        pending.sort();
+        pending.dedup();
+        if pending.is_empty() {
+            return;
+        }
+        let queue = Arc::new(Mutex::new(pending.iter().cloned().collect::<VecDeque<IpAddr>>()));
+        let (sender, receiver) = mpsc::channel();
+        let workers = pending.len().min(MAX_PARALLEL_LOOKUPS);
+        for _ in 0..workers {
+            let (resolver, queue, sender) = (resolver.clone(), queue.clone(), sender.clone());
+            thread::spawn(move || loop {
+                let address = match queue.lock().map(|mut it| it.pop_front()) {
+                    Ok(Some(address)) => address,
+                    _ => break,
+                };
+                if sender.send(Lookup::Started(address, Instant::now())).is_err() {
+                    break;
+                }
+                let name = resolver.lookup(&address);
+                if sender.send(Lookup::Answered(address, name)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut started: HashMap<IpAddr, Instant> = HashMap::new();
+        let mut blocked = 0;
+        let mut done = 0;
+        while done < pending.len() && blocked < workers {
+            let deadline = started.values().min().map(|it| *it + self.timeout);
+            let wait = deadline.map(|it| it.saturating_duration_since(Instant::now())).unwrap_or(self.timeout);
+            match receiver.recv_timeout(wait) {
+                Ok(Lookup::Started(address, start)) => {
+                    started.insert(address, start);
+                }
+                Ok(Lookup::Answered(address, name)) => {
+                    if started.remove(&address).is_some() {
+                        done += 1;
+                        self.cache.insert(address, name);
+                    } else {
+                        // Answer after the timeout, the worker is free again
+                        blocked -= 1;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let now = Instant::now();
+                    let timed_out: Vec<IpAddr> = started.iter()
+                        .filter(|(_, start)| **start + self.timeout <= now)
+                        .map(|(address, _)| *address)
+                        .collect();
+                    for address in timed_out {
+                        debug!("Reverse DNS: Lookup of {} timed out", address);
+                        started.remove(&address);
+                        self.cache.insert(address, None);
+                        done += 1;
+                        blocked += 1;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        if let Ok(mut queue) = queue.lock() {
+            queue.clear();
+        }
+        for address in pending {
+            self.cache.entry(address).or_insert_with(|| {
+                debug!("Reverse DNS: Lookup of {} skipped, all workers are blocked", address);
+                None
+            });
+        }
+    }
+
+    /// Name resolved by a previous `resolve_all`.
+    pub fn name_of(&self, address: &IpAddr) -> Option<&str> {
+        self.cache.get(address).and_then(|it| it.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reverse_dns::{ReverseResolver, ReverseDns, MAX_PARALLEL_LOOKUPS};
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    struct LocalResolver {
+        lookups: AtomicUsize,
+    }
+
+    impl ReverseResolver for LocalResolver {
+        fn lookup(&self, address: &IpAddr) -> Option<String> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            match address.to_string().as_str() {
+                "192.0.2.1" => Some("c2.example.com".to_string()),
+                "192.0.2.2" => {
+                    thread::sleep(Duration::from_secs(5));
+                    Some("slow.example.com".to_string())
+                }
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_all() {
+        let resolver = Arc::new(LocalResolver { lookups: AtomicUsize::new(0) });
+        let mut reverse_dns = ReverseDns::new(resolver.clone(), Duration::from_millis(200));
+        let addresses: Vec<IpAddr> = ["192.0.2.1", "192.0.2.1", "192.0.2.2", "192.0.2.3"].iter()
+            .map(|it| IpAddr::from_str(it).unwrap())
+            .collect();
+        reverse_dns.resolve_all(&addresses);
+        reverse_dns.resolve_all(&addresses);
+        assert_eq!(reverse_dns.name_of(&addresses[0]), Some("c2.example.com"));
+        assert_eq!(reverse_dns.name_of(&addresses[2]), None);
+        assert_eq!(reverse_dns.name_of(&addresses[3]), None);
+        assert_eq!(resolver.lookups.load(Ordering::SeqCst), 3);
+    }
+
+    struct BlockedResolver {
+        lookups: AtomicUsize,
+    }
+
+    impl ReverseResolver for BlockedResolver {
+        fn lookup(&self, _: &IpAddr) -> Option<String> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_secs(5));
+            None
+        }
+    }
+
+    #[test]
+    fn test_blocked_workers_are_not_replaced() {
+        let resolver = Arc::new(BlockedResolver { lookups: AtomicUsize::new(0) });
+        let mut reverse_dns = ReverseDns::new(resolver.clone(), Duration::from_millis(100));
+        let addresses: Vec<IpAddr> = (1..=40).map(|it| IpAddr::from_str(&format!("192.0.2.{}", it)).unwrap()).collect();
+        reverse_dns.resolve_all(&addresses);
+        assert_eq!(resolver.lookups.load(Ordering::SeqCst), MAX_PARALLEL_LOOKUPS);
+        assert!(addresses.iter().all(|it| reverse_dns.name_of(it).is_none()));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut reverse_dns = ReverseDns::disabled();
+        let address = IpAddr::from_str("192.0.2.1").unwrap();
+        reverse_dns.resolve_all(&[address]);
+        assert!(!reverse_dns.is_enabled());
+        assert_eq!(reverse_dns.name_of(&address), None);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::data::{IocEntryId, IocId, SearchType, Evidence};
+use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult};
+use crate::search_pattern::{SearchPattern, optional_match};
+use crate::proc_fs;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+const PROC_MODULES_PATH: &str = "/proc/modules";
+const SYS_MODULE_PATH: &str = "/sys/module";
+const LD_SO_PRELOAD_PATH: &str = "/etc/ld.so.preload";
+const LD_PRELOAD: &str = "LD_PRELOAD";
+
+pub struct KernelModuleParameters {
+    pub ioc_id: IocId,
+    pub ioc_entry_id: IocEntryId,
+    pub search: SearchType,
+    pub name: Option<String>,
+    pub hidden: bool,
+}
+
+pub struct PreloadParameters {
+    pub ioc_id: IocId,
+    pub ioc_entry_id: IocEntryId,
+    pub search: SearchType,
+    pub library: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelModule {
+    pub name: String,
+    pub in_proc_modules: bool,
+    pub in_sys_module: bool,
+}
+
+impl KernelModule {
+    /// Rootkits usually unlink themselves from only one of the two listings.
+    pub fn is_hidden(&self) -> bool {
+        self.in_proc_modules != self.in_sys_module
+    }
+}
+
+pub fn check_kernel_modules(search_parameters: Vec<KernelModuleParameters>) -> Vec<IocEntrySearchResult> {
+    if search_parameters.is_empty() {
+        return vec![];
+    }
+    info!("Kernel module search: Searching IOCs using kernel module search.");
+    let search_parameters: Vec<(KernelModuleParameters, Option<SearchPattern>)> = search_parameters.into_iter()
+        .filter_map(|sp| {
+            if sp.name.is_none() && !sp.hidden {
+                warn!("Kernel module search: IOC {} specifies nothing to search for, skipping", sp.ioc_id);
+                return None;
+            }
+            match sp.name.as_ref().map(|it| SearchPattern::new(sp.search, it)).transpose() {
+                Ok(pattern) => Some((sp, pattern)),
+                Err(err) => {
+                    error!("Kernel module search: {}", err);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let proc_modules = match std::fs::read_to_string(PROC_MODULES_PATH) {
+        Ok(content) => parse_proc_modules(&content),
+        Err(err) => {
+            error!("Kernel module search: Cannot read {}: {}", PROC_MODULES_PATH, err);
+            return vec![];
+        }
+    };
+    let modules = merge_module_listings(&proc_modules, &loaded_sys_modules(Path::new(SYS_MODULE_PATH)));
+
+    let mut result: Vec<IocEntrySearchResult> = Vec::new();
+    for module in &modules {
+        debug!("Kernel module search: Checking module {}", module.name);
+        search_parameters.iter()
+            .filter(|(sp, pattern)| (!sp.hidden || module.is_hidden()) && optional_match(pattern, Some(&module.name)))
+            .for_each(|(sp, _)| {
+                let message = if module.is_hidden() {
+                    format!("Kernel module search: Found module {} hidden from {} for IOC {}",
+                            module.name,
+                            if module.in_proc_modules { SYS_MODULE_PATH } else { PROC_MODULES_PATH },
+                            sp.ioc_id
+                    )
+                } else {
+                    format!("Kernel module search: Found module {} for IOC {}", module.name, sp.ioc_id)
+                };
+                info!("{}", message);
+                result.push(IocEntrySearchResult {
+                    ioc_id: sp.ioc_id,
+                    ioc_entry_id: sp.ioc_entry_id,
+                    check: CheckKind::KernelModule,
+                    description: message,
+                    timestamp: None,
+                    evidence: Evidence::KernelModule {
+                        name: module.name.clone(),
+                        hidden: module.is_hidden(),
+                    },
+                })
+            });
+    }
+    result
+}
+
+/// Module name is the first column of /proc/modules.
+pub fn parse_proc_modules(content: &str) -> Vec<String> {
+    content.lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|it| it.to_string())
+        .collect()
+}
+
+/// Built-in modules also appear in /sys/module, only loadable ones have `initstate`.
+fn loaded_sys_modules(sys_module_path: &Path) -> Vec<String> {
+    match std::fs::read_dir(sys_module_path) {
+        Ok(entries) => entries.filter_map(Result::ok)
+            .filter(|entry| entry.path().join("initstate").exists())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect(),
+        Err(err) => {
+            error!("Kernel module search: Cannot list {}: {}", sys_module_path.display(), err);
+            vec![]
+        }
+    }
+}
+
+pub fn merge_module_listings(proc_modules: &[String], sys_modules: &[String]) -> Vec<KernelModule> {
+    let names: BTreeSet<&String> = proc_modules.iter().chain(sys_modules.iter()).collect();
+    names.into_iter()
+        .map(|name| KernelModule {
+            name: name.clone(),
+            in_proc_modules: proc_modules.contains(name),
+            in_sys_module: sys_modules.contains(name),
+        })
+        .collect()
+}
+
+pub fn check_preload(search_parameters: Vec<PreloadParameters>) -> Vec<IocEntrySearchResult> {
+    if search_parameters.is_empty() {
+        return vec![];
+    }
+    info!("Preload search: Searching IOCs using ld.so.preload and LD_PRELOAD search.");
+    let search_parameters: Vec<(PreloadParameters, Option<SearchPattern>)> = search_parameters.into_iter()
+        .filter_map(|sp| match sp.library.as_ref().map(|it| SearchPattern::new(sp.search, it)).transpose() {
+            Ok(pattern) => Some((sp, pattern)),
+            Err(err) => {
+                error!("Preload search: {}", err);
+                None
+            }
+        })
+        .collect();
+
+    // (library, where it was preloaded from)
+    let mut preloads: Vec<(String, String)> = match std::fs::read_to_string(LD_SO_PRELOAD_PATH) {
+        Ok(content) => parse_preload_list(&content).into_iter()
+            .map(|library| (library, LD_SO_PRELOAD_PATH.to_string()))
+            .collect(),
+        Err(err) => {
+            debug!("Preload search: Cannot read {}: {}", LD_SO_PRELOAD_PATH, err);
+            vec![]
+        }
+    };
+    for pid in proc_fs::list_pids() {
+        let environ = match proc_fs::process_environ(pid) {
+            Some(environ) => environ,
+            None => continue,
+        };
+        environ.iter()
+            .filter(|(name, _)| name == LD_PRELOAD)
+            .flat_map(|(_, value)| parse_preload_list(value))
+            .for_each(|library| preloads.push((
+                library,
+                format!("{} of process {} (pid {})", LD_PRELOAD, proc_fs::process_name(pid), pid)
+            )));
+    }
+
+    let mut result: Vec<IocEntrySearchResult> = Vec::new();
+    for (library, source) in &preloads {
+        debug!("Preload search: Checking library {} from {}", library, source);
+        search_parameters.iter()
+            .filter(|(_, pattern)| optional_match(pattern, Some(library)))
+            .for_each(|(sp, _)| {
+                let message = format!("Preload search: Found preloaded library {} in {} for IOC {}",
+                                      library,
+                                      source,
+                                      sp.ioc_id
+                );
+                info!("{}", message);
+                result.push(IocEntrySearchResult {
+                    ioc_id: sp.ioc_id,
+                    ioc_entry_id: sp.ioc_entry_id,
+                    check: CheckKind::Preload,
+                    description: message,
+                    timestamp: None,
+                    evidence: Evidence::Preload {
+                        library: library.clone(),
+                        source: source.clone(),
+                    },
+                })
+            });
+    }
+    result
+}
+
+/// Both ld.so.preload and LD_PRELOAD separate libraries by whitespace or colons.
+pub fn parse_preload_list(content: &str) -> Vec<String> {
+    content.lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ':'))
+        .filter(|it| !it.is_empty())
+        .map(|it| it.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rootkit_checker::{parse_proc_modules, merge_module_listings, parse_preload_list};
+
+    #[test]
+    fn test_hidden_modules() {
+        let proc_modules = parse_proc_modules(
+            "nf_tables 249856 0 - Live 0x0000000000000000\n\
+             ext4 737280 1 - Live 0x0000000000000000\n"
+        );
+        let sys_modules = vec!["ext4".to_string(), "diamorphine".to_string()];
+        let modules = merge_module_listings(&proc_modules, &sys_modules);
+        let hidden: Vec<&str> = modules.iter()
+            .filter(|it| it.is_hidden())
+            .map(|it| it.name.as_str())
+            .collect();
+        assert_eq!(hidden, vec!["diamorphine", "nf_tables"]);
+        assert!(!modules.iter().find(|it| it.name == "ext4").unwrap().is_hidden());
+    }
+
+    #[test]
+    fn test_parse_preload_list() {
+        let libraries = parse_preload_list("# rootkit\n/usr/lib/libprocesshider.so /lib/x.so\n\n/tmp/a.so:/tmp/b.so\n");
+        assert_eq!(libraries, vec!["/usr/lib/libprocesshider.so", "/lib/x.so", "/tmp/a.so", "/tmp/b.so"]);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::data::SearchType;
+use regex::{Regex, RegexBuilder};
+
+/// IOC string pattern compiled once before the search, so that regexes are not rebuilt per item.
+pub enum SearchPattern {
+    Exact(String),
+    ExactIgnoreCase(String),
+    Regex(Regex),
+}
+
+impl SearchPattern {
+    pub fn new(search: SearchType, pattern: &str) -> Result<SearchPattern, regex::Error> {
+        match search {
+            SearchType::Exact => Ok(SearchPattern::Exact(pattern.to_string())),
+            SearchType::Regex => Regex::new(pattern).map(SearchPattern::Regex),
+        }
+    }
+
+    /// Windows paths and names are case insensitive, exact patterns for them should be too.
+    pub fn new_ignore_case(search: SearchType, pattern: &str) -> Result<SearchPattern, regex::Error> {
+        match search {
+            SearchType::Exact => Ok(SearchPattern::ExactIgnoreCase(pattern.to_lowercase())),
+            SearchType::Regex => RegexBuilder::new(pattern).case_insensitive(true).build().map(SearchPattern::Regex),
+        }
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        match self {
+            SearchPattern::Exact(pattern) => pattern == value,
+            SearchPattern::ExactIgnoreCase(pattern) => pattern == &value.to_lowercase(),
+            SearchPattern::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// Unspecified pattern matches anything, a specified one requires a value to match.
+pub fn optional_match(pattern: &Option<SearchPattern>, value: Option<&str>) -> bool {
+    match pattern {
+        None => true,
+        Some(pattern) => value.map(|value| pattern.is_match(value)).unwrap_or(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::SearchType;
+    use crate::search_pattern::SearchPattern;
+
+    #[test]
+    fn test_ignore_case() {
+        let regex = SearchPattern::new_ignore_case(SearchType::Regex, r"^c2\.evil\.com$").unwrap();
+        assert!(regex.is_match("C2.Evil.COM"));
+        assert!(!SearchPattern::new(SearchType::Regex, r"^c2\.evil\.com$").unwrap().is_match("C2.Evil.COM"));
+        let exact = SearchPattern::new_ignore_case(SearchType::Exact, "c2.evil.com").unwrap();
+        assert!(exact.is_match("C2.Evil.COM"));
+    }
+}
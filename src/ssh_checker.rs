@@ -0,0 +1,439 @@
+use crate::data::{IocEntryId, IocId, SearchType, Evidence};
+use crate::ioc_evaluator::{CheckKind, IocEntrySearchResult};
+use crate::search_pattern::{SearchPattern, optional_match};
+use crate::utils::modified_time;
+use crate::unix_accounts::{read_passwd, PASSWD_PATH, PasswdEntry};
+use md5::Md5;
+use sha2::{Sha256, Digest};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const SSHD_CONFIG_PATH: &str = "/etc/ssh/sshd_config";
+const SYSTEM_KNOWN_HOSTS_PATH: &str = "/etc/ssh/ssh_known_hosts";
+const DEFAULT_AUTHORIZED_KEYS_FILES: [&str; 2] = [".ssh/authorized_keys", ".ssh/authorized_keys2"];
+
+pub struct SshKeyParameters {
+    pub ioc_id: IocId,
+    pub ioc_entry_id: IocEntryId,
+    pub search: SearchType,
+    /// Always compared exactly, `search` applies to comment and command only
+    pub fingerprint: Option<String>,
+    pub comment: Option<String>,
+    pub command: Option<String>,
+}
+
+struct SshKeyParametersRegexed {
+    ssh_param: SshKeyParameters,
+    comment: Option<SearchPattern>,
+    command: Option<SearchPattern>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SshKeyLine {
+    pub line_number: usize,
+    pub key_type: String,
+    pub key_blob: Vec<u8>,
+    pub comment: Option<String>,
+    pub command: Option<String>,
+}
+
+struct SshKeyFile {
+    user: String,
+    path: PathBuf,
+    known_hosts: bool,
+}
+
+pub fn check_ssh_keys(search_parameters: Vec<SshKeyParameters>) -> Vec<IocEntrySearchResult> {
+    if search_parameters.is_empty() {
+        return vec![];
+    }
+    info!("SSH key search: Searching IOCs using SSH key search.");
+    let search_parameters: Vec<SshKeyParametersRegexed> = search_parameters.into_iter().filter_map(|sp| {
+        if sp.fingerprint.is_none() && sp.comment.is_none() && sp.command.is_none() {
+            warn!("SSH key search: IOC {} specifies nothing to search for, skipping", sp.ioc_id);
+            return None;
+        }
+        let comment = sp.comment.as_ref().map(|it| SearchPattern::new(sp.search, it)).transpose();
+        let command = sp.command.as_ref().map(|it| SearchPattern::new(sp.search, it)).transpose();
+        match (comment, command) {
+            (Ok(comment), Ok(command)) => Some(SshKeyParametersRegexed { ssh_param: sp, comment, command }),
+            (Err(err), _) | (_, Err(err)) => {
+                error!("SSH key search: {}", err);
+                None
+            }
+        }
+    }).collect();
+
+    let users = read_passwd(Path::new(PASSWD_PATH));
+    let mut result: Vec<IocEntrySearchResult> = Vec::new();
+    for key_file in key_files(&users, Path::new(SSHD_CONFIG_PATH)) {
+        let content = match std::fs::read_to_string(&key_file.path) {
+            Ok(content) => content,
+            Err(err) => {
+                debug!("SSH key search: Cannot read {}: {}", key_file.path.display(), err);
+                continue;
+            }
+        };
+        debug!("SSH key search: Checking keys in {}", key_file.path.display());
+        let keys = if key_file.known_hosts { parse_known_hosts(&content) } else { parse_authorized_keys(&content) };
+        for key in keys {
+            search_parameters.iter()
+                .filter(|sp| key_matches(sp, &key))
+                .for_each(|sp| {
+                    let message = format!("SSH key search: Found key {} of user {} at {}:{} for IOC {}",
+                                          fingerprint_sha256(&key.key_blob),
+                                          key_file.user,
+                                          key_file.path.display(),
+                                          key.line_number,
+                                          sp.ssh_param.ioc_id
+                    );
+                    info!("{}", message);
+                    result.push(IocEntrySearchResult {
+                        ioc_id: sp.ssh_param.ioc_id,
+                        ioc_entry_id: sp.ssh_param.ioc_entry_id,
+                        check: CheckKind::SshKey,
+                        description: message,
+                        timestamp: modified_time(&key_file.path),
+                        evidence: Evidence::SshKey {
+                            path: key_file.path.display().to_string(),
+                            user: key_file.user.clone(),
+                            line: key.line_number,
+                            key_type: key.key_type.clone(),
+                            fingerprint: fingerprint_sha256(&key.key_blob),
+                        },
+                    })
+                });
+        }
+    }
+    result
+}
+
+fn key_matches(sp: &SshKeyParametersRegexed, key: &SshKeyLine) -> bool {
+    let fingerprint_matched = match &sp.ssh_param.fingerprint {
+        None => true,
+        Some(fingerprint) => fingerprint_matches(fingerprint, &key.key_blob),
+    };
+    fingerprint_matched
+        && optional_match(&sp.comment, key.comment.as_deref())
+        && optional_match(&sp.command, key.command.as_deref())
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    match text.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => text.get(prefix.len()..),
+        _ => None,
+    }
+}
+
+/// Accepts fingerprints as printed by `ssh-keygen -l`, with or without the algorithm prefix.
+pub fn fingerprint_matches(fingerprint: &str, key_blob: &[u8]) -> bool {
+    let fingerprint = fingerprint.trim();
+    if let Some(sha256) = strip_prefix_ignore_case(fingerprint, "SHA256:") {
+        return sha256.trim_end_matches('=') == &fingerprint_sha256(key_blob)[7..];
+    }
+    let md5_hex = strip_prefix_ignore_case(fingerprint, "MD5:").unwrap_or(fingerprint);
+    let md5_hex = md5_hex.replace(':', "").to_ascii_lowercase();
+    if md5_hex.len() == 32 {
+        md5_hex == hex::encode(Md5::digest(key_blob))
+    } else {
+        fingerprint.trim_end_matches('=') == &fingerprint_sha256(key_blob)[7..]
+    }
+}
+
+pub fn fingerprint_sha256(key_blob: &[u8]) -> String {
+    format!("SHA256:{}", base64::encode_config(&Sha256::digest(key_blob), base64::STANDARD_NO_PAD))
+}
+
+/// Parses sshd(8) AUTHORIZED_KEYS FILE FORMAT: `[options] keytype base64-key [comment]`.
+pub fn parse_authorized_keys(content: &str) -> Vec<SshKeyLine> {
+    content.lines().enumerate()
+        .filter_map(|(i, line)| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (options, rest) = if is_key_type(line.split_whitespace().next()?) {
+                ("", line)
+            } else {
+                split_options(line)
+            };
+            let mut key = parse_key(rest, i + 1)?;
+            key.command = option_value(options, "command");
+            Some(key)
+        })
+        .collect()
+}
+
+/// Parses ssh(1) known_hosts: `[@marker] hostnames keytype base64-key [comment]`.
+pub fn parse_known_hosts(content: &str) -> Vec<SshKeyLine> {
+    content.lines().enumerate()
+        .filter_map(|(i, line)| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let line = if line.starts_with('@') {
+                line.split_once(char::is_whitespace)?.1.trim_start()
+            } else {
+                line
+            };
+            let rest = line.split_once(char::is_whitespace)?.1.trim_start();
+            parse_key(rest, i + 1)
+        })
+        .collect()
+}
+
+fn is_key_type(token: &str) -> bool {
+    token.starts_with("ssh-") || token.starts_with("ecdsa-") || token.starts_with("sk-")
+}
+
+fn parse_key(line: &str, line_number: usize) -> Option<SshKeyLine> {
+    let mut parts = line.split_whitespace();
+    let key_type = parts.next()?.to_string();
+    let key_blob = base64::decode(parts.next()?).ok().filter(|it| !it.is_empty())?;
+    let comment = Some(parts.collect::<Vec<&str>>().join(" "))
+        .filter(|it| !it.is_empty());
+    Some(SshKeyLine { line_number, key_type, key_blob, comment, command: None })
+}
+
+/// Options end at the first whitespace outside of double quotes.
+fn split_options(line: &str) -> (&str, &str) {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => return (&line[..i], line[i..].trim_start()),
+            _ => {}
+        }
+    }
+    (line, "")
+}
+
+/// Options are separated by commas outside of double quotes.
+fn option_list(options: &str) -> Vec<&str> {
+    let mut list = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in options.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                list.push(&options[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    list.push(&options[start..]);
+    list
+}
+
+fn option_value(options: &str, name: &str) -> Option<String> {
+    let quoted = option_list(options).into_iter()
+        .filter_map(|option| option.split_once('='))
+        .find(|(option, _)| option.eq_ignore_ascii_case(name))?.1;
+    let mut value = String::new();
+    let mut escaped = false;
+    for c in quoted.strip_prefix('"')?.chars() {
+        match c {
+            _ if escaped => {
+                value.push(c);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            '"' => return Some(value),
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+fn key_files(users: &[PasswdEntry], sshd_config_path: &Path) -> Vec<SshKeyFile> {
+    let patterns = authorized_keys_patterns(sshd_config_path);
+    let mut seen_paths = HashSet::<PathBuf>::new();
+    let mut key_files = Vec::<SshKeyFile>::new();
+    for user in users {
+        for pattern in &patterns {
+            let path = expand_authorized_keys_pattern(pattern, user);
+            if seen_paths.insert(path.clone()) {
+                key_files.push(SshKeyFile { user: user.name.clone(), path, known_hosts: false });
+            }
+        }
+        let known_hosts = user.home.join(".ssh/known_hosts");
+        if seen_paths.insert(known_hosts.clone()) {
+            key_files.push(SshKeyFile { user: user.name.clone(), path: known_hosts, known_hosts: true });
+        }
+    }
+    key_files.push(SshKeyFile { user: "*".to_string(), path: PathBuf::from(SYSTEM_KNOWN_HOSTS_PATH), known_hosts: true });
+    key_files
+}
+
+/// Collects every AuthorizedKeysFile value, including those from Match blocks and included files.
+fn authorized_keys_patterns(sshd_config_path: &Path) -> Vec<String> {
+    let mut patterns: Vec<String> = DEFAULT_AUTHORIZED_KEYS_FILES.iter().map(|it| it.to_string()).collect();
+    for config_path in sshd_config_files(sshd_config_path) {
+        let content = match std::fs::read_to_string(&config_path) {
+            Ok(content) => content,
+            Err(err) => {
+                debug!("SSH key search: Cannot read {}: {}", config_path.display(), err);
+                continue;
+            }
+        };
+        for line in content.lines() {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some(keyword) if keyword.eq_ignore_ascii_case("AuthorizedKeysFile") => {
+                    tokens.filter(|it| *it != "none")
+                        .for_each(|it| if !patterns.iter().any(|p| p == it) { patterns.push(it.to_string()) })
+                }
+                _ => {}
+            }
+        }
+    }
+    patterns
+}
+
+fn sshd_config_files(sshd_config_path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![sshd_config_path.to_path_buf()];
+    let content = std::fs::read_to_string(sshd_config_path).unwrap_or_default();
+    for line in content.lines() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some(keyword) if keyword.eq_ignore_ascii_case("Include") => {
+                tokens.for_each(|include| files.extend(expand_include(sshd_config_path, include)))
+            }
+            _ => {}
+        }
+    }
+    files
+}
+
+/// Supports the common `dir/*.conf` form, relative includes are resolved against /etc/ssh.
+fn expand_include(sshd_config_path: &Path, include: &str) -> Vec<PathBuf> {
+    let config_dir = sshd_config_path.parent().unwrap_or(Path::new("/"));
+    let include = config_dir.join(include);
+    let file_pattern = include.file_name().map(|it| it.to_string_lossy().to_string()).unwrap_or_default();
+    if !file_pattern.contains('*') {
+        return vec![include];
+    }
+    let mut pattern_parts = file_pattern.splitn(2, '*');
+    let prefix = pattern_parts.next().unwrap_or_default().to_string();
+    let suffix = pattern_parts.next().unwrap_or_default().to_string();
+    let dir = include.parent().unwrap_or(config_dir);
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| entries.filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.file_name()
+                .map(|it| it.to_string_lossy())
+                .map(|it| it.starts_with(&prefix) && it.ends_with(&suffix))
+                .unwrap_or(false))
+            .collect())
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn expand_authorized_keys_pattern(pattern: &str, user: &PasswdEntry) -> PathBuf {
+    let mut expanded = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('h') => expanded.push_str(&user.home.to_string_lossy()),
+            Some('u') => expanded.push_str(&user.name),
+            Some('U') => expanded.push_str(&user.uid.to_string()),
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+    let expanded = PathBuf::from(expanded);
+    if expanded.is_absolute() { expanded } else { user.home.join(expanded) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ssh_checker::{parse_authorized_keys, parse_known_hosts, fingerprint_matches, fingerprint_sha256, expand_authorized_keys_pattern};
+    use crate::unix_accounts::PasswdEntry;
+    use std::path::PathBuf;
+
+    const KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGAOv1m2PwOML2ZkR/+LLWqPhz4kgRM2c58GqeN79Se1";
+
+    #[test]
+    fn test_parse_authorized_keys_with_options() {
+        let content = format!(
+            "# managed by ansible\n{} admin@host\nno-pty,command=\"/bin/sh -c \\\"id\\\"\",from=\"10.0.0.1\" {} attacker@kali\n",
+            KEY, KEY
+        );
+        let keys = parse_authorized_keys(&content);
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].line_number, 2);
+        assert_eq!(keys[0].comment.as_deref(), Some("admin@host"));
+        assert_eq!(keys[0].command, None);
+        assert_eq!(keys[1].line_number, 3);
+        assert_eq!(keys[1].key_type, "ssh-ed25519");
+        assert_eq!(keys[1].comment.as_deref(), Some("attacker@kali"));
+        assert_eq!(keys[1].command.as_deref(), Some("/bin/sh -c \"id\""));
+    }
+
+    #[test]
+    fn test_parse_authorized_keys_option_names() {
+        let content = format!(
+            "environment=\"X=a,command=\\\"evil\\\"\",nocommand=\"x\",Command=\"/bin/true\" {}\nssh-ed25519\n",
+            KEY
+        );
+        let keys = parse_authorized_keys(&content);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].command.as_deref(), Some("/bin/true"));
+        assert_eq!(keys[0].comment, None);
+    }
+
+    #[test]
+    fn test_parse_known_hosts() {
+        let content = format!("@cert-authority *.example.com {}\n|1|aGFzaA==|c2FsdA== {}\n", KEY, KEY);
+        let keys = parse_known_hosts(&content);
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[1].line_number, 2);
+    }
+
+    #[test]
+    fn test_fingerprints() {
+        let key = &parse_authorized_keys(KEY)[0];
+        assert_eq!(fingerprint_sha256(&key.key_blob), "SHA256:dpH9cdH0iYFpdxTxwHm249JuLom1nlRhQxlAXOIRpmw");
+        assert!(fingerprint_matches("SHA256:dpH9cdH0iYFpdxTxwHm249JuLom1nlRhQxlAXOIRpmw", &key.key_blob));
+        assert!(fingerprint_matches("dpH9cdH0iYFpdxTxwHm249JuLom1nlRhQxlAXOIRpmw=", &key.key_blob));
+        assert!(fingerprint_matches("MD5:98:40:c6:4d:00:10:df:04:dd:a0:7c:d5:81:ad:c0:5f", &key.key_blob));
+        assert!(fingerprint_matches("9840C64D0010DF04DDA07CD581ADC05F", &key.key_blob));
+        assert!(!fingerprint_matches("MD5:00:40:c6:4d:00:10:df:04:dd:a0:7c:d5:81:ad:c0:5f", &key.key_blob));
+        assert!(fingerprint_matches("sha256:dpH9cdH0iYFpdxTxwHm249JuLom1nlRhQxlAXOIRpmw", &key.key_blob));
+        assert!(!fingerprint_matches("SHA25ä:dpH9cdH0iYFpdxTxwHm249JuLom1nlRhQxlAXOIRpmw", &key.key_blob));
+        assert!(!fingerprint_matches("MDä", &key.key_blob));
+    }
+
+    #[test]
+    fn test_expand_authorized_keys_pattern() {
+        let user = PasswdEntry {
+            name: "bob".to_string(),
+            uid: 1000,
+            gid: 1000,
+            gecos: "".to_string(),
+            home: PathBuf::from("/home/bob"),
+            shell: "/bin/bash".to_string(),
+        };
+        assert_eq!(expand_authorized_keys_pattern(".ssh/authorized_keys", &user), PathBuf::from("/home/bob/.ssh/authorized_keys"));
+        assert_eq!(expand_authorized_keys_pattern("/etc/ssh/keys/%u_%U", &user), PathBuf::from("/etc/ssh/keys/bob_1000"));
+        assert_eq!(expand_authorized_keys_pattern("%h/.ssh/extra", &user), PathBuf::from("/home/bob/.ssh/extra"));
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+pub const PASSWD_PATH: &str = "/etc/passwd";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswdEntry {
+    pub name: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub gecos: String,
+    pub home: PathBuf,
+    pub shell: String,
+}
+
+/// Parses passwd(5) formatted content, malformed lines are skipped.
+pub fn parse_passwd(content: &str) -> Vec<PasswdEntry> {
+    content.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 7 {
+                return None;
+            }
+            Some(PasswdEntry {
+                name: fields[0].to_string(),
+                uid: fields[2].parse().ok()?,
+                gid: fields[3].parse().ok()?,
+                gecos: fields[4].to_string(),
+                home: PathBuf::from(fields[5]),
+                shell: fields[6].to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn read_passwd(path: &Path) -> Vec<PasswdEntry> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => parse_passwd(&content),
+        Err(err) => {
+            debug!("Cannot read {}: {}", path.display(), err);
+            vec![]
+        }
+    }
+}
+
+pub const GROUP_PATH: &str = "/etc/group";
+pub const SHADOW_PATH: &str = "/etc/shadow";
+pub const SUDOERS_PATH: &str = "/etc/sudoers";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupEntry {
+    pub name: String,
+    pub gid: u32,
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowEntry {
+    pub name: String,
+    pub password_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SudoRule {
+    pub users: Vec<String>,
+    pub rule: String,
+    pub source: PathBuf,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SudoersFile {
+    pub rules: Vec<SudoRule>,
+    pub user_aliases: HashMap<String, Vec<String>>,
+    pub includes: Vec<PathBuf>,
+}
+
+/// Everything known about local accounts, files which cannot be read are left empty.
+pub struct AccountDatabase {
+    pub users: Vec<PasswdEntry>,
+    pub groups: Vec<GroupEntry>,
+    pub shadow: Vec<ShadowEntry>,
+    pub sudoers: SudoersFile,
+}
+
+impl AccountDatabase {
+    pub fn load() -> AccountDatabase {
+        AccountDatabase {
+            users: read_passwd(Path::new(PASSWD_PATH)),
+            groups: parse_group(&read_or_empty(Path::new(GROUP_PATH))),
+            shadow: parse_shadow(&read_or_empty(Path::new(SHADOW_PATH))),
+            sudoers: read_sudoers(Path::new(SUDOERS_PATH)),
+        }
+    }
+
+    /// Primary group first, then supplementary groups.
+    pub fn groups_of(&self, user: &PasswdEntry) -> Vec<&GroupEntry> {
+        let primary = self.groups.iter().filter(|group| group.gid == user.gid);
+        let supplementary = self.groups.iter()
+            .filter(|group| group.gid != user.gid && group.members.iter().any(|member| member == &user.name));
+        primary.chain(supplementary).collect()
+    }
+
+    pub fn shadow_of(&self, user: &PasswdEntry) -> Option<&ShadowEntry> {
+        self.shadow.iter().find(|entry| entry.name == user.name)
+    }
+
+    pub fn sudo_rules_of(&self, user: &PasswdEntry) -> Vec<&SudoRule> {
+        let groups = self.groups_of(user);
+        let applies = |entry: &str| {
+            entry == "ALL"
+                || entry == user.name
+                || entry == format!("#{}", user.uid)
+                || entry.strip_prefix('%').map(|group| groups.iter().any(|it| it.name == group)).unwrap_or(false)
+        };
+        self.sudoers.rules.iter()
+            .filter(|rule| rule.users.iter().any(|entry| {
+                applies(entry) || self.sudoers.user_aliases.get(entry)
+                    .map(|aliased| aliased.iter().any(|it| applies(it)))
+                    .unwrap_or(false)
+            }))
+            .collect()
+    }
+}
+
+fn read_or_empty(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|err| {
+        debug!("Cannot read {}: {}", path.display(), err);
+        String::new()
+    })
+}
+
+pub fn parse_group(content: &str) -> Vec<GroupEntry> {
+    content.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            Some(GroupEntry {
+                name: fields[0].to_string(),
+                gid: fields[2].parse().ok()?,
+                members: fields[3].split(',')
+                    .map(|it| it.trim().to_string())
+                    .filter(|it| !it.is_empty())
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+pub fn parse_shadow(content: &str) -> Vec<ShadowEntry> {
+    content.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            Some(ShadowEntry {
+                name: fields.next()?.to_string(),
+                password_hash: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Reads sudoers together with all files it includes.
+pub fn read_sudoers(path: &Path) -> SudoersFile {
+    let mut sudoers = SudoersFile::default();
+    let mut pending = vec![path.to_path_buf()];
+    let mut visited = HashSet::<PathBuf>::new();
+    while let Some(path) = pending.pop() {
+        if !visited.insert(path.clone()) {
+            continue;
+        }
+        let parsed = parse_sudoers(&read_or_empty(&path), &path);
+        sudoers.rules.extend(parsed.rules);
+        sudoers.user_aliases.extend(parsed.user_aliases);
+        pending.extend(parsed.includes);
+    }
+    sudoers
+}
+
+/// Parses sudoers(5) user specifications, user aliases and include directives.
+/// Defaults and other aliases are not needed for the IOC search and are skipped.
+pub fn parse_sudoers(content: &str, source: &Path) -> SudoersFile {
+    let mut sudoers = SudoersFile::default();
+    let source_dir = source.parent().unwrap_or(Path::new("/"));
+    for (line_number, line) in logical_lines(content) {
+        let line = line.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if line.is_empty() {
+            continue;
+        }
+        let mut tokens = line.splitn(2, ' ');
+        let keyword = tokens.next().unwrap_or_default();
+        let argument = tokens.next().unwrap_or_default().trim();
+        match keyword {
+            "#include" | "@include" => sudoers.includes.push(source_dir.join(argument)),
+            "#includedir" | "@includedir" => sudoers.includes.extend(include_dir(&source_dir.join(argument))),
+            _ if keyword.starts_with('#') && !is_uid_user(keyword) => {}
+            _ if keyword.starts_with("Defaults") => {}
+            "User_Alias" => {
+                for alias in argument.split(':') {
+                    let mut alias = alias.splitn(2, '=');
+                    let name = alias.next().unwrap_or_default().trim().to_string();
+                    let members = alias.next().unwrap_or_default()
+                        .split(',')
+                        .map(|it| it.trim().to_string())
+                        .filter(|it| !it.is_empty())
+                        .collect();
+                    sudoers.user_aliases.insert(name, members);
+                }
+            }
+            "Runas_Alias" | "Host_Alias" | "Cmnd_Alias" | "Cmd_Alias" => {}
+            _ => {
+                if !line.contains('=') {
+                    continue;
+                }
+                sudoers.rules.push(SudoRule {
+                    users: rule_users(&line),
+                    rule: line.clone(),
+                    source: source.to_path_buf(),
+                    line_number,
+                })
+            }
+        }
+    }
+    sudoers
+}
+
+/// `#1000 ALL=...` is a user specification by uid, not a comment.
+fn is_uid_user(keyword: &str) -> bool {
+    keyword.len() > 1 && keyword[1..].trim_end_matches(',').chars().all(|it| it.is_ascii_digit())
+}
+
+/// The user list ends at the first token which is not followed by a comma, e.g. `bob, %wheel ALL=...`.
+fn rule_users(line: &str) -> Vec<String> {
+    let mut users = Vec::<String>::new();
+    for token in line.split(' ') {
+        users.extend(token.split(',')
+            .map(|it| it.trim().trim_start_matches('!').to_string())
+            .filter(|it| !it.is_empty()));
+        if !token.ends_with(',') {
+            break;
+        }
+    }
+    users
+}
+
+/// Joins lines continued by a trailing backslash and strips comments, keeping include directives.
+fn logical_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::<(usize, String)>::new();
+    let mut current: Option<(usize, String)> = None;
+    for (i, line) in content.lines().enumerate() {
+        let line = strip_comment(line.trim()).trim_end();
+        let (start, mut text) = current.take().unwrap_or((i + 1, String::new()));
+        match line.strip_suffix('\\') {
+            Some(continued) => {
+                text.push_str(continued);
+                text.push(' ');
+                current = Some((start, text));
+            }
+            None => {
+                text.push_str(line);
+                lines.push((start, text));
+            }
+        }
+    }
+    if let Some(unfinished) = current {
+        lines.push(unfinished);
+    }
+    lines
+}
+
+/// A `#` starts a comment unless it is escaped, starts an include directive or is a uid like `#0`
+/// where a user or runas list item can start.
+fn strip_comment(line: &str) -> &str {
+    let start = if line.starts_with("#include") { 1 } else { 0 };
+    let bytes = line.as_bytes();
+    let comment = (start..bytes.len()).find(|&i| bytes[i] == b'#'
+        && (i == 0 || bytes[i - 1] != b'\\')
+        && !is_uid(bytes, i));
+    match comment {
+        Some(comment) => &line[..comment],
+        None => line,
+    }
+}
+
+fn is_uid(bytes: &[u8], i: usize) -> bool {
+    bytes.get(i + 1).map(|it| it.is_ascii_digit()).unwrap_or(false)
+        && bytes[..i].iter().rev()
+            .find(|it| !it.is_ascii_whitespace())
+            .map(|it| b"(,:=!%".contains(it))
+            .unwrap_or(true)
+}
+
+/// sudo skips files in include directories which end with `~` or contain a `.`.
+fn include_dir(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| entries.filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.file_name()
+                .map(|it| it.to_string_lossy())
+                .map(|it| !it.ends_with('~') && !it.contains('.'))
+                .unwrap_or(false))
+            .collect())
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::unix_accounts::{parse_passwd, parse_group, parse_shadow, parse_sudoers, AccountDatabase, SudoersFile};
+    use std::path::{Path, PathBuf};
+
+    const PASSWD: &str = "root:x:0:0:root:/root:/bin/bash\n\
+                          bob:x:1000:1000:Bob,,,:/home/bob:/bin/bash\n\
+                          toor:x:0:0::/var/tmp/.t:/bin/sh\n";
+    const GROUP: &str = "root:x:0:\nbob:x:1000:\nsudo:x:27:bob\n";
+    const SUDOERS: &str = "# User privilege specification\n\
+                           Defaults env_reset\n\
+                           User_Alias OPS = alice, %sudo\n\
+                           root ALL=(ALL:ALL) ALL # administrators\n\
+                           OPS ALL=(ALL) NOPASSWD: \\\n    /usr/bin/vim\n\
+                           #includedir /etc/sudoers.d # local rules\n";
+
+    #[test]
+    fn test_parse_passwd() {
+        let users = parse_passwd(PASSWD);
+        assert_eq!(users.len(), 3);
+        assert_eq!(users[2].name, "toor");
+        assert_eq!(users[2].uid, 0);
+        assert_eq!(users[2].home, PathBuf::from("/var/tmp/.t"));
+    }
+
+    #[test]
+    fn test_parse_sudoers() {
+        let sudoers = parse_sudoers(SUDOERS, Path::new("/etc/sudoers"));
+        assert_eq!(sudoers.rules.len(), 2);
+        assert_eq!(sudoers.rules[0].users, vec!["root".to_string()]);
+        assert_eq!(sudoers.rules[0].rule, "root ALL=(ALL:ALL) ALL");
+        assert_eq!(sudoers.rules[1].rule, "OPS ALL=(ALL) NOPASSWD: /usr/bin/vim");
+        assert_eq!(sudoers.rules[1].line_number, 5);
+        assert_eq!(sudoers.user_aliases["OPS"], vec!["alice".to_string(), "%sudo".to_string()]);
+        let by_uid = parse_sudoers("#1000 ALL=(#0) /usr/bin/id # by uid\n#include sudoers.local # local rules\n",
+                                   Path::new("/etc/sudoers"));
+        assert_eq!(by_uid.rules[0].rule, "#1000 ALL=(#0) /usr/bin/id");
+        assert_eq!(by_uid.includes, vec![PathBuf::from("/etc/sudoers.local")]);
+        let numbered = parse_sudoers("alice, #1001 ALL = (root, #0) ALL # 1st rule\n", Path::new("/etc/sudoers"));
+        assert_eq!(numbered.rules[0].rule, "alice, #1001 ALL = (root, #0) ALL");
+    }
+
+    #[test]
+    fn test_sudo_rules_of_user_by_group_alias() {
+        let database = AccountDatabase {
+            users: parse_passwd(PASSWD),
+            groups: parse_group(GROUP),
+            shadow: parse_shadow("root:*:18000:0:99999:7:::\ntoor::18000:0:99999:7:::\n"),
+            sudoers: parse_sudoers(SUDOERS, Path::new("/etc/sudoers")),
+        };
+        let bob = database.users[1].clone();
+        let groups: Vec<&str> = database.groups_of(&bob).iter().map(|it| it.name.as_str()).collect();
+        assert_eq!(groups, vec!["bob", "sudo"]);
+        let rules = database.sudo_rules_of(&bob);
+        assert_eq!(rules.len(), 1);
+        assert!(rules[0].rule.contains("NOPASSWD"));
+        assert_eq!(database.shadow_of(&database.users[2]).unwrap().password_hash, "");
+        assert!(AccountDatabase { sudoers: SudoersFile::default(), ..database }.sudo_rules_of(&bob).is_empty());
+    }
+}
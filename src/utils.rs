@@ -6,4 +6,19 @@
 //         $( map.insert($key, $val); )*
 //         map
 //    }}
-//}
\ No newline at end of file
+//}
+#[cfg(windows)]
+use chrono::TimeZone;
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+/// Last modification of the file, none if the file system does not record it.
+pub fn modified_time(path: &Path) -> Option<DateTime<Utc>> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok().map(DateTime::<Utc>::from)
+}
+
+/// Time given in seconds since the UNIX epoch.
+#[cfg(windows)]
+pub fn epoch_time(seconds: u64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(seconds as i64, 0).single()
+}